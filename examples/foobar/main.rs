@@ -15,22 +15,6 @@ use winit::{
     window::{Window, WindowId},
 };
 
-#[allow(dead_code)]
-struct Image<Data: AsRef<[u8]>> {
-    format: TextureFormat,
-    size: Vector2<u32>,
-    data: Data,
-}
-
-#[allow(dead_code)]
-fn test_image() -> Image<impl AsRef<[u8]>> {
-    Image {
-        format: TextureFormat::Rgba8Unorm,
-        size: vec2(256, 256),
-        data: include_bytes!("./image.bin"),
-    }
-}
-
 const CUBE_VERTICES: [Vertex3dUV; 24] = [
     // South (+Z)
     Vertex3dUV::new([0., 0., 1.], [0.0, 1.0]),
@@ -95,7 +79,7 @@ impl State {
 
         let window_surface = WindowSurface::new(Arc::clone(&window), &instance, &adapter, &device);
 
-        let context = Context::new(device, queue);
+        let context = Context::new(&adapter, device, queue);
 
         let mut scene = Scene::new(
             context.wgpu_device(),
@@ -121,9 +105,12 @@ impl State {
         let cube_0 = context.create_object(&scene, camera.clone(), cube_0_mesh, cube_0_material);
         scene.add_object(cube_0.clone());
 
-        let image = test_image();
-        let texture =
-            Texture2d::create_init(&context, image.size, image.format, image.data.as_ref());
+        let texture = Texture2d::from_image_bytes(
+            &context,
+            include_bytes!("./image.png"),
+            ColorSpace::Linear,
+        )
+        .unwrap();
         let cube_1_material = context.create_material(&{
             materials::Textured::create(
                 texture.view(Default::default()),
@@ -210,7 +197,8 @@ impl State {
             camera.position.z = (f64::sin(t) as f32) * 400.0;
         });
         self.window_surface.frame(|surface| {
-            self.scene.render(&self.context, &surface);
+            self.scene
+                .render(&self.context, &surface, &RenderPassOptions::default());
         });
     }
 }
@@ -0,0 +1,181 @@
+use cgmath::*;
+
+/// An axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    pub const fn new(min: Point3<f32>, max: Point3<f32>) -> Self {
+        Self { min, max }
+    }
+
+    /// The smallest box containing all of the points, `None` if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = Point3<f32>>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Self::new(first, first), |aabb, point| {
+            Self::new(
+                point3(
+                    aabb.min.x.min(point.x),
+                    aabb.min.y.min(point.y),
+                    aabb.min.z.min(point.z),
+                ),
+                point3(
+                    aabb.max.x.max(point.x),
+                    aabb.max.y.max(point.y),
+                    aabb.max.z.max(point.z),
+                ),
+            )
+        }))
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.min.midpoint(self.max)
+    }
+
+    pub fn corners(&self) -> [Point3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            point3(min.x, min.y, min.z),
+            point3(max.x, min.y, min.z),
+            point3(min.x, max.y, min.z),
+            point3(max.x, max.y, min.z),
+            point3(min.x, min.y, max.z),
+            point3(max.x, min.y, max.z),
+            point3(min.x, max.y, max.z),
+            point3(max.x, max.y, max.z),
+        ]
+    }
+
+    /// The smallest box containing this box after it is transformed by `matrix`.
+    pub fn transform(&self, matrix: Matrix4<f32>) -> Self {
+        let corners = self.corners().map(|corner| matrix.transform_point(corner));
+        Self::from_points(corners).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Point3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub const fn new(center: Point3<f32>, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// A sphere containing all of the points, centered at the center of their bounding box.
+    /// `None` if there are no points.
+    pub fn from_points(points: impl IntoIterator<Item = Point3<f32>> + Clone) -> Option<Self> {
+        let center = Aabb::from_points(points.clone())?.center();
+        let radius = points
+            .into_iter()
+            .map(|point| point.distance(center))
+            .fold(0.0, f32::max);
+        Some(Self::new(center, radius))
+    }
+
+    /// A sphere containing this sphere after it is transformed by `matrix`, which is assumed to
+    /// be affine. Non-uniform scales grow the radius by the largest scale.
+    pub fn transform(&self, matrix: Matrix4<f32>) -> Self {
+        let scale = [matrix.x, matrix.y, matrix.z]
+            .map(|column| column.truncate().magnitude())
+            .into_iter()
+            .fold(0.0, f32::max);
+        Self::new(matrix.transform_point(self.center), self.radius * scale)
+    }
+}
+
+/// Bounding volumes of a mesh in model space, for frustum culling. See `AsMesh::bounds`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub aabb: Aabb,
+    pub sphere: BoundingSphere,
+}
+
+impl Bounds {
+    /// `None` if there are no points.
+    pub fn from_points(points: impl IntoIterator<Item = Point3<f32>> + Clone) -> Option<Self> {
+        Some(Self {
+            aabb: Aabb::from_points(points.clone())?,
+            sphere: BoundingSphere::from_points(points)?,
+        })
+    }
+
+    pub fn transform(&self, matrix: Matrix4<f32>) -> Self {
+        Self {
+            aabb: self.aabb.transform(matrix),
+            sphere: self.sphere.transform(matrix),
+        }
+    }
+}
+
+/// The volume visible to a camera, as six planes facing inwards. See `Camera::frustum`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near and far planes. A point `p` is on the inner side of a plane
+    /// if `plane.x * p.x + plane.y * p.y + plane.z * p.z + plane.w >= 0`. The normals `plane.xyz`
+    /// have unit length, so that this is the distance of the point to the plane.
+    pub planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a view-projection matrix, which maps the frustum into clip space
+    /// with depths from -1 to 1, like the projection matrices of cgmath.
+    pub fn from_matrix(view_projection: Matrix4<f32>) -> Self {
+        let rows = view_projection.transpose();
+        let planes = [
+            rows.w + rows.x,
+            rows.w - rows.x,
+            rows.w + rows.y,
+            rows.w - rows.y,
+            rows.w + rows.z,
+            rows.w - rows.z,
+        ];
+        Self {
+            planes: planes.map(|plane| plane / plane.truncate().magnitude()),
+        }
+    }
+
+    fn distance(plane: Vector4<f32>, point: Point3<f32>) -> f32 {
+        plane.truncate().dot(point.to_vec()) + plane.w
+    }
+
+    pub fn contains_point(&self, point: Point3<f32>) -> bool {
+        self.planes
+            .iter()
+            .all(|&plane| Self::distance(plane, point) >= 0.0)
+    }
+
+    /// Whether the sphere is at least partly inside. May also be true for spheres just outside
+    /// the corners of the frustum.
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes
+            .iter()
+            .all(|&plane| Self::distance(plane, sphere.center) >= -sphere.radius)
+    }
+
+    /// Whether the box is at least partly inside. May also be true for boxes just outside the
+    /// corners of the frustum.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|&plane| {
+            // The corner furthest along the normal of the plane.
+            let furthest = |normal: f32, min: f32, max: f32| if normal >= 0.0 { max } else { min };
+            let corner = point3(
+                furthest(plane.x, aabb.min.x, aabb.max.x),
+                furthest(plane.y, aabb.min.y, aabb.max.y),
+                furthest(plane.z, aabb.min.z, aabb.max.z),
+            );
+            Self::distance(plane, corner) >= 0.0
+        })
+    }
+
+    /// Whether both bounding volumes are at least partly inside.
+    pub fn intersects(&self, bounds: &Bounds) -> bool {
+        self.intersects_sphere(&bounds.sphere) && self.intersects_aabb(&bounds.aabb)
+    }
+}
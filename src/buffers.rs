@@ -150,6 +150,55 @@ impl Vertex for Vertex3dUV {
     };
 }
 
+/// Vertex with a tangent frame, for meshes whose materials need normals or normal mapping.
+///
+/// `tangent.w` is the sign of the bitangent, i.e. `bitangent = cross(normal, tangent.xyz) *
+/// tangent.w`. See `compute_smooth_normals`, `compute_flat_normals` and `compute_tangents`.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct Vertex3dNormalTangentUV {
+    pub position: [f32; 3],
+    pub uv: [f32; 2],
+    pub normal: [f32; 3],
+    pub tangent: [f32; 4],
+}
+
+impl Vertex3dNormalTangentUV {
+    pub const fn new(
+        position: [f32; 3],
+        uv: [f32; 2],
+        normal: [f32; 3],
+        tangent: [f32; 4],
+    ) -> Self {
+        Self {
+            position,
+            uv,
+            normal,
+            tangent,
+        }
+    }
+}
+
+/// Normal and tangent are left as zero.
+impl From<Vertex3dUV> for Vertex3dNormalTangentUV {
+    fn from(value: Vertex3dUV) -> Self {
+        Self::new(value.position, value.uv, [0.0; 3], [0.0; 4])
+    }
+}
+
+impl Vertex for Vertex3dNormalTangentUV {
+    const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: mem::size_of::<Self>() as u64,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![
+            0 => Float32x3,
+            1 => Float32x2,
+            2 => Float32x3,
+            3 => Float32x4,
+        ],
+    };
+}
+
 pub trait Index: Pod + Copy {
     const FORMAT: wgpu::IndexFormat;
 }
@@ -250,16 +299,113 @@ impl<T: Pod + Copy> Bindable for UniformBuffer<T> {
             ty: wgpu::BindingType::Buffer {
                 ty: wgpu::BufferBindingType::Uniform,
                 has_dynamic_offset: false,
-                min_binding_size: None,
+                min_binding_size: wgpu::BufferSize::new(mem::size_of::<T>() as u64),
             },
             count: None,
         }
     }
 
-    fn bind_group_entry(&self, binding: u32) -> wgpu::BindGroupEntry {
+    fn bind_group_entry(&self, binding: u32) -> wgpu::BindGroupEntry<'_> {
         wgpu::BindGroupEntry {
             binding,
             resource: self.wgpu_buffer().as_entire_binding(),
         }
     }
 }
+
+/// A uniform buffer holding an array of `T`s, each bound one at a time through a dynamic offset.
+///
+/// Every element occupies a slot aligned to the device's `min_uniform_buffer_offset_alignment`.
+#[derive(Debug, Clone)]
+pub struct DynamicUniformBuffer<T: Pod + Copy> {
+    wgpu_buffer: wgpu::Buffer,
+    stride: u64,
+    capacity: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Pod + Copy> DynamicUniformBuffer<T> {
+    pub fn create(device: &wgpu::Device, capacity: usize) -> Self {
+        let alignment = u64::from(device.limits().min_uniform_buffer_offset_alignment);
+        let stride = (mem::size_of::<T>() as u64).next_multiple_of(alignment);
+        let capacity = capacity.max(1);
+        let wgpu_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: stride * capacity as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            wgpu_buffer,
+            stride,
+            capacity,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn wgpu_buffer(&self) -> &wgpu::Buffer {
+        &self.wgpu_buffer
+    }
+
+    /// Number of elements that fits in the buffer.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Distance in bytes between two consecutive elements.
+    pub fn stride(&self) -> u64 {
+        self.stride
+    }
+
+    /// The dynamic offset for binding the element at `index`.
+    pub fn offset(&self, index: usize) -> wgpu::DynamicOffset {
+        (self.stride * index as u64).try_into().unwrap()
+    }
+
+    /// Writes `contents` to the start of the buffer.
+    ///
+    /// If `contents` doesn't fit, the buffer is re-created with a larger capacity and `true` is
+    /// returned, in which case bind groups referencing the old buffer must be re-created.
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, contents: &[T]) -> bool {
+        let reallocated = contents.len() > self.capacity;
+        if reallocated {
+            *self = Self::create(device, contents.len().next_power_of_two());
+        }
+        if contents.is_empty() {
+            return reallocated;
+        }
+        let stride = self.stride as usize;
+        let mut bytes = vec![0u8; stride * contents.len()];
+        for (chunk, element) in bytes.chunks_exact_mut(stride).zip(contents) {
+            chunk[..mem::size_of::<T>()].copy_from_slice(bytemuck::bytes_of(element));
+        }
+        queue.write_buffer(self.wgpu_buffer(), 0, &bytes);
+        reallocated
+    }
+}
+
+impl<T: Pod + Copy> Bindable for DynamicUniformBuffer<T> {
+    fn bind_group_layout_entry(&self, binding: u32) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::all(),
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: true,
+                min_binding_size: wgpu::BufferSize::new(mem::size_of::<T>() as u64),
+            },
+            count: None,
+        }
+    }
+
+    fn bind_group_entry(&self, binding: u32) -> wgpu::BindGroupEntry<'_> {
+        wgpu::BindGroupEntry {
+            binding,
+            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer: self.wgpu_buffer(),
+                offset: 0,
+                size: wgpu::BufferSize::new(mem::size_of::<T>() as u64),
+            }),
+        }
+    }
+}
@@ -1,6 +1,7 @@
+use bytemuck::{Pod, Zeroable};
 use cgmath::*;
 
-use crate::{UniformBuffer, impl_as_bind_group};
+use crate::{AlphaMode, AsBindGroup, DynamicUniformBuffer, Frustum};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CameraDirection {
@@ -69,23 +70,103 @@ impl Camera {
             }
         }
     }
+
+    /// The volume in world space that the camera sees, extracted from
+    /// `projection_matrix * view_matrix`.
+    pub fn frustum(&self, viewport_size: Vector2<f32>) -> Frustum {
+        Frustum::from_matrix(self.projection_matrix(viewport_size) * self.view_matrix())
+    }
 }
 
-#[derive(Debug, Clone)]
+/// Per-draw transform data, bound to `@group(0) @binding(0)` of mesh vertex shaders.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct CameraUniform {
+    pub projection: [[f32; 4]; 4],
+    pub model_view: [[f32; 4]; 4],
+    /// Inverse transpose of `model_view`, for transforming normals into view space.
+    pub normal: [[f32; 4]; 4],
+    /// For transforming world space (e.g. positions of lights) into view space.
+    pub view: [[f32; 4]; 4],
+    /// For transforming view space back into world space (e.g. for sampling shadow maps).
+    pub inverse_view: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    pub fn new(projection: Matrix4<f32>, view: Matrix4<f32>, model: Matrix4<f32>) -> Self {
+        let model_view = view * model;
+        let normal = model_view
+            .invert()
+            .map_or_else(Matrix4::identity, |inverse| inverse.transpose());
+        Self {
+            projection: projection.into(),
+            model_view: model_view.into(),
+            normal: normal.into(),
+            view: view.into(),
+            inverse_view: view.invert().unwrap_or_else(Matrix4::identity).into(),
+        }
+    }
+}
+
+/// Per-draw object data that doesn't depend on the camera, bound to `@group(0) @binding(1)`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct ObjectUniform {
+    /// 0 if the object doesn't receive shadows.
+    pub receives_shadows: u32,
+    /// Cutoff of `AlphaMode::Mask` materials, 0 for other alpha modes.
+    pub alpha_cutoff: f32,
+    pub _padding: [u32; 2],
+}
+
+impl ObjectUniform {
+    pub fn new(receives_shadows: bool, alpha_mode: AlphaMode) -> Self {
+        let alpha_cutoff = match alpha_mode {
+            AlphaMode::Mask { cutoff } => cutoff,
+            AlphaMode::Opaque | AlphaMode::Blend => 0.0,
+        };
+        Self {
+            receives_shadows: receives_shadows.into(),
+            alpha_cutoff,
+            _padding: [0; 2],
+        }
+    }
+}
+
+#[derive(Debug, Clone, AsBindGroup)]
 pub struct CameraBindGroup {
-    pub projection: UniformBuffer<[[f32; 4]; 4]>,
+    /// One `CameraUniform` for each object drawn in a render pass, selected by dynamic offset.
+    #[binding(0, vertex, fragment)]
+    pub uniforms: DynamicUniformBuffer<CameraUniform>,
+    /// One `ObjectUniform` for each object, at the same index as its `CameraUniform`.
+    #[binding(1, fragment)]
+    pub objects: DynamicUniformBuffer<ObjectUniform>,
 }
 
 impl CameraBindGroup {
     pub fn create(device: &wgpu::Device) -> Self {
         Self {
-            projection: UniformBuffer::create_init(device, Matrix4::identity().into()),
+            uniforms: DynamicUniformBuffer::create(device, 64),
+            objects: DynamicUniformBuffer::create(device, 64),
         }
     }
-}
 
-impl_as_bind_group! {
-    CameraBindGroup {
-        0 => projection,
+    /// Writes the uniforms of the objects in the order they are drawn in. Returns whether a
+    /// buffer was re-created, see `DynamicUniformBuffer::write`.
+    pub fn write(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        uniforms: &[CameraUniform],
+        objects: &[ObjectUniform],
+    ) -> bool {
+        let uniforms_reallocated = self.uniforms.write(device, queue, uniforms);
+        let objects_reallocated = self.objects.write(device, queue, objects);
+        uniforms_reallocated || objects_reallocated
+    }
+
+    /// The dynamic offsets for binding the uniforms of the object at `index`.
+    pub fn offsets(&self, index: usize) -> [wgpu::DynamicOffset; 2] {
+        [self.uniforms.offset(index), self.objects.offset(index)]
     }
 }
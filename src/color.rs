@@ -72,3 +72,13 @@ impl From<Rgba> for (f32, f32, f32, f32) {
     }
 }
 
+impl From<Rgba> for wgpu::Color {
+    fn from(value: Rgba) -> Self {
+        Self {
+            r: value.r.into(),
+            g: value.g.into(),
+            b: value.b.into(),
+            a: value.a.into(),
+        }
+    }
+}
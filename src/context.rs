@@ -1,29 +1,59 @@
 use std::{
+    error::Error,
+    fmt,
     ops::DerefMut,
     sync::{
-        Arc, Mutex,
+        Arc, Mutex, OnceLock,
         atomic::{self, AtomicU64},
     },
 };
 
-use crate::{AsMaterial, AsMesh, Camera, MaterialStorage, MeshStorage, ObjectStorage, Scene};
+use crate::{
+    AsMaterial, AsMesh, Camera, Instance, InstanceBuffer, InstanceId, Light, LightStorage,
+    MaterialStorage, MeshStorage, MipmapGenerator, ObjectStorage, RenderPipelineCache, Scene,
+    ShaderValidationError, Texture2d, environment_map,
+};
 
 #[derive(Debug)]
 pub struct Context {
+    adapter_info: wgpu::AdapterInfo,
     wgpu_device: wgpu::Device,
     wgpu_queue: wgpu::Queue,
     object_id_counter: AtomicU64,
+    pipeline_cache: RenderPipelineCache,
+    mipmap_generator: MipmapGenerator,
+    brdf_lut: OnceLock<Texture2d>,
 }
 
 impl Context {
-    pub fn new(wgpu_device: wgpu::Device, wgpu_queue: wgpu::Queue) -> Self {
+    /// Creates a context for a device and its queue, requested from `adapter`.
+    pub fn new(
+        adapter: &wgpu::Adapter,
+        wgpu_device: wgpu::Device,
+        wgpu_queue: wgpu::Queue,
+    ) -> Self {
         Self {
+            adapter_info: adapter.get_info(),
             wgpu_device,
             wgpu_queue,
             object_id_counter: AtomicU64::new(0),
+            pipeline_cache: RenderPipelineCache::default(),
+            mipmap_generator: MipmapGenerator::default(),
+            brdf_lut: OnceLock::new(),
         }
     }
 
+    /// Returns a builder for creating a `Context` that picks its own adapter and device, without
+    /// needing a window.
+    pub fn builder() -> ContextBuilder {
+        ContextBuilder::default()
+    }
+
+    /// Information about the adapter of the device, e.g. its backend.
+    pub fn adapter_info(&self) -> &wgpu::AdapterInfo {
+        &self.adapter_info
+    }
+
     pub fn wgpu_device(&self) -> &wgpu::Device {
         &self.wgpu_device
     }
@@ -32,18 +62,58 @@ impl Context {
         &self.wgpu_queue
     }
 
+    pub(crate) fn pipeline_cache(&self) -> &RenderPipelineCache {
+        &self.pipeline_cache
+    }
+
+    pub(crate) fn mipmap_generator(&self) -> &MipmapGenerator {
+        &self.mipmap_generator
+    }
+
+    /// The split-sum BRDF lookup texture shared by every `Environment`, rendered on first use. It
+    /// is a 128x128 `Rg16Float` texture of the scale (red) and bias (green) of the specular
+    /// reflectance at normal incidence, by the cosine of the view angle from left to right and by
+    /// roughness from top to bottom. It can be read back for inspection.
+    pub fn brdf_lut(&self) -> &Texture2d {
+        self.brdf_lut
+            .get_or_init(|| environment_map::create_brdf_lut(self))
+    }
+
+    /// Number of distinct render pipelines created for objects so far.
+    /// Objects with the same mesh type, material type and surface formats share one pipeline.
+    /// Depth-only pipelines for shadow maps aren't counted.
+    pub fn render_pipeline_count(&self) -> usize {
+        self.pipeline_cache.render_pipeline_count()
+    }
+
+    /// Watches the files of shaders for changes during development. Shaders from an
+    /// `include_shader!` or a `ShaderSource` with a path are then recompiled from their file when
+    /// it changes, and the pipelines of existing objects are rebuilt in place, at the start of the
+    /// next `Scene::render`.
+    ///
+    /// The engine's shader modules are watched as well if its source can be found, and are read
+    /// from their files by `#import` from then on. Shaders importing a module that changed are
+    /// preprocessed and recompiled again, including shaders without a file of their own.
+    ///
+    /// A shader that fails to compile is logged, and objects keep using its previous version.
+    /// Meshes and materials that implement `create_vertex_shader` or `create_fragment_shader`
+    /// instead of providing WGSL source aren't reloaded.
+    pub fn enable_shader_hot_reload(&self) -> Result<(), notify::Error> {
+        self.pipeline_cache.enable_hot_reload()
+    }
+
     fn increment_object_id_counter(&self) -> u64 {
         self.object_id_counter
             .fetch_add(1, atomic::Ordering::Relaxed)
     }
 
     pub fn create_mesh(&self, mesh_instance: Arc<impl AsMesh>) -> MeshRef {
-        let mesh_storage = MeshStorage::new(self.wgpu_device(), mesh_instance);
+        let mesh_storage = MeshStorage::new(self, mesh_instance);
         MeshRef::new(mesh_storage)
     }
 
     pub fn create_material(&self, material_instance: &impl AsMaterial) -> MaterialRef {
-        let material_storage = MaterialStorage::new(self.wgpu_device(), material_instance);
+        let material_storage = MaterialStorage::new(self, material_instance);
         MaterialRef::new(material_storage)
     }
 
@@ -51,6 +121,19 @@ impl Context {
         CameraRef::new(camera_instance)
     }
 
+    pub fn create_light(&self, light: impl Into<Light>) -> LightRef {
+        let light_storage = LightStorage {
+            id: self.increment_object_id_counter(),
+            light: light.into(),
+            is_hidden: false,
+            casts_shadows: false,
+        };
+        LightRef::new(light_storage)
+    }
+
+    /// # Panics
+    ///
+    /// - if the shaders don't match the mesh, material or scene, see `try_create_object`
     pub fn create_object(
         &self,
         scene: &Scene,
@@ -58,10 +141,171 @@ impl Context {
         mesh: MeshRef,
         material: MaterialRef,
     ) -> ObjectRef {
+        self.try_create_object(scene, camera, mesh, material)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Like `create_object`, but returns an error instead of panicking if the WGSL source of the
+    /// mesh's vertex shader or the material's fragment shader doesn't preprocess or compile, or if
+    /// its declarations don't match the bind groups or vertex buffer layout of the mesh, the
+    /// material and the scene.
+    ///
+    /// Shaders are only checked for the first object of every pipeline, and only if the mesh and
+    /// material provide their WGSL source.
+    pub fn try_create_object(
+        &self,
+        scene: &Scene,
+        camera: CameraRef,
+        mesh: MeshRef,
+        material: MaterialRef,
+    ) -> Result<ObjectRef, ShaderValidationError> {
+        let id = self.increment_object_id_counter();
+        let object_storage = ObjectStorage::new(scene, id, self, camera, mesh, material, false)?;
+        Ok(ObjectRef::new(object_storage))
+    }
+
+    /// Creates an object that draws its mesh once for every instance added to it, in a single
+    /// draw call. See `Instance`.
+    ///
+    /// # Panics
+    ///
+    /// - if the mesh doesn't support instancing (see `AsMesh::instanced_vertex_shader_source`)
+    /// - if the shaders don't match the mesh, material or scene, see `try_create_object`
+    pub fn create_instanced_object(
+        &self,
+        scene: &Scene,
+        camera: CameraRef,
+        mesh: MeshRef,
+        material: MaterialRef,
+    ) -> ObjectRef {
+        self.try_create_instanced_object(scene, camera, mesh, material)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Like `create_instanced_object`, but returns an error if the shaders don't match the mesh,
+    /// material or scene, see `try_create_object`.
+    ///
+    /// # Panics
+    ///
+    /// - if the mesh doesn't support instancing (see `AsMesh::instanced_vertex_shader_source`)
+    pub fn try_create_instanced_object(
+        &self,
+        scene: &Scene,
+        camera: CameraRef,
+        mesh: MeshRef,
+        material: MaterialRef,
+    ) -> Result<ObjectRef, ShaderValidationError> {
         let id = self.increment_object_id_counter();
-        let object_storage =
-            ObjectStorage::new(scene, id, self.wgpu_device(), camera, mesh, material);
-        ObjectRef::new(object_storage)
+        let object_storage = ObjectStorage::new(scene, id, self, camera, mesh, material, true)?;
+        Ok(ObjectRef::new(object_storage))
+    }
+}
+
+/// Builder for a headless `Context`. See `Context::builder`.
+#[derive(Debug, Clone)]
+pub struct ContextBuilder {
+    backends: wgpu::Backends,
+    power_preference: wgpu::PowerPreference,
+    force_fallback_adapter: bool,
+    required_features: wgpu::Features,
+    required_limits: wgpu::Limits,
+}
+
+impl Default for ContextBuilder {
+    fn default() -> Self {
+        Self {
+            backends: wgpu::Backends::all(),
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::default(),
+        }
+    }
+}
+
+impl ContextBuilder {
+    /// The backends to look for adapters in. Defaults to all backends.
+    pub fn backends(mut self, backends: wgpu::Backends) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    pub fn power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    /// Only accept a fallback (software) adapter.
+    pub fn force_fallback_adapter(mut self, force_fallback_adapter: bool) -> Self {
+        self.force_fallback_adapter = force_fallback_adapter;
+        self
+    }
+
+    pub fn required_features(mut self, required_features: wgpu::Features) -> Self {
+        self.required_features = required_features;
+        self
+    }
+
+    pub fn required_limits(mut self, required_limits: wgpu::Limits) -> Self {
+        self.required_limits = required_limits;
+        self
+    }
+
+    pub async fn build_async(self) -> Result<Context, CreateContextError> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: self.backends,
+            ..Default::default()
+        });
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: self.power_preference,
+                force_fallback_adapter: self.force_fallback_adapter,
+                compatible_surface: None,
+            })
+            .await
+            .map_err(CreateContextError::RequestAdapter)?;
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: None,
+                required_features: self.required_features,
+                required_limits: self.required_limits,
+                memory_hints: Default::default(),
+                trace: Default::default(),
+            })
+            .await
+            .map_err(CreateContextError::RequestDevice)?;
+        Ok(Context::new(&adapter, device, queue))
+    }
+
+    /// Blocking version of `build_async`.
+    pub fn build(self) -> Result<Context, CreateContextError> {
+        pollster::block_on(self.build_async())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum CreateContextError {
+    /// No adapter satisfied the requested options.
+    RequestAdapter(wgpu::RequestAdapterError),
+    /// The adapter could not provide a device with the required features and limits.
+    RequestDevice(wgpu::RequestDeviceError),
+}
+
+impl fmt::Display for CreateContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RequestAdapter(error) => write!(f, "unable to request adapter: {error}"),
+            Self::RequestDevice(error) => write!(f, "unable to request device: {error}"),
+        }
+    }
+}
+
+impl Error for CreateContextError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::RequestAdapter(error) => Some(error),
+            Self::RequestDevice(error) => Some(error),
+        }
     }
 }
 
@@ -91,6 +335,7 @@ define_ref_type!(MeshRef, MeshStorage);
 define_ref_type!(MaterialRef, MaterialStorage);
 define_ref_type!(ObjectRef, ObjectStorage);
 define_ref_type!(CameraRef, Camera);
+define_ref_type!(LightRef, LightStorage);
 
 impl ObjectRef {
     pub fn set_is_hidden(&self, is_hidden: bool) {
@@ -100,6 +345,110 @@ impl ObjectRef {
     pub fn get_is_hidden(&self) -> bool {
         self.lock().is_hidden
     }
+
+    /// Whether the object is drawn into the shadow maps of lights, defaults to `true`. Objects
+    /// with `AlphaMode::Blend` materials never cast shadows.
+    pub fn set_casts_shadows(&self, casts_shadows: bool) {
+        self.lock().casts_shadows = casts_shadows;
+    }
+
+    pub fn get_casts_shadows(&self) -> bool {
+        self.lock().casts_shadows
+    }
+
+    /// Whether shadows are cast onto the object, defaults to `true`.
+    /// Only has an effect on lit materials.
+    pub fn set_receives_shadows(&self, receives_shadows: bool) {
+        self.lock().receives_shadows = receives_shadows;
+    }
+
+    pub fn get_receives_shadows(&self) -> bool {
+        self.lock().receives_shadows
+    }
+
+    pub fn is_instanced(&self) -> bool {
+        self.lock().instances.is_some()
+    }
+
+    /// # Panics
+    ///
+    /// - if object isn't instanced
+    pub fn add_instance(&self, instance: Instance) -> InstanceId {
+        self.with_instances(|instances| instances.add(instance))
+    }
+
+    /// Removes an instance, returning its data.
+    ///
+    /// # Panics
+    ///
+    /// - if object isn't instanced
+    /// - if instance doesn't exist
+    pub fn remove_instance(&self, instance_id: InstanceId) -> Instance {
+        self.with_instances(|instances| instances.remove(instance_id))
+    }
+
+    /// # Panics
+    ///
+    /// - if object isn't instanced
+    /// - if instance doesn't exist
+    pub fn update_instance(&self, instance_id: InstanceId, instance: Instance) {
+        self.with_instances(|instances| instances.update(instance_id, instance))
+    }
+
+    /// Returns `None` if the instance doesn't exist.
+    ///
+    /// # Panics
+    ///
+    /// - if object isn't instanced
+    pub fn get_instance(&self, instance_id: InstanceId) -> Option<Instance> {
+        self.with_instances(|instances| instances.get(instance_id))
+    }
+
+    /// # Panics
+    ///
+    /// - if object isn't instanced
+    pub fn instance_count(&self) -> u32 {
+        self.with_instances(|instances| instances.len())
+    }
+
+    #[track_caller]
+    fn with_instances<T>(&self, f: impl FnOnce(&mut InstanceBuffer) -> T) -> T {
+        let mut object = self.lock();
+        let instances = object
+            .instances
+            .as_mut()
+            .unwrap_or_else(|| panic!("object isn't instanced"));
+        f(instances)
+    }
+}
+
+impl LightRef {
+    pub fn get(&self) -> Light {
+        self.lock().light
+    }
+
+    /// Replaces the light, e.g. to move it. Takes effect from the next render.
+    pub fn set(&self, light: impl Into<Light>) {
+        self.lock().light = light.into();
+    }
+
+    pub fn set_is_hidden(&self, is_hidden: bool) {
+        self.lock().is_hidden = is_hidden;
+    }
+
+    pub fn get_is_hidden(&self) -> bool {
+        self.lock().is_hidden
+    }
+
+    /// Whether the light casts shadows, defaults to `false`.
+    /// Only directional and spot lights cast shadows, see `MAX_SHADOW_MAPS`.
+    pub fn set_casts_shadows(&self, casts_shadows: bool) {
+        self.lock().casts_shadows = casts_shadows;
+    }
+
+    pub fn get_casts_shadows(&self) -> bool {
+        self.lock().casts_shadows
+    }
 }
 
 impl CameraRef {
@@ -0,0 +1,424 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::*;
+
+use crate::{
+    AsBindGroup, Context, Sampler, SamplerOptions, Texture2d, TextureCube, TextureFormat,
+    TextureView2d, TextureViewCube, UniformBuffer, binding, create_wgsl_shader_module,
+    preprocess_wgsl,
+};
+
+/// Width and height of the BRDF lookup texture of a `Context`.
+pub(crate) const BRDF_LUT_SIZE: u32 = 128;
+
+/// Bind group of the shader converting one face of a cube map.
+#[derive(Debug, Clone, AsBindGroup)]
+struct EquirectangularBindGroup {
+    #[binding(0, fragment)]
+    equirectangular: TextureView2d,
+    #[binding(1, fragment)]
+    sampler: Sampler,
+    #[binding(2, fragment)]
+    face: UniformBuffer<u32>,
+}
+
+/// Matches `struct Convolution` in `ibl.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+struct ConvolutionUniform {
+    face: u32,
+    roughness: f32,
+    _padding: [u32; 2],
+}
+
+/// Bind group of the shader convolving one face of an environment cube map.
+#[derive(Debug, Clone, AsBindGroup)]
+struct ConvolutionBindGroup {
+    #[binding(0, fragment)]
+    environment: TextureViewCube,
+    #[binding(1, fragment)]
+    sampler: Sampler,
+    #[binding(2, fragment)]
+    convolution: UniformBuffer<ConvolutionUniform>,
+}
+
+fn clamping_sampler(context: &Context) -> Sampler {
+    Sampler::create_with_options(
+        context,
+        &SamplerOptions {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        },
+    )
+}
+
+fn create_ibl_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
+    let source = preprocess_wgsl(include_str!("./shaders/ibl.wgsl"), &[])
+        .expect("the image-based lighting shader is valid");
+    create_wgsl_shader_module(device, "image-based lighting", &source)
+}
+
+/// A pipeline drawing a triangle that covers the whole target, with `vs_main` of `shader`.
+fn create_fullscreen_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    shader: &wgpu::ShaderModule,
+    fragment_entry_point: &str,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(label),
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            compilation_options: Default::default(),
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some(fragment_entry_point),
+            compilation_options: Default::default(),
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+fn draw_fullscreen(
+    encoder: &mut wgpu::CommandEncoder,
+    label: &str,
+    target: &TextureView2d,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: Option<&wgpu::BindGroup>,
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target.wgpu_texture_view(),
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+    render_pass.set_pipeline(pipeline);
+    if let Some(bind_group) = bind_group {
+        render_pass.set_bind_group(0, bind_group, &[]);
+    }
+    render_pass.draw(0..3, 0..1);
+}
+
+/// Renders every face of every mip level of `target` with `fragment_entry_point` of `shader`, and
+/// the bind group returned by `bind_group(face, mip_level)`.
+fn render_cube_faces<BindGroup: AsBindGroup>(
+    context: &Context,
+    label: &str,
+    shader: &wgpu::ShaderModule,
+    fragment_entry_point: &str,
+    target: &TextureCube,
+    bind_group: impl Fn(u32, u32) -> BindGroup,
+) {
+    let device = context.wgpu_device();
+    let bind_groups: Vec<(u32, u32, BindGroup)> = (0..target.mip_level_count())
+        .flat_map(|mip_level| (0..6).map(move |face| (face, mip_level)))
+        .map(|(face, mip_level)| (face, mip_level, bind_group(face, mip_level)))
+        .collect();
+    let bind_group_layout = binding::create_wgpu_bind_group_layout(device, &bind_groups[0].2);
+    let pipeline = create_fullscreen_pipeline(
+        device,
+        label,
+        shader,
+        fragment_entry_point,
+        &[&bind_group_layout],
+        target.wgpu_format(),
+    );
+    let mut encoder = device.create_command_encoder(&Default::default());
+    for (face, mip_level, bind_group) in &bind_groups {
+        let wgpu_bind_group =
+            binding::create_wgpu_bind_group_with_layout(device, bind_group, &bind_group_layout);
+        let view = target.face_view(Default::default(), *face, *mip_level);
+        draw_fullscreen(
+            &mut encoder,
+            label,
+            &view,
+            &pipeline,
+            Some(&wgpu_bind_group),
+        );
+    }
+    context.wgpu_queue().submit([encoder.finish()]);
+}
+
+/// Copies the first mip level of `environment` into a `Rgba16Float` cube map of the same size with
+/// all mip levels, rendering each level from the level above it.
+fn create_mipmapped_environment(
+    context: &Context,
+    shader: &wgpu::ShaderModule,
+    sampler: &Sampler,
+    environment: &TextureViewCube,
+) -> TextureCube {
+    let device = context.wgpu_device();
+    let size = environment.size();
+    let mipmapped = TextureCube::create_with_mip_levels(
+        device,
+        size,
+        TextureFormat::Rgba16Float,
+        wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        Texture2d::max_mip_level_count(vec2(size, size)),
+    );
+    render_cube_faces(
+        context,
+        "environment mip levels",
+        shader,
+        "fs_resample",
+        &mipmapped,
+        |face, mip_level| ConvolutionBindGroup {
+            environment: match mip_level {
+                0 => environment.clone(),
+                _ => mipmapped.view_mip_levels(Default::default(), mip_level - 1..mip_level),
+            },
+            sampler: sampler.clone(),
+            convolution: UniformBuffer::create_init(
+                device,
+                ConvolutionUniform {
+                    face,
+                    ..Zeroable::zeroed()
+                },
+            ),
+        },
+    );
+    mipmapped
+}
+
+impl TextureCube {
+    /// Converts an equirectangular (latitude-longitude) panorama into a cube map with faces of
+    /// `size` by `size` texels, by rendering each face on the GPU. The center of the panorama
+    /// ends up on the +X face, and its top row on the +Y face.
+    ///
+    /// The panorama is usually a HDR image loaded with `Texture2d::from_image_bytes`, and `format`
+    /// `Rgba16Float` to keep its range. The cube map has usages (TEXTURE_BINDING |
+    /// RENDER_ATTACHMENT) in addition to `usage`, and a single mip level.
+    ///
+    /// # Panics
+    ///
+    /// - if `format` isn't renderable
+    /// - if the panorama can't be sampled with filtering
+    pub fn from_equirectangular(
+        context: &Context,
+        equirectangular: &TextureView2d,
+        size: u32,
+        format: TextureFormat,
+        usage: wgpu::TextureUsages,
+    ) -> Self {
+        let device = context.wgpu_device();
+        let usage =
+            usage | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT;
+        let cube = Self::create(device, size, format, usage);
+        let sampler = clamping_sampler(context);
+        let source = preprocess_wgsl(include_str!("./shaders/equirectangular_to_cube.wgsl"), &[])
+            .expect("the equirectangular to cube shader is valid");
+        let shader = create_wgsl_shader_module(device, "equirectangular to cube", &source);
+        render_cube_faces(
+            context,
+            "equirectangular to cube",
+            &shader,
+            "fs_main",
+            &cube,
+            |face, _| EquirectangularBindGroup {
+                equirectangular: equirectangular.clone(),
+                sampler: sampler.clone(),
+                face: UniformBuffer::create_init(device, face),
+            },
+        );
+        cube
+    }
+}
+
+/// Sampler of the environment in lit materials, blending between the mip levels of the
+/// prefiltered cube map.
+pub(crate) fn create_environment_sampler(device: &wgpu::Device) -> Sampler {
+    Sampler::from_raw(device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("environment"),
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    }))
+}
+
+/// Renders the split-sum BRDF lookup texture, see `Context::brdf_lut`.
+pub(crate) fn create_brdf_lut(context: &Context) -> Texture2d {
+    let device = context.wgpu_device();
+    let usage = wgpu::TextureUsages::TEXTURE_BINDING
+        | wgpu::TextureUsages::RENDER_ATTACHMENT
+        | wgpu::TextureUsages::COPY_SRC;
+    let size = vec2(BRDF_LUT_SIZE, BRDF_LUT_SIZE);
+    let brdf_lut = Texture2d::create(device, size, TextureFormat::Rg16Float, usage);
+    let shader = create_ibl_shader(device);
+    let pipeline = create_fullscreen_pipeline(
+        device,
+        "BRDF lookup texture",
+        &shader,
+        "fs_brdf",
+        &[],
+        brdf_lut.wgpu_format(),
+    );
+    let mut encoder = device.create_command_encoder(&Default::default());
+    draw_fullscreen(
+        &mut encoder,
+        "BRDF lookup texture",
+        &brdf_lut.view(Default::default()),
+        &pipeline,
+        None,
+    );
+    context.wgpu_queue().submit([encoder.finish()]);
+    brdf_lut
+}
+
+/// Image-based lighting of a scene by its surroundings, precomputed on the GPU from an environment
+/// cube map. Lit materials add the light of the environment to their ambient term. See
+/// `Scene::set_environment`.
+///
+/// Consists of the diffuse irradiance of the environment, its specular radiance prefiltered with
+/// the GGX distribution for increasing roughness at each mip level, and the BRDF lookup texture of
+/// the `Context`.
+#[derive(Debug, Clone)]
+pub struct Environment {
+    irradiance: TextureCube,
+    prefiltered: TextureCube,
+    brdf_lut: Texture2d,
+    intensity: f32,
+}
+
+impl Environment {
+    /// Size of the faces of the irradiance cube map in `from_cube`.
+    pub const DEFAULT_IRRADIANCE_SIZE: u32 = 32;
+    /// Size of the faces of the first mip level of the prefiltered cube map in `from_cube`.
+    pub const DEFAULT_PREFILTERED_SIZE: u32 = 128;
+    /// Number of mip levels of the prefiltered cube map, from a roughness of 0 to 1, unless its
+    /// size doesn't allow for as many.
+    pub const PREFILTERED_MIP_LEVEL_COUNT: u32 = 5;
+
+    /// Precomputes the lighting of an environment cube map with the default sizes, see
+    /// `from_cube_with_sizes`.
+    pub fn from_cube(context: &Context, environment: &TextureViewCube) -> Self {
+        Self::from_cube_with_sizes(
+            context,
+            environment,
+            Self::DEFAULT_IRRADIANCE_SIZE,
+            Self::DEFAULT_PREFILTERED_SIZE,
+        )
+    }
+
+    /// Precomputes the lighting of an environment cube map, usually one converted from a HDR
+    /// panorama with `TextureCube::from_equirectangular`. The results are in `Rgba16Float` cube
+    /// maps with faces of `irradiance_size` and `prefiltered_size` texels.
+    ///
+    /// Only the first mip level of the environment is read: it is copied into a cube map with a
+    /// full chain of mip levels, and each sample of the convolutions reads the level whose texels
+    /// cover about as much of the environment as the sample stands for, so that small bright
+    /// features like the sun are neither missed nor overweighted.
+    ///
+    /// # Panics
+    ///
+    /// - if the environment can't be sampled with filtering
+    pub fn from_cube_with_sizes(
+        context: &Context,
+        environment: &TextureViewCube,
+        irradiance_size: u32,
+        prefiltered_size: u32,
+    ) -> Self {
+        let device = context.wgpu_device();
+        let usage = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT;
+        let format = TextureFormat::Rgba16Float;
+        let irradiance = TextureCube::create(device, irradiance_size, format, usage);
+        let mip_level_count = Self::PREFILTERED_MIP_LEVEL_COUNT.min(
+            Texture2d::max_mip_level_count(vec2(prefiltered_size, prefiltered_size)),
+        );
+        let prefiltered = TextureCube::create_with_mip_levels(
+            device,
+            prefiltered_size,
+            format,
+            usage,
+            mip_level_count,
+        );
+        let sampler = clamping_sampler(context);
+        let shader = create_ibl_shader(device);
+        let source = create_mipmapped_environment(context, &shader, &sampler, environment);
+        let source_view = source.view(Default::default());
+        let bind_group = |face, roughness| ConvolutionBindGroup {
+            environment: source_view.clone(),
+            sampler: sampler.clone(),
+            convolution: UniformBuffer::create_init(
+                device,
+                ConvolutionUniform {
+                    face,
+                    roughness,
+                    ..Zeroable::zeroed()
+                },
+            ),
+        };
+        render_cube_faces(
+            context,
+            "irradiance",
+            &shader,
+            "fs_irradiance",
+            &irradiance,
+            |face, _| bind_group(face, 1.0),
+        );
+        let roughness_step = 1.0 / (mip_level_count - 1).max(1) as f32;
+        render_cube_faces(
+            context,
+            "prefiltered specular",
+            &shader,
+            "fs_prefilter",
+            &prefiltered,
+            |face, mip_level| bind_group(face, mip_level as f32 * roughness_step),
+        );
+        Self {
+            irradiance,
+            prefiltered,
+            brdf_lut: context.brdf_lut().clone(),
+            intensity: 1.0,
+        }
+    }
+
+    /// Scales the light of the environment. Defaults to 1.
+    pub fn with_intensity(self, intensity: f32) -> Self {
+        Self { intensity, ..self }
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    pub fn irradiance(&self) -> &TextureCube {
+        &self.irradiance
+    }
+
+    pub fn prefiltered(&self) -> &TextureCube {
+        &self.prefiltered
+    }
+
+    pub fn brdf_lut(&self) -> &Texture2d {
+        &self.brdf_lut
+    }
+}
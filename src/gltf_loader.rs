@@ -0,0 +1,507 @@
+use std::{collections::HashMap, error::Error, fmt, path::Path, sync::Arc};
+
+use cgmath::*;
+
+use crate::{
+    AlphaMode, Camera, CameraDirection, CameraRef, Context, MaterialRef, MeshRef, ObjectRef, Rgba,
+    Sampler, SamplerOptions, Scene, ShaderValidationError, Texture2d, TextureFormat,
+    Vertex3dNormalTangentUV, compute_flat_normals, compute_tangents, materials, meshes,
+};
+
+/// Extensions that don't need to be handled. All materials are imported unlit, which is what
+/// `KHR_materials_unlit` asks for.
+const SUPPORTED_EXTENSIONS: &[&str] = &["KHR_materials_unlit"];
+
+/// The result of importing a glTF 2.0 file into a `Scene`.
+#[derive(Debug, Clone)]
+pub struct GltfImport {
+    /// Objects added to the scene, one for each primitive of each mesh node.
+    pub objects: Vec<ObjectRef>,
+    /// Cameras of camera nodes, with their world transforms applied.
+    pub cameras: Vec<Camera>,
+    /// Things in the file that were skipped or only partially imported.
+    pub warnings: Vec<GltfWarning>,
+}
+
+impl GltfImport {
+    /// Imports a `.gltf` or `.glb` file, adding its default scene (or the first scene, if there
+    /// isn't a default one) to `scene`. All objects are created with `camera`.
+    ///
+    /// Meshes are imported as `Mesh3DTbn` with the normals and tangents of the file. Primitives
+    /// without normals get flat normals, and primitives without tangents get tangents computed
+    /// with MikkTSpace.
+    pub fn load(
+        context: &Context,
+        scene: &mut Scene,
+        camera: CameraRef,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, ImportGltfError> {
+        let (document, buffers, images) = gltf::import(path).map_err(ImportGltfError::Gltf)?;
+        Importer::new(context, &buffers, &images).import(scene, camera, &document)
+    }
+
+    /// Like `load`, but from the content of a `.gltf` or `.glb` file.
+    /// External buffers and images can't be resolved, only embedded ones and buffers with data
+    /// URIs. Images must be stored in buffer views.
+    pub fn from_slice(
+        context: &Context,
+        scene: &mut Scene,
+        camera: CameraRef,
+        bytes: &[u8],
+    ) -> Result<Self, ImportGltfError> {
+        let (document, buffers, images) =
+            gltf::import_slice(bytes).map_err(ImportGltfError::Gltf)?;
+        Importer::new(context, &buffers, &images).import(scene, camera, &document)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GltfWarning {
+    /// An extension is used by the file but is not supported, and is ignored.
+    UnsupportedExtension { name: String, required: bool },
+    /// Primitive with a mode other than triangles, which is skipped.
+    UnsupportedPrimitiveMode {
+        mesh: usize,
+        primitive: usize,
+        mode: gltf::mesh::Mode,
+    },
+    /// Image with a pixel format that can't be converted to RGBA8. The material falls back to its
+    /// base color factor.
+    UnsupportedImageFormat {
+        image: usize,
+        format: gltf::image::Format,
+    },
+    /// Primitive without the set of texture coordinates that the base color texture of its
+    /// material uses, which are filled with zero.
+    MissingUvs {
+        mesh: usize,
+        primitive: usize,
+        set: u32,
+    },
+    /// Orthographic cameras are imported as `Camera` with zero FOV, whose extent follows the
+    /// viewport size instead of `xmag`/`ymag`.
+    OrthographicMagnificationIgnored { camera: usize },
+}
+
+impl fmt::Display for GltfWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedExtension { name, required } => {
+                let required = if *required { "required " } else { "" };
+                write!(f, "unsupported {required}extension {name} is ignored")
+            }
+            Self::UnsupportedPrimitiveMode {
+                mesh,
+                primitive,
+                mode,
+            } => write!(
+                f,
+                "primitive {primitive} of mesh {mesh} has unsupported mode {mode:?} and is skipped",
+            ),
+            Self::UnsupportedImageFormat { image, format } => {
+                write!(f, "image {image} has unsupported format {format:?}")
+            }
+            Self::MissingUvs {
+                mesh,
+                primitive,
+                set,
+            } => write!(
+                f,
+                "primitive {primitive} of mesh {mesh} has no texture coordinates {set}",
+            ),
+            Self::OrthographicMagnificationIgnored { camera } => {
+                write!(
+                    f,
+                    "magnification of orthographic camera {camera} is ignored"
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ImportGltfError {
+    Gltf(gltf::Error),
+    /// The file doesn't contain any scene.
+    NoScene,
+    /// A triangle primitive has no `POSITION` attribute.
+    MissingPositions {
+        mesh: usize,
+        primitive: usize,
+    },
+    /// The shader of a mesh doesn't match the shader of the material of one of its primitives.
+    Shader(ShaderValidationError),
+}
+
+impl fmt::Display for ImportGltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gltf(error) => write!(f, "{error}"),
+            Self::NoScene => write!(f, "glTF file contains no scene"),
+            Self::MissingPositions { mesh, primitive } => {
+                write!(f, "primitive {primitive} of mesh {mesh} has no positions")
+            }
+            Self::Shader(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl Error for ImportGltfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Gltf(error) => Some(error),
+            Self::Shader(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+struct Importer<'a> {
+    context: &'a Context,
+    buffers: &'a [gltf::buffer::Data],
+    images: &'a [gltf::image::Data],
+    /// Keyed by mesh index, `None` for skipped primitives.
+    meshes: HashMap<usize, Vec<Option<MeshRef>>>,
+    /// Keyed by material index, `None` for the default material.
+    materials: HashMap<Option<usize>, MaterialRef>,
+    /// Keyed by image index, `None` for images with unsupported formats.
+    textures: HashMap<usize, Option<Texture2d>>,
+    result: GltfImport,
+}
+
+impl<'a> Importer<'a> {
+    fn new(
+        context: &'a Context,
+        buffers: &'a [gltf::buffer::Data],
+        images: &'a [gltf::image::Data],
+    ) -> Self {
+        Self {
+            context,
+            buffers,
+            images,
+            meshes: HashMap::new(),
+            materials: HashMap::new(),
+            textures: HashMap::new(),
+            result: GltfImport {
+                objects: Vec::new(),
+                cameras: Vec::new(),
+                warnings: Vec::new(),
+            },
+        }
+    }
+
+    fn import(
+        mut self,
+        scene: &mut Scene,
+        camera: CameraRef,
+        document: &gltf::Document,
+    ) -> Result<GltfImport, ImportGltfError> {
+        for name in document.extensions_used() {
+            if SUPPORTED_EXTENSIONS.contains(&name) {
+                continue;
+            }
+            let required = document
+                .extensions_required()
+                .any(|required| required == name);
+            self.result
+                .warnings
+                .push(GltfWarning::UnsupportedExtension {
+                    name: name.to_owned(),
+                    required,
+                });
+        }
+        let gltf_scene = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+            .ok_or(ImportGltfError::NoScene)?;
+        for node in gltf_scene.nodes() {
+            self.import_node(scene, &camera, node, Matrix4::identity())?;
+        }
+        Ok(self.result)
+    }
+
+    fn import_node(
+        &mut self,
+        scene: &mut Scene,
+        camera: &CameraRef,
+        node: gltf::Node,
+        parent_transform: Matrix4<f32>,
+    ) -> Result<(), ImportGltfError> {
+        let transform = parent_transform * Matrix4::from(node.transform().matrix());
+        if let Some(mesh) = node.mesh() {
+            let primitives = self.mesh(&mesh)?;
+            for (primitive, mesh_ref) in mesh.primitives().zip(primitives) {
+                let Some(mesh_ref) = mesh_ref else {
+                    continue;
+                };
+                let material = self.material(&primitive.material());
+                let object = self
+                    .context
+                    .try_create_object(scene, camera.clone(), mesh_ref, material)
+                    .map_err(ImportGltfError::Shader)?;
+                scene.set_object_model(&object, transform);
+                scene.add_object(object.clone());
+                self.result.objects.push(object);
+            }
+        }
+        if let Some(gltf_camera) = node.camera() {
+            let camera = self.camera(&gltf_camera, transform);
+            self.result.cameras.push(camera);
+        }
+        for child in node.children() {
+            self.import_node(scene, camera, child, transform)?;
+        }
+        Ok(())
+    }
+
+    fn mesh(&mut self, mesh: &gltf::Mesh) -> Result<Vec<Option<MeshRef>>, ImportGltfError> {
+        if let Some(primitives) = self.meshes.get(&mesh.index()) {
+            return Ok(primitives.clone());
+        }
+        let mut primitives = Vec::new();
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                self.result
+                    .warnings
+                    .push(GltfWarning::UnsupportedPrimitiveMode {
+                        mesh: mesh.index(),
+                        primitive: primitive.index(),
+                        mode: primitive.mode(),
+                    });
+                primitives.push(None);
+                continue;
+            }
+            let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+            let positions: Vec<[f32; 3]> = reader
+                .read_positions()
+                .ok_or(ImportGltfError::MissingPositions {
+                    mesh: mesh.index(),
+                    primitive: primitive.index(),
+                })?
+                .collect();
+            // Only the base color texture is imported, read the set of coordinates it uses.
+            let tex_coord_set = primitive
+                .material()
+                .pbr_metallic_roughness()
+                .base_color_texture()
+                .map_or(0, |info| info.tex_coord());
+            let uvs: Vec<[f32; 2]> = match reader.read_tex_coords(tex_coord_set) {
+                Some(uvs) => uvs.into_f32().collect(),
+                None => {
+                    self.result.warnings.push(GltfWarning::MissingUvs {
+                        mesh: mesh.index(),
+                        primitive: primitive.index(),
+                        set: tex_coord_set,
+                    });
+                    vec![[0.0, 0.0]; positions.len()]
+                }
+            };
+            let normals: Option<Vec<[f32; 3]>> = reader.read_normals().map(Iterator::collect);
+            let tangents: Option<Vec<[f32; 4]>> = reader.read_tangents().map(Iterator::collect);
+            let vertices: Vec<Vertex3dNormalTangentUV> = positions
+                .into_iter()
+                .zip(uvs)
+                .enumerate()
+                .map(|(i, (position, uv))| {
+                    Vertex3dNormalTangentUV::new(
+                        position,
+                        uv,
+                        normals.as_ref().map_or([0.0; 3], |normals| normals[i]),
+                        tangents.as_ref().map_or([0.0; 4], |tangents| tangents[i]),
+                    )
+                })
+                .collect();
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..vertices.len() as u32).collect(),
+            };
+            // Without normals, flat normals are used and tangents computed with MikkTSpace, as
+            // the specification asks for.
+            let (mut vertices, indices) = match normals {
+                Some(_) => (vertices, indices),
+                None => compute_flat_normals(&vertices, &indices),
+            };
+            if tangents.is_none() {
+                compute_tangents(&mut vertices, &indices);
+            }
+            let mesh_instance = meshes::Mesh3DTbn::create(self.context, &vertices, &indices);
+            primitives.push(Some(self.context.create_mesh(Arc::new(mesh_instance))));
+        }
+        self.meshes.insert(mesh.index(), primitives.clone());
+        Ok(primitives)
+    }
+
+    fn material(&mut self, material: &gltf::Material) -> MaterialRef {
+        if let Some(material_ref) = self.materials.get(&material.index()) {
+            return material_ref.clone();
+        }
+        let pbr = material.pbr_metallic_roughness();
+        let alpha_mode = match material.alpha_mode() {
+            gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            gltf::material::AlphaMode::Mask => AlphaMode::Mask {
+                cutoff: material.alpha_cutoff().unwrap_or(0.5),
+            },
+            gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+        };
+        let texture = pbr
+            .base_color_texture()
+            .and_then(|info| Some((self.texture(&info.texture().source())?, info.texture())));
+        let material_ref = match texture {
+            Some((texture, gltf_texture)) => {
+                let gltf_sampler = gltf_texture.sampler();
+                let sampler =
+                    Sampler::create_with_options(self.context, &sampler_options(&gltf_sampler));
+                self.context.create_material(
+                    &materials::Textured::create(texture.view(Default::default()), sampler)
+                        .with_alpha_mode(alpha_mode),
+                )
+            }
+            None => self.context.create_material(
+                &materials::UniformFill::create(self.context, Rgba::from(pbr.base_color_factor()))
+                    .with_alpha_mode(alpha_mode),
+            ),
+        };
+        self.materials
+            .insert(material.index(), material_ref.clone());
+        material_ref
+    }
+
+    fn texture(&mut self, image: &gltf::Image) -> Option<Texture2d> {
+        if let Some(texture) = self.textures.get(&image.index()) {
+            return texture.clone();
+        }
+        let data = &self.images[image.index()];
+        let texture = match rgba8_pixels(data) {
+            Some(pixels) => Some(
+                Texture2d::create_init_mipmapped(
+                    self.context,
+                    vec2(data.width, data.height),
+                    TextureFormat::Rgba8UnormSrgb,
+                    wgpu::TextureUsages::empty(),
+                    &pixels,
+                )
+                .expect("mipmaps can be generated for Rgba8UnormSrgb"),
+            ),
+            None => {
+                self.result
+                    .warnings
+                    .push(GltfWarning::UnsupportedImageFormat {
+                        image: image.index(),
+                        format: data.format,
+                    });
+                None
+            }
+        };
+        self.textures.insert(image.index(), texture.clone());
+        texture
+    }
+
+    fn camera(&mut self, camera: &gltf::Camera, transform: Matrix4<f32>) -> Camera {
+        // glTF cameras look towards -Z with +Y up in their local space.
+        let position = Point3::from_homogeneous(transform * vec4(0.0, 0.0, 0.0, 1.0));
+        let direction = (transform * vec4(0.0, 0.0, -1.0, 0.0)).truncate();
+        let up = (transform * vec4(0.0, 1.0, 0.0, 0.0)).truncate();
+        match camera.projection() {
+            gltf::camera::Projection::Perspective(perspective) => Camera::new(
+                position,
+                up,
+                CameraDirection::LookTo(direction),
+                Rad(perspective.yfov()),
+                perspective.znear(),
+                // Infinite projection isn't supported, use a far plane far enough away instead.
+                perspective
+                    .zfar()
+                    .unwrap_or(perspective.znear() * 1_000_000.0),
+            ),
+            gltf::camera::Projection::Orthographic(orthographic) => {
+                self.result
+                    .warnings
+                    .push(GltfWarning::OrthographicMagnificationIgnored {
+                        camera: camera.index(),
+                    });
+                Camera::new(
+                    position,
+                    up,
+                    CameraDirection::LookTo(direction),
+                    Rad(0.0),
+                    orthographic.znear(),
+                    orthographic.zfar(),
+                )
+            }
+        }
+    }
+}
+
+fn sampler_options(sampler: &gltf::texture::Sampler) -> SamplerOptions {
+    use gltf::texture::MinFilter;
+    let (min_filter, mipmap_filter) = match sampler.min_filter() {
+        Some(MinFilter::Nearest | MinFilter::NearestMipmapNearest) => {
+            (wgpu::FilterMode::Nearest, wgpu::FilterMode::Nearest)
+        }
+        Some(MinFilter::NearestMipmapLinear) => {
+            (wgpu::FilterMode::Nearest, wgpu::FilterMode::Linear)
+        }
+        Some(MinFilter::LinearMipmapNearest) => {
+            (wgpu::FilterMode::Linear, wgpu::FilterMode::Nearest)
+        }
+        Some(MinFilter::Linear | MinFilter::LinearMipmapLinear) | None => {
+            (wgpu::FilterMode::Linear, wgpu::FilterMode::Linear)
+        }
+    };
+    SamplerOptions {
+        address_mode_u: address_mode(sampler.wrap_s()),
+        address_mode_v: address_mode(sampler.wrap_t()),
+        mag_filter: match sampler.mag_filter() {
+            Some(gltf::texture::MagFilter::Nearest) => wgpu::FilterMode::Nearest,
+            _ => wgpu::FilterMode::Linear,
+        },
+        min_filter,
+        mipmap_filter,
+        // Filters without mipmaps only sample the first level.
+        lod_max_clamp: match sampler.min_filter() {
+            Some(MinFilter::Nearest | MinFilter::Linear) => 0.0,
+            _ => SamplerOptions::default().lod_max_clamp,
+        },
+        ..Default::default()
+    }
+}
+
+fn address_mode(wrapping_mode: gltf::texture::WrappingMode) -> wgpu::AddressMode {
+    match wrapping_mode {
+        gltf::texture::WrappingMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+        gltf::texture::WrappingMode::MirroredRepeat => wgpu::AddressMode::MirrorRepeat,
+        gltf::texture::WrappingMode::Repeat => wgpu::AddressMode::Repeat,
+    }
+}
+
+/// Converts 8 and 16 bit images to RGBA8. One and two channel images are treated as grayscale
+/// (with alpha). `None` for float images.
+fn rgba8_pixels(data: &gltf::image::Data) -> Option<Vec<u8>> {
+    use gltf::image::Format;
+    // 16 bit channels are little endian, keep the high byte.
+    let (channels, bytes_per_channel) = match data.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => return Some(data.pixels.clone()),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT | Format::R32G32B32A32FLOAT => return None,
+    };
+    let pixels = data
+        .pixels
+        .chunks_exact(channels * bytes_per_channel)
+        .flat_map(|pixel| {
+            let channel = |i: usize| pixel[i * bytes_per_channel + bytes_per_channel - 1];
+            match channels {
+                1 => [channel(0), channel(0), channel(0), 255],
+                2 => [channel(0), channel(0), channel(0), channel(1)],
+                3 => [channel(0), channel(1), channel(2), 255],
+                _ => [channel(0), channel(1), channel(2), channel(3)],
+            }
+        })
+        .collect();
+    Some(pixels)
+}
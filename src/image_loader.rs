@@ -0,0 +1,122 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use cgmath::*;
+use wgpu::TextureUsages;
+
+use crate::{Context, Texture2d, TextureFormat};
+
+/// How the color channels of an 8 bit image are interpreted, see `Texture2d::from_image_bytes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// For colors, e.g. base color and emissive textures. Creates `Rgba8UnormSrgb` textures.
+    Srgb,
+    /// For data, e.g. normal, roughness and occlusion maps. Creates `Rgba8Unorm` textures.
+    Linear,
+}
+
+impl Texture2d {
+    /// Decodes a PNG, JPEG, BMP, TGA or Radiance HDR image into a texture with a full mip chain,
+    /// of usage (COPY_DST | COPY_SRC | TEXTURE_BINDING | RENDER_ATTACHMENT).
+    ///
+    /// Integer images become `Rgba8UnormSrgb` or `Rgba8Unorm` textures depending on
+    /// `color_space`, with 16 bit channels reduced to 8 bits. Float images, i.e. HDR, become
+    /// `Rgba16Float` textures regardless of `color_space`. Grayscale images are expanded to RGB,
+    /// and images without alpha are opaque. Images wider or higher than the device's
+    /// `max_texture_dimension_2d` are rejected.
+    pub fn from_image_bytes(
+        context: &Context,
+        bytes: &[u8],
+        color_space: ColorSpace,
+    ) -> Result<Self, LoadImageError> {
+        let image = match image::guess_format(bytes) {
+            Ok(format) if format.reading_enabled() => {
+                image::load_from_memory_with_format(bytes, format)
+                    .map_err(LoadImageError::Decode)?
+            }
+            Ok(_) => return Err(LoadImageError::UnknownFormat),
+            // TGA has no signature to recognize it by.
+            Err(_) => image::load_from_memory_with_format(bytes, image::ImageFormat::Tga)
+                .map_err(|_| LoadImageError::UnknownFormat)?,
+        };
+        let size = vec2(image.width(), image.height());
+        let max_size = context.wgpu_device().limits().max_texture_dimension_2d;
+        if size.x > max_size || size.y > max_size {
+            return Err(LoadImageError::TooLarge { size, max_size });
+        }
+        let usage = TextureUsages::COPY_SRC;
+        let texture = match image {
+            image::DynamicImage::ImageRgb32F(_) | image::DynamicImage::ImageRgba32F(_) => {
+                let texels: Vec<u16> = image
+                    .into_rgba32f()
+                    .into_raw()
+                    .into_iter()
+                    .map(|channel| half::f16::from_f32(channel).to_bits())
+                    .collect();
+                Self::create_init_mipmapped(
+                    context,
+                    size,
+                    TextureFormat::Rgba16Float,
+                    usage,
+                    bytemuck::cast_slice(&texels),
+                )
+            }
+            _ => {
+                let format = match color_space {
+                    ColorSpace::Srgb => TextureFormat::Rgba8UnormSrgb,
+                    ColorSpace::Linear => TextureFormat::Rgba8Unorm,
+                };
+                Self::create_init_mipmapped(context, size, format, usage, &image.into_rgba8())
+            }
+        };
+        Ok(texture.expect("mipmaps can be generated for all formats of decoded images"))
+    }
+
+    /// Reads and decodes an image file. See `from_image_bytes`.
+    pub fn from_path(
+        context: &Context,
+        path: impl AsRef<Path>,
+        color_space: ColorSpace,
+    ) -> Result<Self, LoadImageError> {
+        let bytes = fs::read(path).map_err(LoadImageError::Io)?;
+        Self::from_image_bytes(context, &bytes, color_space)
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadImageError {
+    Io(io::Error),
+    /// The data isn't a PNG, JPEG, BMP, TGA or Radiance HDR image.
+    UnknownFormat,
+    /// The image is corrupted, or uses a feature of its format that isn't supported.
+    Decode(image::ImageError),
+    /// The image is wider or higher than the device's `max_texture_dimension_2d`.
+    TooLarge {
+        size: Vector2<u32>,
+        max_size: u32,
+    },
+}
+
+impl fmt::Display for LoadImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::UnknownFormat => write!(f, "unknown image format"),
+            Self::Decode(error) => write!(f, "{error}"),
+            Self::TooLarge { size, max_size } => write!(
+                f,
+                "image of size {}x{} exceeds the maximum texture size {max_size}",
+                size.x, size.y,
+            ),
+        }
+    }
+}
+
+impl Error for LoadImageError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::UnknownFormat | Self::TooLarge { .. } => None,
+            Self::Decode(error) => Some(error),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::{collections::HashMap, mem};
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::*;
+
+use crate::{Rgba, Vertex};
+
+/// Per-instance data of an instanced object, bound as a second vertex buffer with
+/// `wgpu::VertexStepMode::Instance`.
+///
+/// In the instanced vertex shaders of the built-in meshes, `model` is applied before the model
+/// matrix of the object, `color` is passed on to the material as a tint, and `uv_offset` is added
+/// to the UV coordinates.
+///
+/// The attributes are at shader locations 8 to 13 (the four columns of `model`, then `color` and
+/// `uv_offset`), so vertex attributes of meshes must be at lower locations.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct Instance {
+    pub model: [[f32; 4]; 4],
+    pub color: [f32; 4],
+    pub uv_offset: [f32; 2],
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Self::new(Matrix4::identity())
+    }
+}
+
+impl Instance {
+    pub fn new(model: Matrix4<f32>) -> Self {
+        Self {
+            model: model.into(),
+            color: [1.0, 1.0, 1.0, 1.0],
+            uv_offset: [0.0, 0.0],
+        }
+    }
+
+    pub fn with_color(self, color: Rgba) -> Self {
+        Self {
+            color: color.to_array(),
+            ..self
+        }
+    }
+
+    pub fn with_uv_offset(self, uv_offset: Vector2<f32>) -> Self {
+        Self {
+            uv_offset: uv_offset.into(),
+            ..self
+        }
+    }
+}
+
+impl Vertex for Instance {
+    const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: mem::size_of::<Self>() as u64,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &wgpu::vertex_attr_array![
+            8 => Float32x4,
+            9 => Float32x4,
+            10 => Float32x4,
+            11 => Float32x4,
+            12 => Float32x4,
+            13 => Float32x2,
+        ],
+    };
+}
+
+/// Identifies an instance within its instanced object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InstanceId(u64);
+
+/// The instances of an instanced object, and the vertex buffer they are uploaded to.
+///
+/// Instances are kept tightly packed, removing an instance moves the last one into its place.
+#[derive(Debug, Clone)]
+pub(crate) struct InstanceBuffer {
+    instances: Vec<Instance>,
+    ids: Vec<InstanceId>,
+    indices: HashMap<InstanceId, usize>,
+    id_counter: u64,
+    wgpu_buffer: Option<wgpu::Buffer>,
+    /// Whether `instances` changed since the last upload.
+    is_dirty: bool,
+}
+
+impl InstanceBuffer {
+    pub(crate) fn new() -> Self {
+        Self {
+            instances: Vec::new(),
+            ids: Vec::new(),
+            indices: HashMap::new(),
+            id_counter: 0,
+            wgpu_buffer: None,
+            is_dirty: false,
+        }
+    }
+
+    pub(crate) fn len(&self) -> u32 {
+        self.instances.len() as u32
+    }
+
+    pub(crate) fn add(&mut self, instance: Instance) -> InstanceId {
+        let id = InstanceId(self.id_counter);
+        self.id_counter += 1;
+        self.indices.insert(id, self.instances.len());
+        self.instances.push(instance);
+        self.ids.push(id);
+        self.is_dirty = true;
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: InstanceId) -> Instance {
+        let index = self
+            .indices
+            .remove(&id)
+            .unwrap_or_else(|| panic!("instance doesn't exist"));
+        let instance = self.instances.swap_remove(index);
+        self.ids.swap_remove(index);
+        if let Some(&moved_id) = self.ids.get(index) {
+            self.indices.insert(moved_id, index);
+        }
+        self.is_dirty = true;
+        instance
+    }
+
+    pub(crate) fn get(&self, id: InstanceId) -> Option<Instance> {
+        let &index = self.indices.get(&id)?;
+        Some(self.instances[index])
+    }
+
+    pub(crate) fn update(&mut self, id: InstanceId, instance: Instance) {
+        let index = *self
+            .indices
+            .get(&id)
+            .unwrap_or_else(|| panic!("instance doesn't exist"));
+        self.instances[index] = instance;
+        self.is_dirty = true;
+    }
+
+    /// `None` until instances have been uploaded.
+    pub(crate) fn wgpu_buffer(&self) -> Option<&wgpu::Buffer> {
+        self.wgpu_buffer.as_ref()
+    }
+
+    /// Uploads the instances if they changed, re-allocating the buffer if it's too small.
+    pub(crate) fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !self.is_dirty {
+            return;
+        }
+        let size = mem::size_of_val(self.instances.as_slice()) as u64;
+        let is_too_small = self
+            .wgpu_buffer
+            .as_ref()
+            .is_none_or(|wgpu_buffer| wgpu_buffer.size() < size);
+        if is_too_small {
+            self.wgpu_buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: size.next_power_of_two(),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+        let wgpu_buffer = self.wgpu_buffer.as_ref().unwrap();
+        queue.write_buffer(wgpu_buffer, 0, bytemuck::cast_slice(&self.instances));
+        self.is_dirty = false;
+    }
+}
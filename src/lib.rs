@@ -1,6 +1,8 @@
 /// Contains the `Bindable` and `AsBindGroup` traits, and functions for creating wgpu bind groups
 /// and bind group layouts.
 pub(crate) mod binding;
+/// Contains bounding volumes and the camera frustum for culling.
+pub(crate) mod bounds;
 /// Contains vertex, index, and uniform buffers.
 pub(crate) mod buffers;
 /// Contains data structures for camera.
@@ -9,27 +11,86 @@ pub(crate) mod camera;
 pub(crate) mod color;
 /// Contains the `Context`.
 pub(crate) mod context;
+/// Contains `TextureCube::from_equirectangular` and the image-based lighting `Environment`.
+pub(crate) mod environment_map;
+/// Contains the glTF 2.0 importer.
+pub(crate) mod gltf_loader;
+/// Contains `Texture2d::from_image_bytes`, `Texture2d::from_path`, and `LoadImageError`.
+pub(crate) mod image_loader;
+/// Contains `Instance` and the instance buffer of instanced objects.
+pub(crate) mod instancing;
+/// Contains light types and data structures for passing lights to shaders.
+pub(crate) mod light;
 /// Contains the `AsMaterial` trait and various materials.
 pub(crate) mod material;
 /// Contains the `AsMesh` trait and various meshes.
 pub(crate) mod mesh;
+/// Contains `Texture2d_::generate_mipmaps` and `GenerateMipmapsError`.
+pub(crate) mod mipmap;
+/// Contains the Wavefront OBJ loader.
+pub(crate) mod obj_loader;
+/// Contains the cache of render pipelines and the shader modules and bind group layouts they
+/// depend on.
+pub(crate) mod pipeline_cache;
+/// Contains `Pixels` and functions for reading textures back from the GPU.
+pub(crate) mod readback;
 /// Contains `Scene`, various ID types, and data structures used internally in `Scene`.
 pub(crate) mod scene;
-/// Contains `Surface`, `SurfaceView`, `WindowSurface`, and `RenderPass`.
+/// Contains the WGSL preprocessor and the shader modules of the engine that shaders can import.
+pub(crate) mod shader_preprocessor;
+/// Contains `ShaderSource`, the `include_shader!` macro, and the file watcher for hot reloading
+/// shaders.
+pub(crate) mod shader_source;
+/// Contains `ShaderValidationError` and the validation of meshes and materials against their WGSL
+/// shaders.
+pub(crate) mod shader_validation;
+/// Contains shadow map constants and data structures for rendering and sampling shadow maps.
+pub(crate) mod shadow;
+/// Contains `Skybox` and the data structures for drawing it.
+pub(crate) mod skybox;
+/// Contains `Surface`, `SurfaceView`, `WindowSurface`, `RenderPass`, and `RenderPassOptions`.
 pub(crate) mod surface;
+/// Contains functions for computing normals and tangents of meshes.
+pub(crate) mod tangent_space;
 /// Contains textures, texture views, texture formats, and samplers.
 pub(crate) mod texture;
+/// Contains `LocalTransform`.
+pub(crate) mod transform;
 
 pub use binding::*;
+pub use bounds::*;
 pub use buffers::*;
 pub use camera::*;
 pub use color::*;
+pub use environment_map::*;
+pub use gltf_loader::*;
+pub use image_loader::*;
+pub use instancing::*;
+pub use light::*;
 pub use material::*;
 pub use mesh::*;
+pub use mipmap::*;
+pub use obj_loader::*;
+pub(crate) use pipeline_cache::*;
+pub use readback::*;
 pub use scene::*;
+pub use shader_preprocessor::*;
+pub use shader_source::*;
+pub use shader_validation::*;
+pub use shadow::*;
+pub use skybox::*;
 pub use surface::*;
+pub use tangent_space::*;
 pub use texture::*;
+pub use transform::*;
 pub use context::*;
 
+pub use tbn_engine_macros::AsBindGroup;
+
 pub use cgmath;
 pub use obj;
+pub use notify;
+pub use wgpu;
+
+// For `#[derive(AsBindGroup)]` to refer to `::tbn_engine` inside this crate too.
+extern crate self as tbn_engine;
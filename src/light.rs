@@ -0,0 +1,258 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::*;
+
+use crate::{
+    AsBindGroup, ComparingSampler, DepthStencilTextureView2d, Environment, Rgba, Sampler,
+    ShadowMapsUniform, Texture2d, TextureCube, TextureFormat, TextureView2d, TextureViewCube,
+    UniformBuffer, environment_map,
+};
+
+/// A light that lights the objects of a scene with lit materials (e.g. `materials::BlinnPhong`).
+///
+/// Positions and directions are in world space. The alpha channel of colors is ignored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Light {
+    Directional(DirectionalLight),
+    Point(PointLight),
+    Spot(SpotLight),
+}
+
+/// A light infinitely far away, e.g. the sun.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirectionalLight {
+    /// The direction the light travels in.
+    pub direction: Vector3<f32>,
+    pub color: Rgba,
+    pub intensity: f32,
+}
+
+/// A light that shines in all directions from a point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointLight {
+    pub position: Point3<f32>,
+    pub color: Rgba,
+    pub intensity: f32,
+    /// The distance at which the light fades out completely.
+    pub range: f32,
+}
+
+/// A light that shines in a cone from a point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpotLight {
+    pub position: Point3<f32>,
+    /// The direction of the axis of the cone.
+    pub direction: Vector3<f32>,
+    pub color: Rgba,
+    pub intensity: f32,
+    /// The distance at which the light fades out completely.
+    pub range: f32,
+    /// The angle from the axis within which the light is at full intensity.
+    pub inner_angle: Rad<f32>,
+    /// The angle from the axis at which the light fades out completely.
+    pub outer_angle: Rad<f32>,
+}
+
+impl From<DirectionalLight> for Light {
+    fn from(value: DirectionalLight) -> Self {
+        Self::Directional(value)
+    }
+}
+
+impl From<PointLight> for Light {
+    fn from(value: PointLight) -> Self {
+        Self::Point(value)
+    }
+}
+
+impl From<SpotLight> for Light {
+    fn from(value: SpotLight) -> Self {
+        Self::Spot(value)
+    }
+}
+
+impl Light {
+    pub(crate) fn to_uniform(self) -> LightUniform {
+        let color = |color: Rgba| [color.r, color.g, color.b];
+        match self {
+            Self::Directional(light) => LightUniform {
+                kind: LightUniform::DIRECTIONAL,
+                direction: light.direction.normalize().into(),
+                color: color(light.color),
+                intensity: light.intensity,
+                ..LightUniform::zeroed()
+            },
+            Self::Point(light) => LightUniform {
+                position: light.position.into(),
+                kind: LightUniform::POINT,
+                range: light.range,
+                color: color(light.color),
+                intensity: light.intensity,
+                ..LightUniform::zeroed()
+            },
+            Self::Spot(light) => LightUniform {
+                position: light.position.into(),
+                kind: LightUniform::SPOT,
+                direction: light.direction.normalize().into(),
+                range: light.range,
+                color: color(light.color),
+                intensity: light.intensity,
+                cos_inner_angle: light.inner_angle.cos(),
+                cos_outer_angle: light.outer_angle.cos(),
+                ..LightUniform::zeroed()
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct LightStorage {
+    /// Identifies the light in the scene's light list.
+    pub(crate) id: u64,
+    pub(crate) light: Light,
+    pub(crate) is_hidden: bool,
+    pub(crate) casts_shadows: bool,
+}
+
+/// Matches `struct Light` in the lit material shaders.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub(crate) struct LightUniform {
+    position: [f32; 3],
+    kind: u32,
+    direction: [f32; 3],
+    range: f32,
+    color: [f32; 3],
+    intensity: f32,
+    cos_inner_angle: f32,
+    cos_outer_angle: f32,
+    /// Index of the first shadow map of the light, in `ShadowMapsUniform`.
+    first_shadow_map: u32,
+    /// 0 for lights that don't cast shadows.
+    shadow_map_count: u32,
+}
+
+impl LightUniform {
+    const DIRECTIONAL: u32 = 0;
+    const POINT: u32 = 1;
+    const SPOT: u32 = 2;
+
+    pub(crate) fn with_shadow_maps(self, first_shadow_map: usize, shadow_map_count: usize) -> Self {
+        Self {
+            first_shadow_map: first_shadow_map as u32,
+            shadow_map_count: shadow_map_count as u32,
+            ..self
+        }
+    }
+}
+
+/// Maximum number of lights that affect a scene at once, further lights are ignored.
+pub const MAX_LIGHTS: usize = 16;
+
+/// Matches `struct Lights` in the lit material shaders.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub(crate) struct LightsUniform {
+    ambient: [f32; 3],
+    count: u32,
+    lights: [LightUniform; MAX_LIGHTS],
+    /// 0 without an environment.
+    environment_intensity: f32,
+    /// Mip level of the prefiltered environment for a roughness of 1.
+    prefiltered_max_lod: f32,
+    _padding: [u32; 2],
+}
+
+impl LightsUniform {
+    pub(crate) fn new(ambient: Rgba, lights: impl IntoIterator<Item = LightUniform>) -> Self {
+        let mut uniform = Self {
+            ambient: [ambient.r, ambient.g, ambient.b],
+            ..Self::zeroed()
+        };
+        for (slot, light) in uniform.lights.iter_mut().zip(lights) {
+            *slot = light;
+            uniform.count += 1;
+        }
+        uniform
+    }
+
+    pub(crate) fn with_environment(self, environment: Option<&Environment>) -> Self {
+        let Some(environment) = environment else {
+            return self;
+        };
+        Self {
+            environment_intensity: environment.intensity(),
+            prefiltered_max_lod: (environment.prefiltered().mip_level_count() - 1) as f32,
+            ..self
+        }
+    }
+}
+
+/// Bound to `@group(3)` of every object's pipeline.
+#[derive(Debug, Clone, AsBindGroup)]
+pub(crate) struct LightsBindGroup {
+    #[binding(0, fragment)]
+    pub(crate) lights: UniformBuffer<LightsUniform>,
+    #[binding(1, fragment)]
+    pub(crate) shadow_maps: UniformBuffer<ShadowMapsUniform>,
+    #[binding(2, fragment)]
+    pub(crate) shadow_atlas: DepthStencilTextureView2d,
+    #[binding(3, fragment)]
+    pub(crate) shadow_sampler: ComparingSampler,
+    #[binding(4, fragment)]
+    pub(crate) irradiance: TextureViewCube,
+    #[binding(5, fragment)]
+    pub(crate) prefiltered: TextureViewCube,
+    #[binding(6, fragment)]
+    pub(crate) brdf_lut: TextureView2d,
+    #[binding(7, fragment)]
+    pub(crate) environment_sampler: Sampler,
+}
+
+impl LightsBindGroup {
+    pub(crate) fn create(
+        device: &wgpu::Device,
+        shadow_atlas: DepthStencilTextureView2d,
+        shadow_sampler: ComparingSampler,
+    ) -> Self {
+        Self {
+            lights: UniformBuffer::create_init(device, LightsUniform::zeroed()),
+            shadow_maps: UniformBuffer::create_init(device, ShadowMapsUniform::zeroed()),
+            shadow_atlas,
+            shadow_sampler,
+            irradiance: placeholder_cube(device),
+            prefiltered: placeholder_cube(device),
+            brdf_lut: placeholder_texture(device),
+            environment_sampler: environment_map::create_environment_sampler(device),
+        }
+    }
+
+    /// Binds the textures of `environment`, or black placeholders without one.
+    pub(crate) fn set_environment(
+        &mut self,
+        device: &wgpu::Device,
+        environment: Option<&Environment>,
+    ) {
+        match environment {
+            Some(environment) => {
+                self.irradiance = environment.irradiance().view(Default::default());
+                self.prefiltered = environment.prefiltered().view(Default::default());
+                self.brdf_lut = environment.brdf_lut().view(Default::default());
+            }
+            None => {
+                self.irradiance = placeholder_cube(device);
+                self.prefiltered = placeholder_cube(device);
+                self.brdf_lut = placeholder_texture(device);
+            }
+        }
+    }
+}
+
+fn placeholder_cube(device: &wgpu::Device) -> TextureViewCube {
+    let usage = wgpu::TextureUsages::TEXTURE_BINDING;
+    TextureCube::create(device, 1, TextureFormat::Rgba8Unorm, usage).view(Default::default())
+}
+
+fn placeholder_texture(device: &wgpu::Device) -> TextureView2d {
+    let usage = wgpu::TextureUsages::TEXTURE_BINDING;
+    Texture2d::create(device, vec2(1, 1), TextureFormat::Rgba8Unorm, usage).view(Default::default())
+}
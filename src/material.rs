@@ -1,8 +1,60 @@
-use crate::{binding, impl_as_bind_group, AsBindGroup, Rgba, Sampler, UniformBuffer};
+use crate::{
+    AsBindGroup, Context, Rgba, Sampler, ShaderId, ShaderSource, UniformBuffer, binding,
+    include_shader,
+};
 
-pub trait AsMaterial: AsBindGroup {
-    fn create_fragment_shader(device: &wgpu::Device) -> wgpu::ShaderModule;
+/// How the alpha of the color returned by the fragment shader of a material is treated.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum AlphaMode {
+    /// Alpha is ignored. Opaque objects are drawn first, front to back.
+    #[default]
+    Opaque,
+    /// Like `Opaque`, but fragments with an alpha below `cutoff` are discarded. Requires the
+    /// shader to call `alpha_mask` of `tbn::camera`, like the built-in materials do.
+    ///
+    /// The fragment shader also runs when rendering shadow maps, where it is bound to a lights
+    /// bind group without lights, so that discarded fragments don't cast shadows.
+    Mask { cutoff: f32 },
+    /// Blended onto the objects behind with `AsMaterial::blend_state`. Blended objects are drawn
+    /// after opaque ones, back to front, without writing depth. They don't cast shadows.
+    Blend,
+}
+
+pub trait AsMaterial: AsBindGroup + 'static {
+    /// WGSL source of the fragment shader, which may use the directives of `preprocess_wgsl`. Used
+    /// for creating the shader, for validating the material against it (see
+    /// `ShaderValidationError`), and for hot reloading (see `Context::enable_shader_hot_reload`).
+    ///
+    /// `None` if `create_fragment_shader` is implemented instead, which skips validation.
+    fn fragment_shader_source() -> Option<ShaderSource> {
+        None
+    }
+
+    /// Names defined when preprocessing `fragment_shader_source` for this material, for
+    /// specializing the shader with `#ifdef`. Materials with different defines get different
+    /// shaders and pipelines.
+    fn shader_defines(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// Only called if `fragment_shader_source` returns `None`.
+    ///
+    /// # Panics
+    ///
+    /// - by default
+    fn create_fragment_shader(_device: &wgpu::Device) -> wgpu::ShaderModule {
+        panic!(
+            "{} implements neither `fragment_shader_source` nor `create_fragment_shader`",
+            std::any::type_name::<Self>(),
+        )
+    }
+
+    /// Opaque by default.
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Opaque
+    }
 
+    /// Blend state of materials whose alpha mode is `AlphaMode::Blend`. Alpha blending by default.
     fn blend_state() -> Option<wgpu::BlendState> {
         Some(wgpu::BlendState {
             color: wgpu::BlendComponent {
@@ -16,57 +68,60 @@ pub trait AsMaterial: AsBindGroup {
 }
 
 pub mod materials {
-    use crate::{Context, TextureView2d};
+    use bytemuck::{Pod, Zeroable};
+    use cgmath::vec2;
+
+    use crate::{Context, Texture2d, TextureFormat, TextureView2d};
 
     use super::*;
 
-    #[derive(Debug, Clone)]
+    #[derive(Debug, Clone, AsBindGroup)]
     pub struct UniformFill {
+        #[binding(0, fragment)]
         pub fill_color: UniformBuffer<Rgba>,
-    }
-
-    impl_as_bind_group! {
-        UniformFill {
-            0 => fill_color,
-        }
+        alpha_mode: AlphaMode,
     }
 
     impl UniformFill {
+        /// The alpha mode is `AlphaMode::Blend` if the color is translucent, otherwise
+        /// `AlphaMode::Opaque`.
         pub fn create(context: &Context, color: Rgba) -> Self {
             Self {
                 fill_color: UniformBuffer::create_init(context.wgpu_device(), color),
+                alpha_mode: match color.a < 1.0 {
+                    true => AlphaMode::Blend,
+                    false => AlphaMode::Opaque,
+                },
             }
         }
+
+        pub fn with_alpha_mode(self, alpha_mode: AlphaMode) -> Self {
+            Self { alpha_mode, ..self }
+        }
     }
 
     impl AsMaterial for UniformFill {
-        fn create_fragment_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
-            device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: None,
-                source: wgpu::ShaderSource::Wgsl(
-                    include_str!("./shaders/materials/uniform_fill.wgsl").into(),
-                ),
-            })
+        fn fragment_shader_source() -> Option<ShaderSource> {
+            Some(include_shader!("./shaders/materials/uniform_fill.wgsl"))
+        }
+
+        fn alpha_mode(&self) -> AlphaMode {
+            self.alpha_mode
         }
     }
 
-    #[derive(Debug, Clone)]
+    #[derive(Debug, Clone, AsBindGroup)]
     pub struct SdfCircle {
+        #[binding(0, fragment)]
         pub fill_color: UniformBuffer<Rgba>,
         /// The center of the circle, in UV space.
+        #[binding(1, fragment)]
         pub center: UniformBuffer<[f32; 2]>,
         /// The radius of the circle, in UV space.
+        #[binding(2, fragment)]
         pub radius: UniformBuffer<f32>,
     }
 
-    impl_as_bind_group! {
-        SdfCircle {
-            0 => fill_color,
-            1 => center,
-            2 => radius,
-        }
-    }
-
     impl SdfCircle {
         pub fn create(context: &Context, fill_color: Rgba) -> Self {
             Self {
@@ -78,37 +133,292 @@ pub mod materials {
     }
 
     impl AsMaterial for SdfCircle {
-        fn create_fragment_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
-            device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: None,
-                source: wgpu::ShaderSource::Wgsl(
-                    include_str!("./shaders/materials/sdf_circle.wgsl").into(),
-                ),
-            })
+        fn fragment_shader_source() -> Option<ShaderSource> {
+            Some(include_shader!("./shaders/materials/sdf_circle.wgsl"))
+        }
+
+        /// Blended for smooth edges.
+        fn alpha_mode(&self) -> AlphaMode {
+            AlphaMode::Blend
         }
     }
 
-    #[derive(Debug, Clone)]
-    pub struct Textured {
-        texture_view: TextureView2d,
-        sampler: Sampler,
+    /// Lambert diffuse and Blinn-Phong specular lighting from the lights of the scene.
+    ///
+    /// Needs a mesh whose vertex shader outputs view space positions and normals, such as
+    /// `meshes::Mesh3DTbn`.
+    #[derive(Debug, Clone, AsBindGroup)]
+    pub struct BlinnPhong {
+        #[binding(0, fragment)]
+        pub diffuse_color: UniformBuffer<Rgba>,
+        #[binding(1, fragment)]
+        pub specular_color: UniformBuffer<Rgba>,
+        /// The exponent of the specular term, higher for smaller, sharper highlights.
+        #[binding(2, fragment)]
+        pub shininess: UniformBuffer<f32>,
+    }
+
+    impl BlinnPhong {
+        pub fn create(
+            context: &Context,
+            diffuse_color: Rgba,
+            specular_color: Rgba,
+            shininess: f32,
+        ) -> Self {
+            Self {
+                diffuse_color: UniformBuffer::create_init(context.wgpu_device(), diffuse_color),
+                specular_color: UniformBuffer::create_init(context.wgpu_device(), specular_color),
+                shininess: UniformBuffer::create_init(context.wgpu_device(), shininess),
+            }
+        }
     }
 
-    impl_as_bind_group! {
-        Textured {
-            0 => texture_view,
-            1 => sampler,
+    impl AsMaterial for BlinnPhong {
+        fn fragment_shader_source() -> Option<ShaderSource> {
+            Some(include_shader!("./shaders/materials/blinn_phong.wgsl"))
         }
     }
 
+    /// Constant factors of `Pbr`, multiplied with the respective textures.
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+    pub struct PbrFactors {
+        pub base_color: [f32; 4],
+        pub emissive: [f32; 3],
+        pub metallic: f32,
+        pub roughness: f32,
+        /// Scales the X and Y of the normals from the normal texture.
+        pub normal_scale: f32,
+        /// 0 for no occlusion, 1 for full occlusion from the occlusion texture.
+        pub occlusion_strength: f32,
+        pub _padding: f32,
+    }
+
+    impl Default for PbrFactors {
+        /// Same as the glTF defaults.
+        fn default() -> Self {
+            Self {
+                base_color: [1.0; 4],
+                emissive: [0.0; 3],
+                metallic: 1.0,
+                roughness: 1.0,
+                normal_scale: 1.0,
+                occlusion_strength: 1.0,
+                _padding: 0.0,
+            }
+        }
+    }
+
+    /// Physically based metallic-roughness material, using the Cook-Torrance BRDF with GGX
+    /// distribution against the lights of the scene. Follows the glTF 2.0 material model.
+    ///
+    /// Needs a mesh whose vertex shader outputs the TBN basis in view space, such as
+    /// `meshes::Mesh3DTbn`. See `Pbr::builder`.
+    #[derive(Debug, Clone, AsBindGroup)]
+    pub struct Pbr {
+        #[binding(0, fragment)]
+        pub factors: UniformBuffer<PbrFactors>,
+        #[binding(1, fragment)]
+        base_color_texture: TextureView2d,
+        #[binding(2, fragment)]
+        base_color_sampler: Sampler,
+        #[binding(3, fragment)]
+        metallic_roughness_texture: TextureView2d,
+        #[binding(4, fragment)]
+        metallic_roughness_sampler: Sampler,
+        #[binding(5, fragment)]
+        normal_texture: TextureView2d,
+        #[binding(6, fragment)]
+        normal_sampler: Sampler,
+        #[binding(7, fragment)]
+        occlusion_texture: TextureView2d,
+        #[binding(8, fragment)]
+        occlusion_sampler: Sampler,
+        #[binding(9, fragment)]
+        emissive_texture: TextureView2d,
+        #[binding(10, fragment)]
+        emissive_sampler: Sampler,
+        /// Without a normal texture, the shader is specialized to skip normal mapping.
+        has_normal_texture: bool,
+        alpha_mode: AlphaMode,
+    }
+
+    impl Pbr {
+        pub fn builder() -> PbrBuilder {
+            PbrBuilder::default()
+        }
+    }
+
+    impl AsMaterial for Pbr {
+        fn fragment_shader_source() -> Option<ShaderSource> {
+            Some(include_shader!("./shaders/materials/pbr.wgsl"))
+        }
+
+        fn shader_defines(&self) -> Vec<&'static str> {
+            match self.has_normal_texture {
+                true => vec!["NORMAL_MAP"],
+                false => Vec::new(),
+            }
+        }
+
+        fn alpha_mode(&self) -> AlphaMode {
+            self.alpha_mode
+        }
+    }
+
+    /// Builder for `Pbr`. Each map is either a texture multiplied by a constant factor, or just
+    /// the factor if no texture is given.
+    #[derive(Debug, Clone, Default)]
+    pub struct PbrBuilder {
+        factors: PbrFactors,
+        base_color_texture: Option<(TextureView2d, Sampler)>,
+        metallic_roughness_texture: Option<(TextureView2d, Sampler)>,
+        normal_texture: Option<(TextureView2d, Sampler)>,
+        occlusion_texture: Option<(TextureView2d, Sampler)>,
+        emissive_texture: Option<(TextureView2d, Sampler)>,
+        alpha_mode: AlphaMode,
+    }
+
+    impl PbrBuilder {
+        pub fn base_color(mut self, base_color: Rgba) -> Self {
+            self.factors.base_color = base_color.to_array();
+            self
+        }
+
+        /// Should be in an sRGB format.
+        pub fn base_color_texture(mut self, texture_view: TextureView2d, sampler: Sampler) -> Self {
+            self.base_color_texture = Some((texture_view, sampler));
+            self
+        }
+
+        pub fn metallic(mut self, metallic: f32) -> Self {
+            self.factors.metallic = metallic;
+            self
+        }
+
+        pub fn roughness(mut self, roughness: f32) -> Self {
+            self.factors.roughness = roughness;
+            self
+        }
+
+        /// Roughness is read from the green channel, metallic from the blue channel.
+        pub fn metallic_roughness_texture(
+            mut self,
+            texture_view: TextureView2d,
+            sampler: Sampler,
+        ) -> Self {
+            self.metallic_roughness_texture = Some((texture_view, sampler));
+            self
+        }
+
+        /// Tangent space normal map, should be in a linear format.
+        pub fn normal_texture(mut self, texture_view: TextureView2d, sampler: Sampler) -> Self {
+            self.normal_texture = Some((texture_view, sampler));
+            self
+        }
+
+        pub fn normal_scale(mut self, normal_scale: f32) -> Self {
+            self.factors.normal_scale = normal_scale;
+            self
+        }
+
+        /// Occlusion is read from the red channel. Only affects ambient light.
+        pub fn occlusion_texture(mut self, texture_view: TextureView2d, sampler: Sampler) -> Self {
+            self.occlusion_texture = Some((texture_view, sampler));
+            self
+        }
+
+        pub fn occlusion_strength(mut self, occlusion_strength: f32) -> Self {
+            self.factors.occlusion_strength = occlusion_strength;
+            self
+        }
+
+        /// Alpha is ignored.
+        pub fn emissive(mut self, emissive: Rgba) -> Self {
+            self.factors.emissive = [emissive.r, emissive.g, emissive.b];
+            self
+        }
+
+        /// Should be in an sRGB format.
+        pub fn emissive_texture(mut self, texture_view: TextureView2d, sampler: Sampler) -> Self {
+            self.emissive_texture = Some((texture_view, sampler));
+            self
+        }
+
+        /// Opaque by default. The alpha is the one of the base color.
+        pub fn alpha_mode(mut self, alpha_mode: AlphaMode) -> Self {
+            self.alpha_mode = alpha_mode;
+            self
+        }
+
+        pub fn build(self, context: &Context) -> Pbr {
+            let sampler = || {
+                Sampler::create(
+                    context,
+                    wgpu::AddressMode::Repeat,
+                    wgpu::FilterMode::Linear,
+                    wgpu::FilterMode::Linear,
+                )
+            };
+            let texture = |rgba: [u8; 4]| {
+                Texture2d::create_init(context, vec2(1, 1), TextureFormat::Rgba8Unorm, &rgba)
+                    .view(Default::default())
+            };
+            // Maps that aren't given are neutral, so that only the factors take effect.
+            let white = || (texture([255, 255, 255, 255]), sampler());
+            let (base_color_texture, base_color_sampler) =
+                self.base_color_texture.unwrap_or_else(white);
+            let (metallic_roughness_texture, metallic_roughness_sampler) =
+                self.metallic_roughness_texture.unwrap_or_else(white);
+            let has_normal_texture = self.normal_texture.is_some();
+            let (normal_texture, normal_sampler) = self
+                .normal_texture
+                .unwrap_or_else(|| (texture([128, 128, 255, 255]), sampler()));
+            let (occlusion_texture, occlusion_sampler) =
+                self.occlusion_texture.unwrap_or_else(white);
+            let (emissive_texture, emissive_sampler) = self.emissive_texture.unwrap_or_else(white);
+            Pbr {
+                factors: UniformBuffer::create_init(context.wgpu_device(), self.factors),
+                base_color_texture,
+                base_color_sampler,
+                metallic_roughness_texture,
+                metallic_roughness_sampler,
+                normal_texture,
+                normal_sampler,
+                occlusion_texture,
+                occlusion_sampler,
+                emissive_texture,
+                emissive_sampler,
+                has_normal_texture,
+                alpha_mode: self.alpha_mode,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, AsBindGroup)]
+    pub struct Textured {
+        #[binding(0, fragment)]
+        texture_view: TextureView2d,
+        #[binding(1, fragment)]
+        sampler: Sampler,
+        alpha_mode: AlphaMode,
+    }
+
     impl Textured {
+        /// The alpha mode is `AlphaMode::Opaque`, see `with_alpha_mode` for textures with
+        /// transparency.
         pub fn create(texture_view: TextureView2d, sampler: Sampler) -> Self {
             Self {
                 texture_view,
                 sampler,
+                alpha_mode: AlphaMode::Opaque,
             }
         }
 
+        pub fn with_alpha_mode(self, alpha_mode: AlphaMode) -> Self {
+            Self { alpha_mode, ..self }
+        }
+
         pub fn texture_view(&self) -> &TextureView2d {
             &self.texture_view
         }
@@ -119,38 +429,59 @@ pub mod materials {
     }
 
     impl AsMaterial for Textured {
-        fn create_fragment_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
-            device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: None,
-                source: wgpu::ShaderSource::Wgsl(
-                    include_str!("./shaders/materials/textured.wgsl").into(),
-                ),
-            })
+        fn fragment_shader_source() -> Option<ShaderSource> {
+            Some(include_shader!("./shaders/materials/textured.wgsl"))
+        }
+
+        fn alpha_mode(&self) -> AlphaMode {
+            self.alpha_mode
         }
     }
 }
 
 #[derive(Debug, Clone)]
 pub(crate) struct MaterialStorage {
-    pub(crate) fragment_shader: wgpu::ShaderModule,
+    pub(crate) type_name: &'static str,
+    pub(crate) bind_group_layout_entries: Vec<wgpu::BindGroupLayoutEntry>,
+    pub(crate) fragment_shader: ShaderId,
     pub(crate) wgpu_bind_group: wgpu::BindGroup,
     pub(crate) bind_group_layout: wgpu::BindGroupLayout,
+    pub(crate) alpha_mode: AlphaMode,
+    /// `None` unless the alpha mode is `AlphaMode::Blend`.
     pub(crate) blend_state: Option<wgpu::BlendState>,
 }
 
 impl MaterialStorage {
     pub(crate) fn new<Material: AsMaterial>(
-        device: &wgpu::Device,
+        context: &Context,
         material_instance: &Material,
     ) -> Self {
-        let (wgpu_bind_group, bind_group_layout) =
-            binding::create_wgpu_bind_group(device, material_instance);
+        let device = context.wgpu_device();
+        let pipeline_cache = context.pipeline_cache();
+        let bind_group_layout_entries = material_instance.bind_group_layout_entries();
+        let bind_group_layout = pipeline_cache.bind_group_layout(
+            device,
+            Some(std::any::type_name::<Material>()),
+            bind_group_layout_entries.clone(),
+        );
+        let wgpu_bind_group = binding::create_wgpu_bind_group_with_layout(
+            device,
+            material_instance,
+            &bind_group_layout,
+        );
+        let alpha_mode = material_instance.alpha_mode();
         Self {
-            fragment_shader: Material::create_fragment_shader(device),
+            type_name: std::any::type_name::<Material>(),
+            bind_group_layout_entries,
+            fragment_shader: pipeline_cache
+                .fragment_shader::<Material>(device, material_instance.shader_defines()),
             wgpu_bind_group,
             bind_group_layout,
-            blend_state: Material::blend_state(),
+            alpha_mode,
+            blend_state: match alpha_mode {
+                AlphaMode::Blend => Material::blend_state(),
+                AlphaMode::Opaque | AlphaMode::Mask { .. } => None,
+            },
         }
     }
 }
-
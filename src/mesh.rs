@@ -2,7 +2,8 @@ use core::fmt;
 use std::{fmt::Debug, ops::Deref as _, sync::Arc};
 
 use crate::{
-    binding, impl_as_bind_group, AsBindGroup, Index, IndexBuffer, UniformBuffer, Vertex, Vertex2d, Vertex3dUV, VertexBuffer
+    AsBindGroup, Bounds, Context, Index, IndexBuffer, ShaderId, ShaderSource, UniformBuffer,
+    Vertex, Vertex2d, Vertex3dNormalTangentUV, Vertex3dUV, VertexBuffer, binding, include_shader,
 };
 
 use cgmath::*;
@@ -11,16 +12,51 @@ pub trait AsMesh: AsBindGroup + Sized + 'static {
     type Vertex: Vertex;
     type Index: Index;
 
-    /// TODO: Perhaps make an abstraction for shaders similar to bevy's `ShaderRef`.
-    fn create_vertex_shader(device: &wgpu::Device) -> wgpu::ShaderModule;
+    /// WGSL source of the vertex shader, which may use the directives of `preprocess_wgsl`. Used
+    /// for creating the shader, for validating the mesh against it (see `ShaderValidationError`),
+    /// and for hot reloading (see `Context::enable_shader_hot_reload`).
+    ///
+    /// `None` if `create_vertex_shader` is implemented instead, which skips validation.
+    fn vertex_shader_source() -> Option<ShaderSource> {
+        None
+    }
+
+    /// WGSL source of the vertex shader for instanced objects, which additionally takes an
+    /// `Instance` per instance. `None` if the mesh doesn't support instancing.
+    fn instanced_vertex_shader_source() -> Option<ShaderSource> {
+        None
+    }
+
+    /// Only called if `vertex_shader_source` returns `None`.
+    ///
+    /// # Panics
+    ///
+    /// - by default
+    fn create_vertex_shader(_device: &wgpu::Device) -> wgpu::ShaderModule {
+        panic!(
+            "{} implements neither `vertex_shader_source` nor `create_vertex_shader`",
+            std::any::type_name::<Self>(),
+        )
+    }
+
+    /// Vertex shader for instanced objects, only called if `instanced_vertex_shader_source`
+    /// returns `None`. `None` if the mesh doesn't support instancing.
+    fn create_instanced_vertex_shader(_device: &wgpu::Device) -> Option<wgpu::ShaderModule> {
+        None
+    }
 
     fn vertex_buffer(&self) -> &VertexBuffer<Self::Vertex>;
 
     fn index_buffer(&self) -> &IndexBuffer<Self::Index>;
 
-    /// The model-view matrix.
-    /// `None` if this mesh doesn't use a traditional model-view matrix setup.
-    fn model_view(&self) -> Option<&UniformBuffer<[[f32; 4]; 4]>>;
+    /// Bounding volumes of the vertices in model space, read once when the mesh is registered.
+    /// Objects whose bounds are outside the frustum of their camera aren't drawn.
+    ///
+    /// `None` by default, which never culls objects of the mesh, e.g. for vertex shaders that
+    /// don't use the camera. Instanced objects are never culled either.
+    fn bounds(&self) -> Option<Bounds> {
+        None
+    }
 
     fn as_arc_dyn(self: Arc<Self>) -> Arc<dyn DynMesh> {
         self
@@ -32,7 +68,7 @@ pub trait DynMesh {
     fn vertex_buffer(&self) -> &wgpu::Buffer;
     fn index_buffer(&self) -> &wgpu::Buffer;
     fn index_buffer_length(&self) -> u32;
-    fn model_view(&self) -> Option<&wgpu::Buffer>;
+    fn bounds(&self) -> Option<Bounds>;
 }
 
 impl<T: AsMesh> DynMesh for T {
@@ -48,8 +84,8 @@ impl<T: AsMesh> DynMesh for T {
         AsMesh::index_buffer(self).length()
     }
 
-    fn model_view(&self) -> Option<&wgpu::Buffer> {
-        AsMesh::model_view(self).map(UniformBuffer::wgpu_buffer)
+    fn bounds(&self) -> Option<Bounds> {
+        AsMesh::bounds(self)
     }
 }
 
@@ -70,32 +106,26 @@ pub mod meshes {
     /// +--------+
     ///  (0, 0)   (1, 0)
     /// ```
-    #[derive(Debug, Clone)]
+    #[derive(Debug, Clone, AsBindGroup)]
     pub struct Quad {
         vertex_buffer: VertexBuffer<Vertex2d>,
         index_buffer: IndexBuffer<u16>,
-        /// The model-view matrix.
-        pub model_view: UniformBuffer<[[f32; 4]; 4]>,
         /// Apply a transform on the UV coordinates.
+        #[binding(0, vertex)]
         pub uv_transform: UniformBuffer<[[f32; 4]; 4]>,
-    }
-
-    impl_as_bind_group! {
-        Quad {
-            0 => model_view,
-            1 => uv_transform,
-        }
+        bounds: Bounds,
     }
 
     impl AsMesh for Quad {
         type Vertex = Vertex2d;
         type Index = u16;
 
-        fn create_vertex_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
-            device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: None,
-                source: wgpu::ShaderSource::Wgsl(include_str!("./shaders/shapes/quad.wgsl").into()),
-            })
+        fn vertex_shader_source() -> Option<ShaderSource> {
+            Some(include_shader!("./shaders/shapes/quad.wgsl"))
+        }
+
+        fn instanced_vertex_shader_source() -> Option<ShaderSource> {
+            Some(include_shader!("./shaders/shapes/quad_instanced.wgsl"))
         }
 
         fn vertex_buffer(&self) -> &VertexBuffer<Self::Vertex> {
@@ -106,8 +136,8 @@ pub mod meshes {
             &self.index_buffer
         }
 
-        fn model_view(&self) -> Option<&UniformBuffer<[[f32; 4]; 4]>> {
-            Some(&self.model_view)
+        fn bounds(&self) -> Option<Bounds> {
+            Some(self.bounds)
         }
     }
 
@@ -125,30 +155,27 @@ pub mod meshes {
             Self {
                 vertex_buffer: VertexBuffer::create_init(context.wgpu_device(), &Self::VERTICES),
                 index_buffer: IndexBuffer::create_init(context.wgpu_device(), &Self::INDICES),
-                model_view: UniformBuffer::create_init(
-                    context.wgpu_device(),
-                    Matrix4::identity().into(),
-                ),
                 uv_transform: UniformBuffer::create_init(
                     context.wgpu_device(),
                     Matrix4::identity().into(),
                 ),
+                bounds: Bounds::from_points(
+                    Self::VERTICES
+                        .iter()
+                        .map(|vertex| point3(vertex.position[0], vertex.position[1], 0.0)),
+                )
+                .unwrap(),
             }
         }
     }
 
     /// A generic 3d mesh without UV.
-    #[derive(Debug, Clone)]
+    #[derive(Debug, Clone, AsBindGroup)]
     pub struct Mesh3D {
         vertex_buffer: VertexBuffer<Vertex3dUV>,
         index_buffer: IndexBuffer<u32>,
-        model_view: UniformBuffer<[[f32; 4]; 4]>,
-    }
-
-    impl_as_bind_group! {
-        Mesh3D {
-            0 => model_view,
-        }
+        /// `None` if there are no vertices.
+        bounds: Option<Bounds>,
     }
 
     impl Mesh3D {
@@ -156,9 +183,8 @@ pub mod meshes {
             Self {
                 vertex_buffer: VertexBuffer::create_init(context.wgpu_device(), vertices),
                 index_buffer: IndexBuffer::create_init(context.wgpu_device(), indices),
-                model_view: UniformBuffer::create_init(
-                    context.wgpu_device(),
-                    Matrix4::identity().into(),
+                bounds: Bounds::from_points(
+                    vertices.iter().map(|vertex| Point3::from(vertex.position)),
                 ),
             }
         }
@@ -169,11 +195,60 @@ pub mod meshes {
 
         type Index = u32;
 
-        fn create_vertex_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
-            device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: None,
-                source: wgpu::ShaderSource::Wgsl(include_str!("./shaders/shapes/3d.wgsl").into()),
-            })
+        fn vertex_shader_source() -> Option<ShaderSource> {
+            Some(include_shader!("./shaders/shapes/3d.wgsl"))
+        }
+
+        fn instanced_vertex_shader_source() -> Option<ShaderSource> {
+            Some(include_shader!("./shaders/shapes/3d_instanced.wgsl"))
+        }
+
+        fn vertex_buffer(&self) -> &VertexBuffer<Self::Vertex> {
+            &self.vertex_buffer
+        }
+
+        fn index_buffer(&self) -> &IndexBuffer<Self::Index> {
+            &self.index_buffer
+        }
+
+        fn bounds(&self) -> Option<Bounds> {
+            self.bounds
+        }
+    }
+
+    /// A 3d mesh with normals and tangents, whose vertex shader outputs the position, normal,
+    /// tangent and bitangent in view space (at locations 2 to 5) for the material.
+    #[derive(Debug, Clone, AsBindGroup)]
+    pub struct Mesh3DTbn {
+        vertex_buffer: VertexBuffer<Vertex3dNormalTangentUV>,
+        index_buffer: IndexBuffer<u32>,
+        /// `None` if there are no vertices.
+        bounds: Option<Bounds>,
+    }
+
+    impl Mesh3DTbn {
+        pub fn create(
+            context: &Context,
+            vertices: &[Vertex3dNormalTangentUV],
+            indices: &[u32],
+        ) -> Self {
+            Self {
+                vertex_buffer: VertexBuffer::create_init(context.wgpu_device(), vertices),
+                index_buffer: IndexBuffer::create_init(context.wgpu_device(), indices),
+                bounds: Bounds::from_points(
+                    vertices.iter().map(|vertex| Point3::from(vertex.position)),
+                ),
+            }
+        }
+    }
+
+    impl AsMesh for Mesh3DTbn {
+        type Vertex = Vertex3dNormalTangentUV;
+
+        type Index = u32;
+
+        fn vertex_shader_source() -> Option<ShaderSource> {
+            Some(include_shader!("./shaders/shapes/3d_tbn.wgsl"))
         }
 
         fn vertex_buffer(&self) -> &VertexBuffer<Self::Vertex> {
@@ -184,8 +259,8 @@ pub mod meshes {
             &self.index_buffer
         }
 
-        fn model_view(&self) -> Option<&UniformBuffer<[[f32; 4]; 4]>> {
-            Some(&self.model_view)
+        fn bounds(&self) -> Option<Bounds> {
+            self.bounds
         }
     }
 }
@@ -193,38 +268,61 @@ pub mod meshes {
 #[derive(Clone)]
 pub(crate) struct MeshStorage {
     pub(crate) instance: Arc<dyn DynMesh>,
-    pub(crate) vertex_shader: wgpu::ShaderModule,
+    pub(crate) type_name: &'static str,
+    pub(crate) bind_group_layout_entries: Vec<wgpu::BindGroupLayoutEntry>,
+    pub(crate) vertex_shader: ShaderId,
+    pub(crate) instanced_vertex_shader: Option<ShaderId>,
     pub(crate) wgpu_bind_group: wgpu::BindGroup,
     pub(crate) bind_group_layout: wgpu::BindGroupLayout,
     pub(crate) vertex_buffer_layout: wgpu::VertexBufferLayout<'static>,
     pub(crate) index_format: wgpu::IndexFormat,
+    pub(crate) bounds: Option<Bounds>,
 }
 
 impl Debug for MeshStorage {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         formatter
             .debug_struct("MeshStorage")
+            .field("type_name", &self.type_name)
             .field("vertex_shader", &self.vertex_shader)
+            .field("instanced_vertex_shader", &self.instanced_vertex_shader)
             .field("wgpu_bind_group", &self.wgpu_bind_group)
             .field("bind_group_layout", &self.bind_group_layout)
             .field("vertex_buffer_layout", &self.vertex_buffer_layout)
+            .field("bounds", &self.bounds)
             .finish_non_exhaustive()
     }
 }
 
 impl MeshStorage {
-    pub(crate) fn new<Mesh: AsMesh>(device: &wgpu::Device, mesh_instance: Arc<Mesh>) -> Self {
-        let (wgpu_bind_group, bind_group_layout) =
-            binding::create_wgpu_bind_group(device, Arc::deref(&mesh_instance));
+    pub(crate) fn new<Mesh: AsMesh>(context: &Context, mesh_instance: Arc<Mesh>) -> Self {
+        let device = context.wgpu_device();
+        let pipeline_cache = context.pipeline_cache();
+        let bind_group_layout_entries = mesh_instance.bind_group_layout_entries();
+        let bind_group_layout = pipeline_cache.bind_group_layout(
+            device,
+            Some(std::any::type_name::<Mesh>()),
+            bind_group_layout_entries.clone(),
+        );
+        let wgpu_bind_group = binding::create_wgpu_bind_group_with_layout(
+            device,
+            Arc::deref(&mesh_instance),
+            &bind_group_layout,
+        );
         let vertex_buffer_layout = mesh_instance.vertex_buffer().layout();
         let index_format = mesh_instance.index_buffer().index_format();
+        let bounds = AsMesh::bounds(mesh_instance.as_ref());
         Self {
             instance: mesh_instance.as_arc_dyn(),
-            vertex_shader: Mesh::create_vertex_shader(device),
+            type_name: std::any::type_name::<Mesh>(),
+            bind_group_layout_entries,
+            vertex_shader: pipeline_cache.vertex_shader::<Mesh>(device),
+            instanced_vertex_shader: pipeline_cache.instanced_vertex_shader::<Mesh>(device),
             wgpu_bind_group,
             bind_group_layout,
             vertex_buffer_layout,
             index_format,
+            bounds,
         }
     }
 
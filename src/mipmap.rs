@@ -0,0 +1,342 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    sync::{Mutex, OnceLock},
+};
+
+use crate::{Context, Texture2d_, TextureFormatTrait, create_wgsl_shader_module};
+
+impl<Format: TextureFormatTrait> Texture2d_<Format> {
+    /// Fills mip levels 1 and above with downsampled versions of level 0, each one by averaging
+    /// 2x2 texels of the level above it.
+    ///
+    /// The format must have a float sample type. Levels of renderable formats are generated in a
+    /// render pass, which requires usages RENDER_ATTACHMENT and TEXTURE_BINDING. Levels of other
+    /// formats that support storage, e.g. `Rgba8Snorm`, are generated in a compute pass and
+    /// copied into the texture, which requires usages COPY_DST and TEXTURE_BINDING instead.
+    /// Integer formats, and formats that support neither, e.g. `R8Snorm`, `Rg8Snorm` and
+    /// `Rgb9e5Ufloat`, are not supported.
+    pub fn generate_mipmaps(&self, context: &Context) -> Result<(), GenerateMipmapsError> {
+        let device = context.wgpu_device();
+        let wgpu_format = self.wgpu_format();
+        let Some(pass) = MipmapPass::for_format(device, wgpu_format) else {
+            return Err(GenerateMipmapsError::UnsupportedFormat(wgpu_format));
+        };
+        let required_usage = pass.required_usage();
+        if !self.usage().contains(required_usage) {
+            return Err(GenerateMipmapsError::MissingUsage(
+                required_usage - self.usage(),
+            ));
+        }
+        if self.mip_level_count() == 1 {
+            return Ok(());
+        }
+        let generator = context.mipmap_generator();
+        let level_view = |mip_level| {
+            self.wgpu_texture()
+                .create_view(&wgpu::TextureViewDescriptor {
+                    base_mip_level: mip_level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+        };
+        let mut encoder = device.create_command_encoder(&Default::default());
+        match pass {
+            MipmapPass::Render => {
+                let pipeline = generator.pipeline(device, wgpu_format);
+                for mip_level in 1..self.mip_level_count() {
+                    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("mipmap source"),
+                        layout: generator.bind_group_layout(device),
+                        entries: &[wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&level_view(
+                                mip_level - 1,
+                            )),
+                        }],
+                    });
+                    let target = level_view(mip_level);
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("mipmap"),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                            view: &target,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                store: wgpu::StoreOp::Store,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+                    render_pass.set_pipeline(&pipeline);
+                    render_pass.set_bind_group(0, &bind_group, &[]);
+                    render_pass.draw(0..3, 0..1);
+                }
+            }
+            MipmapPass::Compute(storage_format) => {
+                let pipeline = generator.compute_pipeline(device, wgpu_format, storage_format);
+                for mip_level in 1..self.mip_level_count() {
+                    // Written into a separate texture and then copied into the level, as GL,
+                    // which has no real texture views, ignores writes to a level of a texture
+                    // that is sampled at another level.
+                    let size = self.mip_level_size(mip_level);
+                    let extent = wgpu::Extent3d {
+                        width: size.x,
+                        height: size.y,
+                        depth_or_array_layers: 1,
+                    };
+                    let destination = device.create_texture(&wgpu::TextureDescriptor {
+                        label: Some("mipmap"),
+                        size: extent,
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: wgpu_format,
+                        usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+                        view_formats: &[],
+                    });
+                    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("mipmap"),
+                        layout: &pipeline.get_bind_group_layout(0),
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: wgpu::BindingResource::TextureView(&level_view(
+                                    mip_level - 1,
+                                )),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: wgpu::BindingResource::TextureView(
+                                    &destination.create_view(&Default::default()),
+                                ),
+                            },
+                        ],
+                    });
+                    let mut compute_pass =
+                        encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                            label: Some("mipmap"),
+                            timestamp_writes: None,
+                        });
+                    compute_pass.set_pipeline(&pipeline);
+                    compute_pass.set_bind_group(0, &bind_group, &[]);
+                    compute_pass.dispatch_workgroups(
+                        size.x.div_ceil(COMPUTE_WORKGROUP_SIZE),
+                        size.y.div_ceil(COMPUTE_WORKGROUP_SIZE),
+                        1,
+                    );
+                    drop(compute_pass);
+                    encoder.copy_texture_to_texture(
+                        destination.as_image_copy(),
+                        wgpu::TexelCopyTextureInfo {
+                            texture: self.wgpu_texture(),
+                            mip_level,
+                            origin: wgpu::Origin3d::ZERO,
+                            aspect: wgpu::TextureAspect::All,
+                        },
+                        extent,
+                    );
+                }
+            }
+        }
+        context.wgpu_queue().submit([encoder.finish()]);
+        Ok(())
+    }
+}
+
+/// Width and height of the workgroups of `mipmap_compute.wgsl`.
+const COMPUTE_WORKGROUP_SIZE: u32 = 8;
+
+/// How the mip levels of a texture format are generated.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum MipmapPass {
+    Render,
+    /// With the WGSL texel format of the storage texture.
+    Compute(&'static str),
+}
+
+impl MipmapPass {
+    /// `None` if mip levels can't be generated for the format.
+    pub(crate) fn for_format(device: &wgpu::Device, format: wgpu::TextureFormat) -> Option<Self> {
+        let is_float = matches!(
+            format.sample_type(None, Some(device.features())),
+            Some(wgpu::TextureSampleType::Float { .. }),
+        );
+        if !is_float {
+            return None;
+        }
+        let allowed_usages = format
+            .guaranteed_format_features(device.features())
+            .allowed_usages;
+        if allowed_usages.contains(wgpu::TextureUsages::RENDER_ATTACHMENT) {
+            Some(Self::Render)
+        } else if allowed_usages.contains(wgpu::TextureUsages::STORAGE_BINDING) {
+            storage_texel_format(format).map(Self::Compute)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn required_usage(self) -> wgpu::TextureUsages {
+        match self {
+            Self::Render => {
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+            }
+            Self::Compute(_) => {
+                wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING
+            }
+        }
+    }
+}
+
+/// The WGSL texel format of float formats that may support storage.
+fn storage_texel_format(format: wgpu::TextureFormat) -> Option<&'static str> {
+    use wgpu::TextureFormat as F;
+    Some(match format {
+        F::R8Unorm => "r8unorm",
+        F::R8Snorm => "r8snorm",
+        F::R16Unorm => "r16unorm",
+        F::R16Snorm => "r16snorm",
+        F::R16Float => "r16float",
+        F::Rg8Unorm => "rg8unorm",
+        F::Rg8Snorm => "rg8snorm",
+        F::R32Float => "r32float",
+        F::Rg16Unorm => "rg16unorm",
+        F::Rg16Snorm => "rg16snorm",
+        F::Rg16Float => "rg16float",
+        F::Rgba8Unorm => "rgba8unorm",
+        F::Rgba8Snorm => "rgba8snorm",
+        F::Bgra8Unorm => "bgra8unorm",
+        F::Rgb10a2Unorm => "rgb10a2unorm",
+        F::Rg11b10Ufloat => "rg11b10float",
+        F::Rg32Float => "rg32float",
+        F::Rgba16Unorm => "rgba16unorm",
+        F::Rgba16Snorm => "rgba16snorm",
+        F::Rgba16Float => "rgba16float",
+        F::Rgba32Float => "rgba32float",
+        _ => return None,
+    })
+}
+
+/// The shaders and the pipelines for every texture format used by
+/// `Texture2d_::generate_mipmaps`, created when first needed.
+#[derive(Debug, Default)]
+pub(crate) struct MipmapGenerator {
+    shader: OnceLock<wgpu::ShaderModule>,
+    bind_group_layout: OnceLock<wgpu::BindGroupLayout>,
+    pipelines: Mutex<HashMap<wgpu::TextureFormat, wgpu::RenderPipeline>>,
+    /// The compute shader depends on the format, so each pipeline has its own.
+    compute_pipelines: Mutex<HashMap<wgpu::TextureFormat, wgpu::ComputePipeline>>,
+}
+
+impl MipmapGenerator {
+    fn bind_group_layout(&self, device: &wgpu::Device) -> &wgpu::BindGroupLayout {
+        self.bind_group_layout.get_or_init(|| {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("mipmap source"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            })
+        })
+    }
+
+    fn pipeline(&self, device: &wgpu::Device, format: wgpu::TextureFormat) -> wgpu::RenderPipeline {
+        let mut pipelines = self.pipelines.lock().unwrap();
+        let pipeline = pipelines.entry(format).or_insert_with(|| {
+            let shader = self.shader.get_or_init(|| {
+                create_wgsl_shader_module(device, "mipmap", include_str!("./shaders/mipmap.wgsl"))
+            });
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("mipmap"),
+                bind_group_layouts: &[self.bind_group_layout(device)],
+                push_constant_ranges: &[],
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("mipmap"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: shader,
+                    entry_point: Some("vs_main"),
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader,
+                    entry_point: Some("fs_main"),
+                    compilation_options: Default::default(),
+                    targets: &[Some(format.into())],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        });
+        pipeline.clone()
+    }
+
+    fn compute_pipeline(
+        &self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        storage_format: &str,
+    ) -> wgpu::ComputePipeline {
+        let mut compute_pipelines = self.compute_pipelines.lock().unwrap();
+        let pipeline = compute_pipelines.entry(format).or_insert_with(|| {
+            let source = include_str!("./shaders/mipmap_compute.wgsl")
+                .replace("STORAGE_FORMAT", storage_format);
+            let shader = create_wgsl_shader_module(device, "mipmap", &source);
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("mipmap"),
+                layout: None,
+                module: &shader,
+                entry_point: Some("cs_main"),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        });
+        pipeline.clone()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum GenerateMipmapsError {
+    /// The texture lacks these usages.
+    MissingUsage(wgpu::TextureUsages),
+    /// The format doesn't have a float sample type, or is neither renderable nor supports
+    /// storage.
+    UnsupportedFormat(wgpu::TextureFormat),
+}
+
+impl fmt::Display for GenerateMipmapsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingUsage(usage) => {
+                write!(
+                    f,
+                    "texture is missing usages {usage:?} for generating mipmaps"
+                )
+            }
+            Self::UnsupportedFormat(format) => {
+                write!(
+                    f,
+                    "unable to generate mipmaps for texture format {format:?}"
+                )
+            }
+        }
+    }
+}
+
+impl Error for GenerateMipmapsError {}
@@ -0,0 +1,246 @@
+use std::{
+    collections::{HashMap, hash_map},
+    error::Error,
+    fmt,
+    io::{self, BufRead, Read},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{Context, Vertex3dUV, meshes};
+
+/// A Wavefront OBJ model, split into one mesh per material.
+#[derive(Debug, Clone)]
+pub struct ObjModel {
+    pub meshes: Vec<ObjMesh>,
+}
+
+/// Triangulated, indexed geometry of all faces in an OBJ file that share one material.
+#[derive(Debug, Clone)]
+pub struct ObjMesh {
+    /// Name given by `usemtl`, `None` for faces declared before any `usemtl`.
+    pub material_name: Option<String>,
+    /// The material as loaded from the MTL libraries, if it was found.
+    pub material: Option<Arc<obj::Material>>,
+    /// UVs are flipped vertically to match wgpu's texture coordinates. Vertices without UV have
+    /// their UV set to zero.
+    pub vertices: Vec<Vertex3dUV>,
+    /// Per-vertex normals, parallel to `vertices`.
+    /// Empty if any face of the mesh doesn't specify normals.
+    pub normals: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+}
+
+impl ObjMesh {
+    pub fn create_mesh(&self, context: &Context) -> meshes::Mesh3D {
+        meshes::Mesh3D::create(context, &self.vertices, &self.indices)
+    }
+}
+
+impl ObjModel {
+    /// Loads an OBJ file and the MTL libraries it references, which are looked up relative to the
+    /// directory of the OBJ file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LoadObjError> {
+        let mut obj = obj::Obj::load(path).map_err(LoadObjError::Obj)?;
+        obj.load_mtls().map_err(LoadObjError::Mtl)?;
+        Self::from_obj_data(&obj.data)
+    }
+
+    /// Loads an OBJ file from a reader, without loading any MTL libraries.
+    pub fn from_reader(reader: impl Read) -> Result<Self, LoadObjError> {
+        let data = obj::ObjData::load_buf(reader).map_err(LoadObjError::Obj)?;
+        Self::from_obj_data(&data)
+    }
+
+    /// Loads an OBJ file from a reader, with `resolve_mtl` providing the content of the MTL
+    /// libraries by their names as referenced in `mtllib`.
+    pub fn from_reader_with_mtls<R: BufRead>(
+        reader: impl Read,
+        mut resolve_mtl: impl FnMut(&str) -> io::Result<R>,
+    ) -> Result<Self, LoadObjError> {
+        let data = obj::ObjData::load_buf(reader).map_err(LoadObjError::Obj)?;
+        let mut obj = obj::Obj {
+            data,
+            path: Default::default(),
+        };
+        obj.load_mtls_fn(|_, name| resolve_mtl(name))
+            .map_err(LoadObjError::Mtl)?;
+        Self::from_obj_data(&obj.data)
+    }
+
+    /// Triangulates and de-duplicates the faces in `data`.
+    pub fn from_obj_data(data: &obj::ObjData) -> Result<Self, LoadObjError> {
+        let mut builders: Vec<ObjMeshBuilder> = Vec::new();
+        let mut builder_indices: HashMap<Option<&str>, usize> = HashMap::new();
+        let groups = data.objects.iter().flat_map(|object| &object.groups);
+        for group in groups {
+            let (material_name, material) = match &group.material {
+                Some(obj::ObjMaterial::Ref(name)) => (Some(name.as_str()), None),
+                Some(obj::ObjMaterial::Mtl(material)) => {
+                    (Some(material.name.as_str()), Some(material))
+                }
+                None => (None, None),
+            };
+            let index = match builder_indices.entry(material_name) {
+                hash_map::Entry::Occupied(entry) => *entry.get(),
+                hash_map::Entry::Vacant(entry) => {
+                    builders.push(ObjMeshBuilder::new(material_name, material.cloned()));
+                    *entry.insert(builders.len() - 1)
+                }
+            };
+            let builder = &mut builders[index];
+            for polygon in &group.polys {
+                builder.add_polygon(data, &polygon.0)?;
+            }
+        }
+        Ok(Self {
+            meshes: builders.into_iter().map(ObjMeshBuilder::finish).collect(),
+        })
+    }
+}
+
+struct ObjMeshBuilder {
+    mesh: ObjMesh,
+    has_normals: bool,
+    vertex_indices: HashMap<obj::IndexTuple, u32>,
+}
+
+impl ObjMeshBuilder {
+    fn new(material_name: Option<&str>, material: Option<Arc<obj::Material>>) -> Self {
+        Self {
+            mesh: ObjMesh {
+                material_name: material_name.map(String::from),
+                material,
+                vertices: Vec::new(),
+                normals: Vec::new(),
+                indices: Vec::new(),
+            },
+            has_normals: true,
+            vertex_indices: HashMap::new(),
+        }
+    }
+
+    fn add_polygon(
+        &mut self,
+        data: &obj::ObjData,
+        polygon: &[obj::IndexTuple],
+    ) -> Result<(), LoadObjError> {
+        if polygon.len() < 3 {
+            return Err(LoadObjError::DegeneratePolygon {
+                vertex_count: polygon.len(),
+            });
+        }
+        let indices = polygon
+            .iter()
+            .map(|&index_tuple| self.vertex(data, index_tuple))
+            .collect::<Result<Vec<u32>, _>>()?;
+        // Triangle fan, assumes the polygon is convex.
+        for i in 1..indices.len() - 1 {
+            self.mesh
+                .indices
+                .extend([indices[0], indices[i], indices[i + 1]]);
+        }
+        Ok(())
+    }
+
+    fn vertex(
+        &mut self,
+        data: &obj::ObjData,
+        index_tuple: obj::IndexTuple,
+    ) -> Result<u32, LoadObjError> {
+        let vacant_entry = match self.vertex_indices.entry(index_tuple) {
+            hash_map::Entry::Occupied(entry) => return Ok(*entry.get()),
+            hash_map::Entry::Vacant(entry) => entry,
+        };
+        let obj::IndexTuple(position_index, uv_index, normal_index) = index_tuple;
+        let position = lookup(&data.position, position_index, ObjAttribute::Position)?;
+        let uv = match uv_index {
+            Some(uv_index) => {
+                let [u, v] = lookup(&data.texture, uv_index, ObjAttribute::Uv)?;
+                [u, 1.0 - v]
+            }
+            None => [0.0, 0.0],
+        };
+        match normal_index {
+            Some(normal_index) => {
+                let normal = lookup(&data.normal, normal_index, ObjAttribute::Normal)?;
+                self.mesh.normals.push(normal);
+            }
+            None => self.has_normals = false,
+        }
+        let index: u32 = self
+            .mesh
+            .vertices
+            .len()
+            .try_into()
+            .map_err(|_| LoadObjError::TooManyVertices)?;
+        self.mesh.vertices.push(Vertex3dUV::new(position, uv));
+        vacant_entry.insert(index);
+        Ok(index)
+    }
+
+    fn finish(mut self) -> ObjMesh {
+        if !self.has_normals {
+            self.mesh.normals.clear();
+        }
+        self.mesh
+    }
+}
+
+fn lookup<T: Copy>(items: &[T], index: usize, attribute: ObjAttribute) -> Result<T, LoadObjError> {
+    items
+        .get(index)
+        .copied()
+        .ok_or(LoadObjError::IndexOutOfRange { attribute, index })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjAttribute {
+    Position,
+    Uv,
+    Normal,
+}
+
+#[derive(Debug)]
+pub enum LoadObjError {
+    /// The OBJ file failed to parse.
+    Obj(obj::ObjError),
+    /// One or more of the MTL libraries failed to load.
+    Mtl(obj::MtlLibsLoadError),
+    /// A face has less than 3 vertices.
+    DegeneratePolygon { vertex_count: usize },
+    /// A face refers to a position, UV or normal that doesn't exist.
+    /// `index` is zero-based.
+    IndexOutOfRange {
+        attribute: ObjAttribute,
+        index: usize,
+    },
+    /// A mesh has more vertices than what `u32` indices can address.
+    TooManyVertices,
+}
+
+impl fmt::Display for LoadObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Obj(error) => write!(f, "{error}"),
+            Self::Mtl(error) => write!(f, "{error}"),
+            Self::DegeneratePolygon { vertex_count } => {
+                write!(f, "face with {vertex_count} vertices, expected at least 3")
+            }
+            Self::IndexOutOfRange { attribute, index } => {
+                write!(f, "{attribute:?} index {} is out of range", index + 1)
+            }
+            Self::TooManyVertices => write!(f, "mesh has too many vertices for u32 indices"),
+        }
+    }
+}
+
+impl Error for LoadObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Obj(error) => Some(error),
+            Self::Mtl(error) => Some(error),
+            _ => None,
+        }
+    }
+}
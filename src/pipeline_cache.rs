@@ -0,0 +1,592 @@
+use std::{
+    any::TypeId,
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use crate::{
+    AsMaterial, AsMesh, SHADOW_MAP_FORMAT, ShaderSource, ShaderValidationError,
+    ShaderValidationErrorKind, ShaderWatcher, preprocess_wgsl_with_imports, shader_module_paths,
+    shader_validation,
+};
+
+/// Identifies a cached shader module. Pipelines refer to shaders by ID rather than by module, so
+/// that they can be rebuilt with a new module when the shader is hot-reloaded.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum ShaderId {
+    Vertex(TypeId),
+    InstancedVertex(TypeId),
+    /// With the sorted defines of the material, see `AsMaterial::shader_defines`.
+    Fragment(TypeId, Vec<&'static str>),
+}
+
+#[derive(Debug, Clone)]
+struct CachedShader {
+    /// Type name of the mesh or material.
+    label: &'static str,
+    /// `Err` if the source doesn't preprocess or compile, which creating objects with the shader
+    /// returns.
+    module: Result<wgpu::ShaderModule, ShaderValidationError>,
+    /// The current preprocessed WGSL source, which differs from the embedded one after hot
+    /// reloads. `None` for shaders created by `AsMesh::create_vertex_shader` or
+    /// `AsMaterial::create_fragment_shader`.
+    wgsl: Option<Arc<str>>,
+    /// The embedded WGSL source, to preprocess it again when a module it imports changes.
+    source: Option<&'static str>,
+    /// Defines the source was preprocessed with, to preprocess it the same way when reloading.
+    defines: Vec<&'static str>,
+    /// Canonical path of the file of the shader, for hot reloading.
+    path: Option<PathBuf>,
+    /// Shader modules the last successfully preprocessed source imported, directly or through
+    /// other modules, for hot reloading.
+    imports: HashSet<&'static str>,
+}
+
+impl CachedShader {
+    /// Preprocesses and compiles `source` if there is one, otherwise calls `create`. Imported
+    /// modules are read from their files if `read_module_files`.
+    fn create(
+        device: &wgpu::Device,
+        label: &'static str,
+        source: Option<ShaderSource>,
+        defines: &[&'static str],
+        read_module_files: bool,
+        create: impl FnOnce() -> Option<wgpu::ShaderModule>,
+    ) -> Option<Self> {
+        let Some(source) = source else {
+            return Some(Self {
+                label,
+                module: Ok(create()?),
+                wgsl: None,
+                source: None,
+                defines: Vec::new(),
+                path: None,
+                imports: HashSet::new(),
+            });
+        };
+        let (module, wgsl, imports) =
+            match compile_wgsl(device, label, source.wgsl(), defines, read_module_files) {
+                Ok((module, wgsl, imports)) => (Ok(module), Some(wgsl), imports),
+                Err(error) => (Err(error), None, HashSet::new()),
+            };
+        Some(Self {
+            label,
+            module,
+            wgsl,
+            source: Some(source.wgsl()),
+            defines: defines.to_vec(),
+            path: source.path().and_then(|path| fs::canonicalize(path).ok()),
+            imports,
+        })
+    }
+}
+
+/// Everything a render pipeline of an object depends on.
+///
+/// Bind group layouts are compared by identity, which is why they are cached as well, instead of
+/// being created for every mesh and material.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct RenderPipelineKey {
+    pub(crate) vertex_shader: ShaderId,
+    pub(crate) fragment_shader: ShaderId,
+    pub(crate) camera_bind_group_layout: wgpu::BindGroupLayout,
+    pub(crate) mesh_bind_group_layout: wgpu::BindGroupLayout,
+    pub(crate) material_bind_group_layout: wgpu::BindGroupLayout,
+    pub(crate) lights_bind_group_layout: wgpu::BindGroupLayout,
+    /// The mesh's vertex buffer layout, followed by the one of `Instance` for instanced objects.
+    pub(crate) vertex_buffer_layouts: Vec<wgpu::VertexBufferLayout<'static>>,
+    pub(crate) blend_state: Option<wgpu::BlendState>,
+    /// Disabled for blended objects, so that they don't hide blended objects behind them.
+    pub(crate) depth_write_enabled: bool,
+    pub(crate) color_format: wgpu::TextureFormat,
+    pub(crate) depth_stencil_format: wgpu::TextureFormat,
+}
+
+/// Everything a depth-only pipeline for rendering an object into shadow maps depends on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ShadowPipelineKey {
+    pub(crate) vertex_shader: ShaderId,
+    pub(crate) camera_bind_group_layout: wgpu::BindGroupLayout,
+    pub(crate) mesh_bind_group_layout: wgpu::BindGroupLayout,
+    pub(crate) vertex_buffer_layouts: Vec<wgpu::VertexBufferLayout<'static>>,
+    /// `Some` for objects with `AlphaMode::Mask` materials.
+    pub(crate) alpha_test: Option<ShadowAlphaTest>,
+}
+
+/// The material's fragment shader, run in shadow passes only for discarding the fragments below
+/// the alpha cutoff.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ShadowAlphaTest {
+    pub(crate) fragment_shader: ShaderId,
+    pub(crate) material_bind_group_layout: wgpu::BindGroupLayout,
+    pub(crate) lights_bind_group_layout: wgpu::BindGroupLayout,
+}
+
+/// A cached render pipeline, shared by all objects using it, so that it can be replaced for all of
+/// them when one of its shaders is hot-reloaded.
+#[derive(Debug, Clone)]
+pub(crate) struct SharedRenderPipeline(Arc<RwLock<wgpu::RenderPipeline>>);
+
+impl SharedRenderPipeline {
+    fn new(pipeline: wgpu::RenderPipeline) -> Self {
+        Self(Arc::new(RwLock::new(pipeline)))
+    }
+
+    pub(crate) fn get(&self) -> wgpu::RenderPipeline {
+        self.0.read().unwrap().clone()
+    }
+
+    fn replace(&self, pipeline: wgpu::RenderPipeline) {
+        *self.0.write().unwrap() = pipeline;
+    }
+}
+
+/// Caches shader modules by mesh and material types, bind group layouts by their entries, and
+/// render pipelines by `RenderPipelineKey`, so that objects of the same kind share them.
+#[derive(Debug, Default)]
+pub(crate) struct RenderPipelineCache {
+    /// `None` for instanced vertex shaders of meshes that don't support instancing.
+    shaders: Mutex<HashMap<ShaderId, Option<CachedShader>>>,
+    bind_group_layouts: Mutex<HashMap<Vec<wgpu::BindGroupLayoutEntry>, wgpu::BindGroupLayout>>,
+    render_pipelines: Mutex<HashMap<RenderPipelineKey, SharedRenderPipeline>>,
+    shadow_pipelines: Mutex<HashMap<ShadowPipelineKey, SharedRenderPipeline>>,
+    /// `Some` if hot reloading is enabled.
+    shader_watcher: Mutex<Option<ShaderWatcher>>,
+}
+
+impl RenderPipelineCache {
+    pub(crate) fn vertex_shader<Mesh: AsMesh>(&self, device: &wgpu::Device) -> ShaderId {
+        let id = ShaderId::Vertex(TypeId::of::<Mesh>());
+        self.shader(id, |read_module_files| {
+            let label = std::any::type_name::<Mesh>();
+            let source = Mesh::vertex_shader_source();
+            CachedShader::create(device, label, source, &[], read_module_files, || {
+                Some(Mesh::create_vertex_shader(device))
+            })
+        })
+        .expect("vertex shaders are always created")
+    }
+
+    pub(crate) fn instanced_vertex_shader<Mesh: AsMesh>(
+        &self,
+        device: &wgpu::Device,
+    ) -> Option<ShaderId> {
+        let id = ShaderId::InstancedVertex(TypeId::of::<Mesh>());
+        self.shader(id, |read_module_files| {
+            let label = std::any::type_name::<Mesh>();
+            let source = Mesh::instanced_vertex_shader_source();
+            CachedShader::create(device, label, source, &[], read_module_files, || {
+                Mesh::create_instanced_vertex_shader(device)
+            })
+        })
+    }
+
+    pub(crate) fn fragment_shader<Material: AsMaterial>(
+        &self,
+        device: &wgpu::Device,
+        mut defines: Vec<&'static str>,
+    ) -> ShaderId {
+        defines.sort_unstable();
+        defines.dedup();
+        let id = ShaderId::Fragment(TypeId::of::<Material>(), defines.clone());
+        self.shader(id, |read_module_files| {
+            let label = std::any::type_name::<Material>();
+            let source = Material::fragment_shader_source();
+            CachedShader::create(device, label, source, &defines, read_module_files, || {
+                Some(Material::create_fragment_shader(device))
+            })
+        })
+        .expect("fragment shaders are always created")
+    }
+
+    /// Creates the shader with `create` if it isn't cached yet, passing whether hot reloading is
+    /// enabled.
+    fn shader(
+        &self,
+        id: ShaderId,
+        create: impl FnOnce(bool) -> Option<CachedShader>,
+    ) -> Option<ShaderId> {
+        let mut shaders = self.shaders.lock().unwrap();
+        let shader = shaders.entry(id.clone()).or_insert_with(|| {
+            let mut shader_watcher = self.shader_watcher.lock().unwrap();
+            let shader = create(shader_watcher.is_some())?;
+            if let (Some(path), Some(watcher)) = (&shader.path, shader_watcher.as_mut()) {
+                watcher.watch(path);
+            }
+            Some(shader)
+        });
+        shader.as_ref().map(|_| id)
+    }
+
+    fn shader_module(&self, id: &ShaderId) -> wgpu::ShaderModule {
+        let shaders = self.shaders.lock().unwrap();
+        shaders[id]
+            .as_ref()
+            .expect("shader IDs are only handed out for created shaders")
+            .module
+            .clone()
+            .expect("pipelines are only created for shaders that compile")
+    }
+
+    /// Why a shader created from WGSL source doesn't preprocess or compile.
+    pub(crate) fn shader_error(&self, id: &ShaderId) -> Option<ShaderValidationError> {
+        let shaders = self.shaders.lock().unwrap();
+        shaders[id].as_ref()?.module.as_ref().err().cloned()
+    }
+
+    /// The current WGSL source of a shader, `None` if it wasn't created from WGSL source.
+    pub(crate) fn shader_wgsl(&self, id: &ShaderId) -> Option<Arc<str>> {
+        let shaders = self.shaders.lock().unwrap();
+        shaders[id].as_ref()?.wgsl.clone()
+    }
+
+    pub(crate) fn bind_group_layout(
+        &self,
+        device: &wgpu::Device,
+        label: Option<&str>,
+        entries: Vec<wgpu::BindGroupLayoutEntry>,
+    ) -> wgpu::BindGroupLayout {
+        let mut bind_group_layouts = self.bind_group_layouts.lock().unwrap();
+        bind_group_layouts
+            .entry(entries)
+            .or_insert_with_key(|entries| {
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor { label, entries })
+            })
+            .clone()
+    }
+
+    pub(crate) fn render_pipeline(
+        &self,
+        device: &wgpu::Device,
+        key: RenderPipelineKey,
+    ) -> SharedRenderPipeline {
+        let mut render_pipelines = self.render_pipelines.lock().unwrap();
+        render_pipelines
+            .entry(key)
+            .or_insert_with_key(|key| {
+                let vertex_shader = self.shader_module(&key.vertex_shader);
+                let fragment_shader = self.shader_module(&key.fragment_shader);
+                SharedRenderPipeline::new(create_render_pipeline(
+                    device,
+                    key,
+                    &vertex_shader,
+                    &fragment_shader,
+                ))
+            })
+            .clone()
+    }
+
+    pub(crate) fn contains_render_pipeline(&self, key: &RenderPipelineKey) -> bool {
+        self.render_pipelines.lock().unwrap().contains_key(key)
+    }
+
+    pub(crate) fn shadow_pipeline(
+        &self,
+        device: &wgpu::Device,
+        key: ShadowPipelineKey,
+    ) -> SharedRenderPipeline {
+        let mut shadow_pipelines = self.shadow_pipelines.lock().unwrap();
+        shadow_pipelines
+            .entry(key)
+            .or_insert_with_key(|key| {
+                let vertex_shader = self.shader_module(&key.vertex_shader);
+                let fragment_shader = key
+                    .alpha_test
+                    .as_ref()
+                    .map(|alpha_test| self.shader_module(&alpha_test.fragment_shader));
+                SharedRenderPipeline::new(create_shadow_pipeline(
+                    device,
+                    key,
+                    &vertex_shader,
+                    fragment_shader.as_ref(),
+                ))
+            })
+            .clone()
+    }
+
+    pub(crate) fn render_pipeline_count(&self) -> usize {
+        self.render_pipelines.lock().unwrap().len()
+    }
+
+    /// Starts watching the files of all shaders created from an `include_shader!` or a
+    /// `ShaderSource` with a path, including shaders created later, and the files of the shader
+    /// modules.
+    pub(crate) fn enable_hot_reload(&self) -> notify::Result<()> {
+        let shaders = self.shaders.lock().unwrap();
+        let mut shader_watcher = self.shader_watcher.lock().unwrap();
+        if shader_watcher.is_some() {
+            return Ok(());
+        }
+        let watcher = shader_watcher.insert(ShaderWatcher::new()?);
+        for path in shaders
+            .values()
+            .flatten()
+            .filter_map(|shader| shader.path.as_ref())
+        {
+            watcher.watch(path);
+        }
+        for (_, path) in shader_module_paths() {
+            watcher.watch(&path);
+        }
+        Ok(())
+    }
+
+    /// Recompiles the shaders whose files or imported modules changed, and rebuilds the pipelines
+    /// using them in place. A shader or pipeline that fails to compile is logged and keeps its
+    /// previous version.
+    pub(crate) fn reload_changed_shaders(&self, device: &wgpu::Device) {
+        let changed_files = match self.shader_watcher.lock().unwrap().as_ref() {
+            Some(watcher) => watcher.changed_files(),
+            None => return,
+        };
+        if changed_files.is_empty() {
+            return;
+        }
+        let changed_modules: HashSet<&str> = shader_module_paths()
+            .filter(|(_, path)| changed_files.contains(path))
+            .map(|(name, _)| name)
+            .collect();
+        let mut reloaded_shaders = HashSet::new();
+        for (id, shader) in self.shaders.lock().unwrap().iter_mut() {
+            let Some(shader) = shader else {
+                continue;
+            };
+            let Some(source) = shader.source else {
+                continue;
+            };
+            let is_file_changed = shader
+                .path
+                .as_ref()
+                .is_some_and(|path| changed_files.contains(path));
+            if !is_file_changed && shader.imports.is_disjoint(&changed_modules) {
+                continue;
+            }
+            match reload_shader(device, shader, source) {
+                Ok((module, wgsl, imports)) => {
+                    log::info!("reloaded shader of {}", shader.label);
+                    shader.module = Ok(module);
+                    shader.wgsl = Some(wgsl);
+                    shader.imports = imports;
+                    reloaded_shaders.insert(id.clone());
+                }
+                Err(error) => log::error!("cannot reload shader of {}: {error}", shader.label),
+            }
+        }
+        if reloaded_shaders.is_empty() {
+            return;
+        }
+        for (key, pipeline) in self.render_pipelines.lock().unwrap().iter() {
+            if !reloaded_shaders.contains(&key.vertex_shader)
+                && !reloaded_shaders.contains(&key.fragment_shader)
+            {
+                continue;
+            }
+            let vertex_shader = self.shader_module(&key.vertex_shader);
+            let fragment_shader = self.shader_module(&key.fragment_shader);
+            match capture_validation_error(device, || {
+                create_render_pipeline(device, key, &vertex_shader, &fragment_shader)
+            }) {
+                Ok(new_pipeline) => pipeline.replace(new_pipeline),
+                Err(error) => log::error!("cannot rebuild pipeline after shader reload: {error}"),
+            }
+        }
+        for (key, pipeline) in self.shadow_pipelines.lock().unwrap().iter() {
+            let fragment_shader = key
+                .alpha_test
+                .as_ref()
+                .map(|alpha_test| &alpha_test.fragment_shader);
+            if !reloaded_shaders.contains(&key.vertex_shader)
+                && !fragment_shader.is_some_and(|shader| reloaded_shaders.contains(shader))
+            {
+                continue;
+            }
+            let vertex_shader = self.shader_module(&key.vertex_shader);
+            let fragment_shader = fragment_shader.map(|shader| self.shader_module(shader));
+            match capture_validation_error(device, || {
+                create_shadow_pipeline(device, key, &vertex_shader, fragment_shader.as_ref())
+            }) {
+                Ok(new_pipeline) => pipeline.replace(new_pipeline),
+                Err(error) => {
+                    log::error!("cannot rebuild shadow pipeline after shader reload: {error}")
+                }
+            }
+        }
+    }
+}
+
+/// Preprocesses and compiles a shader again, from its file if it has one, otherwise from
+/// `embedded_source`, with the imported modules read from their files.
+fn reload_shader(
+    device: &wgpu::Device,
+    shader: &CachedShader,
+    embedded_source: &str,
+) -> Result<CompiledShader, String> {
+    let source = match &shader.path {
+        Some(path) => fs::read_to_string(path).map_err(|error| error.to_string())?,
+        None => embedded_source.to_owned(),
+    };
+    compile_wgsl(device, shader.label, &source, &shader.defines, true)
+        .map_err(|error| error.to_string())
+}
+
+/// A compiled shader module, its preprocessed WGSL source and the modules it imported.
+type CompiledShader = (wgpu::ShaderModule, Arc<str>, HashSet<&'static str>);
+
+/// Preprocesses and compiles WGSL source. Imported modules are read from their files if
+/// `read_module_files`.
+fn compile_wgsl(
+    device: &wgpu::Device,
+    label: &'static str,
+    source: &str,
+    defines: &[&'static str],
+    read_module_files: bool,
+) -> Result<CompiledShader, ShaderValidationError> {
+    let error = |kind| ShaderValidationError {
+        type_name: label,
+        kind,
+    };
+    let (wgsl, imports) = preprocess_wgsl_with_imports(source, defines, read_module_files)
+        .map_err(|preprocess_error| {
+            error(ShaderValidationErrorKind::Preprocess(preprocess_error))
+        })?;
+    // Checked with naga first for readable errors, wgpu's error scope catches the rest.
+    shader_validation::parse_and_validate(label, &wgsl)?;
+    let module =
+        capture_validation_error(device, || create_wgsl_shader_module(device, label, &wgsl))
+            .map_err(|wgpu_error| {
+                error(ShaderValidationErrorKind::Invalid(wgpu_error.to_string()))
+            })?;
+    Ok((module, wgsl.into(), imports))
+}
+
+/// Runs `f`, returning the validation error it causes instead of passing it to the device's
+/// uncaptured error handler, which panics by default.
+fn capture_validation_error<T>(
+    device: &wgpu::Device,
+    f: impl FnOnce() -> T,
+) -> Result<T, wgpu::Error> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let result = f();
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(error) => Err(error),
+        None => Ok(result),
+    }
+}
+
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    key: &RenderPipelineKey,
+    vertex_shader: &wgpu::ShaderModule,
+    fragment_shader: &wgpu::ShaderModule,
+) -> wgpu::RenderPipeline {
+    let bind_group_layouts: &[&wgpu::BindGroupLayout] = &[
+        &key.camera_bind_group_layout,
+        &key.mesh_bind_group_layout,
+        &key.material_bind_group_layout,
+        &key.lights_bind_group_layout,
+    ];
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: vertex_shader,
+            entry_point: Some("vs_main"),
+            buffers: &key.vertex_buffer_layouts,
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: fragment_shader,
+            entry_point: Some("fs_main"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: key.color_format,
+                blend: key.blend_state,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: key.depth_stencil_format,
+            depth_write_enabled: key.depth_write_enabled,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: Default::default(),
+            bias: Default::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Runs the vertex shader of the mesh, with the light's view and projection in place of the
+/// camera's. The fragment shader of the material only runs for alpha testing, without color
+/// targets.
+fn create_shadow_pipeline(
+    device: &wgpu::Device,
+    key: &ShadowPipelineKey,
+    vertex_shader: &wgpu::ShaderModule,
+    fragment_shader: Option<&wgpu::ShaderModule>,
+) -> wgpu::RenderPipeline {
+    let mut bind_group_layouts = vec![&key.camera_bind_group_layout, &key.mesh_bind_group_layout];
+    if let Some(alpha_test) = &key.alpha_test {
+        bind_group_layouts.extend([
+            &alpha_test.material_bind_group_layout,
+            &alpha_test.lights_bind_group_layout,
+        ]);
+    }
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: vertex_shader,
+            entry_point: Some("vs_main"),
+            buffers: &key.vertex_buffer_layouts,
+            compilation_options: Default::default(),
+        },
+        fragment: fragment_shader.map(|module| wgpu::FragmentState {
+            module,
+            entry_point: Some("fs_main"),
+            targets: &[],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: SHADOW_MAP_FORMAT.into(),
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: Default::default(),
+            // Against shadow acne, i.e. surfaces shadowing themselves.
+            bias: wgpu::DepthBiasState {
+                constant: 2,
+                slope_scale: 2.0,
+                clamp: 0.0,
+            },
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+pub(crate) fn create_wgsl_shader_module(
+    device: &wgpu::Device,
+    label: &str,
+    source: &str,
+) -> wgpu::ShaderModule {
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    })
+}
@@ -0,0 +1,361 @@
+use std::{
+    error::Error,
+    fmt,
+    fs::File,
+    future::Future,
+    io::{self, BufWriter, Write},
+    path::Path,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{self, Poll, Waker},
+};
+
+use cgmath::*;
+
+use crate::{Context, Surface, Texture2d_, TextureFormat, TextureFormatTrait};
+
+/// Pixels read back from a texture, tightly packed (no row padding), row by row from the top.
+#[derive(Debug, Clone)]
+pub struct Pixels<Format: TextureFormatTrait> {
+    format: Format,
+    size: Vector2<u32>,
+    bytes: Vec<u8>,
+}
+
+impl<Format: TextureFormatTrait> Pixels<Format> {
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    pub fn wgpu_format(&self) -> wgpu::TextureFormat {
+        self.format().into()
+    }
+
+    pub fn size(&self) -> Vector2<u32> {
+        self.size
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Encodes the pixels as PNG.
+    ///
+    /// Supports `R8Unorm`, `Rgba8Unorm(Srgb)`, `Bgra8Unorm(Srgb)` and `Rgba16Unorm`.
+    pub fn write_png(&self, writer: impl Write) -> Result<(), SavePngError> {
+        let wgpu_format = self.wgpu_format();
+        let (color_type, bit_depth, data) = match wgpu_format {
+            wgpu::TextureFormat::R8Unorm => (
+                png::ColorType::Grayscale,
+                png::BitDepth::Eight,
+                self.bytes.clone(),
+            ),
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => (
+                png::ColorType::Rgba,
+                png::BitDepth::Eight,
+                self.bytes.clone(),
+            ),
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => {
+                let mut data = self.bytes.clone();
+                data.chunks_exact_mut(4).for_each(|pixel| pixel.swap(0, 2));
+                (png::ColorType::Rgba, png::BitDepth::Eight, data)
+            }
+            wgpu::TextureFormat::Rgba16Unorm => {
+                // PNG stores 16 bit samples in big endian.
+                let mut data = self.bytes.clone();
+                data.chunks_exact_mut(2)
+                    .for_each(|sample| sample.swap(0, 1));
+                (png::ColorType::Rgba, png::BitDepth::Sixteen, data)
+            }
+            _ => return Err(SavePngError::UnsupportedFormat(wgpu_format)),
+        };
+        let mut encoder = png::Encoder::new(writer, self.size.x, self.size.y);
+        encoder.set_color(color_type);
+        encoder.set_depth(bit_depth);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Saves the pixels as a PNG file. See `write_png` for supported formats.
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), SavePngError> {
+        let file = File::create(path).map_err(SavePngError::Io)?;
+        self.write_png(BufWriter::new(file))
+    }
+}
+
+#[derive(Debug)]
+pub enum ReadPixelsError {
+    /// The texture wasn't created with `wgpu::TextureUsages::COPY_SRC`.
+    MissingCopySrcUsage,
+    /// The format can't be copied out of a texture (e.g. `Depth24Plus`).
+    UnsupportedFormat(wgpu::TextureFormat),
+    Map(wgpu::BufferAsyncError),
+    Poll(wgpu::PollError),
+}
+
+impl fmt::Display for ReadPixelsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingCopySrcUsage => write!(f, "texture is missing the COPY_SRC usage"),
+            Self::UnsupportedFormat(format) => {
+                write!(f, "texture format {format:?} cannot be copied to a buffer")
+            }
+            Self::Map(error) => write!(f, "unable to map staging buffer: {error}"),
+            Self::Poll(error) => write!(f, "unable to poll device: {error}"),
+        }
+    }
+}
+
+impl Error for ReadPixelsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Map(error) => Some(error),
+            Self::Poll(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SavePngError {
+    UnsupportedFormat(wgpu::TextureFormat),
+    Io(io::Error),
+    Encoding(png::EncodingError),
+}
+
+impl From<png::EncodingError> for SavePngError {
+    fn from(value: png::EncodingError) -> Self {
+        Self::Encoding(value)
+    }
+}
+
+impl fmt::Display for SavePngError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedFormat(format) => {
+                write!(f, "texture format {format:?} cannot be saved as PNG")
+            }
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Encoding(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl Error for SavePngError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::UnsupportedFormat(_) => None,
+            Self::Io(error) => Some(error),
+            Self::Encoding(error) => Some(error),
+        }
+    }
+}
+
+impl<Format: TextureFormatTrait> Texture2d_<Format> {
+    /// Copies the texture into a staging buffer and reads it back.
+    ///
+    /// The copy is submitted immediately, but the returned future only resolves after the device
+    /// has been polled (see `wgpu::Device::poll`). Use `read_pixels` for a blocking version.
+    ///
+    /// For depth-stencil textures, the depth aspect is read if the format has one, otherwise the
+    /// stencil aspect.
+    pub fn read_pixels_async(
+        &self,
+        context: &Context,
+    ) -> impl Future<Output = Result<Pixels<Format>, ReadPixelsError>> + use<Format> {
+        self.read_mip_level_pixels_async(context, 0)
+    }
+
+    /// Like `read_pixels_async`, but reads a mip level other than the first.
+    ///
+    /// # Panics
+    ///
+    /// - if the texture doesn't have this mip level
+    pub fn read_mip_level_pixels_async(
+        &self,
+        context: &Context,
+        mip_level: u32,
+    ) -> impl Future<Output = Result<Pixels<Format>, ReadPixelsError>> + use<Format> {
+        assert!(
+            mip_level < self.mip_level_count(),
+            "mip level {mip_level} out of range for a texture with {} mip levels",
+            self.mip_level_count(),
+        );
+        let staging = StagingBuffer::copy_from_texture(context, self, mip_level);
+        let format = self.format();
+        let size = self.mip_level_size(mip_level);
+        async move {
+            let mut staging = staging?;
+            (&mut staging.mapped).await.map_err(ReadPixelsError::Map)?;
+            let bytes = staging.unpadded_bytes(size.y);
+            Ok(Pixels {
+                format,
+                size,
+                bytes,
+            })
+        }
+    }
+
+    /// Blocking version of `read_pixels_async`.
+    pub fn read_pixels(&self, context: &Context) -> Result<Pixels<Format>, ReadPixelsError> {
+        self.read_mip_level_pixels(context, 0)
+    }
+
+    /// Blocking version of `read_mip_level_pixels_async`.
+    pub fn read_mip_level_pixels(
+        &self,
+        context: &Context,
+        mip_level: u32,
+    ) -> Result<Pixels<Format>, ReadPixelsError> {
+        let future = self.read_mip_level_pixels_async(context, mip_level);
+        context
+            .wgpu_device()
+            .poll(wgpu::PollType::Wait)
+            .map_err(ReadPixelsError::Poll)?;
+        pollster::block_on(future)
+    }
+}
+
+impl Surface {
+    /// Reads back the color texture of the surface. See `Texture2d_::read_pixels_async`.
+    pub fn read_pixels_async(
+        &self,
+        context: &Context,
+    ) -> impl Future<Output = Result<Pixels<TextureFormat>, ReadPixelsError>> + use<> {
+        self.color_texture().read_pixels_async(context)
+    }
+
+    /// Reads back the color texture of the surface. See `Texture2d_::read_pixels`.
+    pub fn read_pixels(&self, context: &Context) -> Result<Pixels<TextureFormat>, ReadPixelsError> {
+        self.color_texture().read_pixels(context)
+    }
+}
+
+struct StagingBuffer {
+    wgpu_buffer: wgpu::Buffer,
+    unpadded_bytes_per_row: u32,
+    padded_bytes_per_row: u32,
+    mapped: MapFuture,
+}
+
+impl StagingBuffer {
+    fn copy_from_texture<Format: TextureFormatTrait>(
+        context: &Context,
+        texture: &Texture2d_<Format>,
+        mip_level: u32,
+    ) -> Result<Self, ReadPixelsError> {
+        if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            return Err(ReadPixelsError::MissingCopySrcUsage);
+        }
+        let wgpu_format = texture.wgpu_format();
+        let aspect = if wgpu_format.has_depth_aspect() {
+            wgpu::TextureAspect::DepthOnly
+        } else if wgpu_format.has_stencil_aspect() {
+            wgpu::TextureAspect::StencilOnly
+        } else {
+            wgpu::TextureAspect::All
+        };
+        let bytes_per_pixel = wgpu_format
+            .block_copy_size(Some(aspect))
+            .ok_or(ReadPixelsError::UnsupportedFormat(wgpu_format))?;
+        let size = texture.mip_level_size(mip_level);
+        let unpadded_bytes_per_row = size.x * bytes_per_pixel;
+        let padded_bytes_per_row =
+            unpadded_bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let device = context.wgpu_device();
+        let wgpu_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: u64::from(padded_bytes_per_row) * u64::from(size.y),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: texture.wgpu_texture(),
+                mip_level,
+                origin: wgpu::Origin3d::ZERO,
+                aspect,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &wgpu_buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(size.y),
+                },
+            },
+            wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+        context.wgpu_queue().submit([encoder.finish()]);
+
+        let mapped = MapFuture::default();
+        let state = Arc::clone(&mapped.state);
+        wgpu_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let mut state = state.lock().unwrap();
+                state.result = Some(result);
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+            });
+
+        Ok(Self {
+            wgpu_buffer,
+            unpadded_bytes_per_row,
+            padded_bytes_per_row,
+            mapped,
+        })
+    }
+
+    /// Must only be called after the buffer has been mapped.
+    fn unpadded_bytes(&self, rows: u32) -> Vec<u8> {
+        let mapped_range = self.wgpu_buffer.slice(..).get_mapped_range();
+        let mut bytes = Vec::with_capacity((self.unpadded_bytes_per_row * rows) as usize);
+        for row in mapped_range.chunks_exact(self.padded_bytes_per_row as usize) {
+            bytes.extend_from_slice(&row[..self.unpadded_bytes_per_row as usize]);
+        }
+        drop(mapped_range);
+        self.wgpu_buffer.unmap();
+        bytes
+    }
+}
+
+#[derive(Default)]
+struct MapState {
+    result: Option<Result<(), wgpu::BufferAsyncError>>,
+    waker: Option<Waker>,
+}
+
+/// Resolves when the callback of `wgpu::BufferSlice::map_async` is called.
+#[derive(Default)]
+struct MapFuture {
+    state: Arc<Mutex<MapState>>,
+}
+
+impl Future for MapFuture {
+    type Output = Result<(), wgpu::BufferAsyncError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -1,14 +1,19 @@
 use std::{
     collections::{HashMap, hash_map},
     fmt::Debug,
-    ops::DerefMut,
 };
 
+use bytemuck::Zeroable as _;
 use cgmath::*;
 
 use crate::{
-    CameraBindGroup, CameraRef, Context, DepthStencilTextureFormat, MaterialRef, MeshRef,
-    ObjectRef, SurfaceView, TextureFormat, binding,
+    AlphaMode, AsBindGroup as _, CameraBindGroup, CameraRef, CameraUniform, Context,
+    DepthStencilTextureFormat, Environment, Instance, InstanceBuffer, Light, LightRef,
+    LightsBindGroup, LightsUniform, LocalTransform, MAX_LIGHTS, MAX_SHADOW_MAPS, MaterialRef,
+    MeshRef, ObjectRef, ObjectUniform, RenderPassOptions, RenderPipelineKey, Rgba,
+    ShaderValidationError, ShadowAlphaTest, ShadowAtlas, ShadowMap, ShadowMapsUniform,
+    ShadowPipelineKey, SharedRenderPipeline, Skybox, SkyboxStorage, SurfaceView, TextureFormat,
+    Vertex as _, binding, shader_validation, shadow,
 };
 
 #[derive(Debug, Clone)]
@@ -18,75 +23,172 @@ pub(crate) struct ObjectStorage {
     pub(crate) camera: CameraRef,
     pub(crate) mesh: MeshRef,
     pub(crate) material: MaterialRef,
-    pub(crate) pipeline: wgpu::RenderPipeline,
+    pub(crate) pipeline: SharedRenderPipeline,
+    /// Depth-only pipeline for rendering the object into shadow maps.
+    pub(crate) shadow_pipeline: SharedRenderPipeline,
+    /// `Some` for instanced objects.
+    pub(crate) instances: Option<InstanceBuffer>,
+    /// The model matrix relative to the parent object, or to the world for root objects.
     pub(crate) model: Matrix4<f32>,
+    /// Computed from the model matrices of this object and its ancestors.
+    pub(crate) world: Matrix4<f32>,
+    /// Whether `world` needs to be re-computed for this object and its descendants.
+    pub(crate) is_world_dirty: bool,
     pub(crate) is_hidden: bool,
+    /// Whether this object or any of its ancestors is hidden.
+    pub(crate) is_hidden_in_hierarchy: bool,
+    pub(crate) casts_shadows: bool,
+    pub(crate) receives_shadows: bool,
 }
 
 impl ObjectStorage {
     pub(crate) fn new(
         scene: &Scene,
         id: u64,
-        device: &wgpu::Device,
+        context: &Context,
         camera: CameraRef,
         mesh: MeshRef,
         material: MaterialRef,
-    ) -> Self {
+        is_instanced: bool,
+    ) -> Result<Self, ShaderValidationError> {
         let mesh_storage = mesh.lock();
         let material_storage = material.lock();
-        let pipeline = {
-            let bind_group_layouts: &[&wgpu::BindGroupLayout] = &[
-                &binding::create_wgpu_bind_group_layout(device, &scene.camera_bind_group),
-                &mesh_storage.bind_group_layout,
-                &material_storage.bind_group_layout,
+        let (vertex_shader, vertex_buffer_layouts) = if is_instanced {
+            let vertex_shader = mesh_storage
+                .instanced_vertex_shader
+                .clone()
+                .unwrap_or_else(|| panic!("mesh doesn't support instancing"));
+            let vertex_buffer_layouts =
+                vec![mesh_storage.vertex_buffer_layout.clone(), Instance::LAYOUT];
+            (vertex_shader, vertex_buffer_layouts)
+        } else {
+            let vertex_shader = mesh_storage.vertex_shader.clone();
+            (
+                vertex_shader,
+                vec![mesh_storage.vertex_buffer_layout.clone()],
+            )
+        };
+        // The layouts of the scene's own bind groups are equivalent to the cached ones, which are
+        // shared by all scenes, so that their objects can share pipelines.
+        let camera_entries = scene.camera_bind_group.bind_group_layout_entries();
+        let lights_entries = scene.lights_bind_group.bind_group_layout_entries();
+        let pipeline_cache = context.pipeline_cache();
+        let camera_bind_group_layout = pipeline_cache.bind_group_layout(
+            context.wgpu_device(),
+            Some(std::any::type_name::<CameraBindGroup>()),
+            camera_entries.clone(),
+        );
+        let lights_bind_group_layout = pipeline_cache.bind_group_layout(
+            context.wgpu_device(),
+            Some(std::any::type_name::<LightsBindGroup>()),
+            lights_entries.clone(),
+        );
+        let key = RenderPipelineKey {
+            vertex_shader: vertex_shader.clone(),
+            fragment_shader: material_storage.fragment_shader.clone(),
+            camera_bind_group_layout: camera_bind_group_layout.clone(),
+            mesh_bind_group_layout: mesh_storage.bind_group_layout.clone(),
+            material_bind_group_layout: material_storage.bind_group_layout.clone(),
+            lights_bind_group_layout: lights_bind_group_layout.clone(),
+            vertex_buffer_layouts: vertex_buffer_layouts.clone(),
+            blend_state: material_storage.blend_state,
+            depth_write_enabled: material_storage.alpha_mode != AlphaMode::Blend,
+            color_format: scene.surface_color_format.into(),
+            depth_stencil_format: scene.surface_depth_stencil_format.into(),
+        };
+        // Objects of an existing pipeline were already validated.
+        if !pipeline_cache.contains_render_pipeline(&key) {
+            for shader in [&vertex_shader, &material_storage.fragment_shader] {
+                if let Some(error) = pipeline_cache.shader_error(shader) {
+                    return Err(error);
+                }
+            }
+            let bind_groups: [&[wgpu::BindGroupLayoutEntry]; 4] = [
+                &camera_entries,
+                &mesh_storage.bind_group_layout_entries,
+                &material_storage.bind_group_layout_entries,
+                &lights_entries,
             ];
-            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: None,
-                bind_group_layouts,
-                push_constant_ranges: &[],
-            });
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: None,
-                layout: Some(&layout),
-                vertex: wgpu::VertexState {
-                    module: &mesh_storage.vertex_shader,
-                    entry_point: Some("vs_main"),
-                    buffers: &[mesh_storage.vertex_buffer_layout.clone()],
-                    compilation_options: Default::default(),
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &material_storage.fragment_shader,
-                    entry_point: Some("fs_main"),
-                    compilation_options: Default::default(),
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: scene.surface_color_format.into(),
-                        blend: material_storage.blend_state,
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                primitive: wgpu::PrimitiveState::default(),
-                depth_stencil: Some(wgpu::DepthStencilState {
-                    format: wgpu::TextureFormat::Depth32Float,
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::Less,
-                    stencil: Default::default(),
-                    bias: Default::default(),
-                }),
-                multisample: wgpu::MultisampleState::default(),
-                multiview: None,
-                cache: None,
-            })
+            if let Some(source) = pipeline_cache.shader_wgsl(&vertex_shader) {
+                shader_validation::validate_shader(
+                    mesh_storage.type_name,
+                    &source,
+                    naga::ShaderStage::Vertex,
+                    &bind_groups,
+                    &vertex_buffer_layouts,
+                )?;
+            }
+            if let Some(source) = pipeline_cache.shader_wgsl(&material_storage.fragment_shader) {
+                shader_validation::validate_shader(
+                    material_storage.type_name,
+                    &source,
+                    naga::ShaderStage::Fragment,
+                    &bind_groups,
+                    &[],
+                )?;
+            }
+        }
+        let pipeline = pipeline_cache.render_pipeline(context.wgpu_device(), key);
+        let alpha_test = match material_storage.alpha_mode {
+            AlphaMode::Mask { .. } => Some(ShadowAlphaTest {
+                fragment_shader: material_storage.fragment_shader.clone(),
+                material_bind_group_layout: material_storage.bind_group_layout.clone(),
+                lights_bind_group_layout,
+            }),
+            AlphaMode::Opaque | AlphaMode::Blend => None,
         };
+        let shadow_pipeline = pipeline_cache.shadow_pipeline(
+            context.wgpu_device(),
+            ShadowPipelineKey {
+                vertex_shader,
+                camera_bind_group_layout,
+                mesh_bind_group_layout: mesh_storage.bind_group_layout.clone(),
+                vertex_buffer_layouts,
+                alpha_test,
+            },
+        );
         drop((mesh_storage, material_storage));
-        Self {
+        Ok(Self {
             id,
             camera,
             mesh,
             material,
             pipeline,
+            shadow_pipeline,
+            instances: is_instanced.then(InstanceBuffer::new),
             model: Matrix4::identity(),
+            world: Matrix4::identity(),
+            is_world_dirty: true,
             is_hidden: false,
+            is_hidden_in_hierarchy: false,
+            casts_shadows: true,
+            receives_shadows: true,
+        })
+    }
+
+    /// Whether the object may be visible to its camera, i.e. its bounds aren't outside the
+    /// camera's frustum. See `AsMesh::bounds`.
+    fn is_in_frustum(&self, viewport_size: Vector2<f32>) -> bool {
+        if self.instances.is_some() {
+            return true;
         }
+        let Some(bounds) = self.mesh.lock().bounds else {
+            return true;
+        };
+        let frustum = self.camera.lock().frustum(viewport_size);
+        frustum.intersects(&bounds.transform(self.world))
+    }
+
+    /// Distance from the camera along its view direction to the center of the bounds of the
+    /// object, or to its origin if its mesh has no bounds.
+    fn view_depth(&self) -> f32 {
+        let center = self
+            .mesh
+            .lock()
+            .bounds
+            .map_or(Point3::origin(), |bounds| bounds.sphere.center);
+        let view = self.camera.lock().view_matrix();
+        -(view * self.world * center.to_homogeneous()).z
     }
 }
 
@@ -96,13 +198,38 @@ pub struct Scene {
     camera_wgpu_bind_group: wgpu::BindGroup,
     objects: Vec<Option<ObjectRef>>,
     object_indices: HashMap<u64, usize>,
-    #[expect(dead_code)]
+    /// Parent object ID of each object that has a parent.
+    parents: HashMap<u64, u64>,
+    /// Child object IDs of each object that has children.
+    children: HashMap<u64, Vec<u64>>,
     camera_wgpu_bind_group_layout: wgpu::BindGroupLayout,
+    lights: Vec<Option<LightRef>>,
+    light_indices: HashMap<u64, usize>,
+    ambient_light: Rgba,
+    lights_bind_group: LightsBindGroup,
+    lights_wgpu_bind_group: wgpu::BindGroup,
+    lights_wgpu_bind_group_layout: wgpu::BindGroupLayout,
+    /// Separate from `camera_bind_group`, as the shadow maps are rendered in a pass before the
+    /// main pass.
+    shadow_camera_bind_group: CameraBindGroup,
+    shadow_camera_wgpu_bind_group: wgpu::BindGroup,
+    /// Bound in shadow passes for the fragment shaders of masked materials, without lights and
+    /// with a placeholder in place of the shadow atlas that is being rendered.
+    shadow_lights_wgpu_bind_group: wgpu::BindGroup,
+    shadow_atlas: ShadowAtlas,
+    shadow_distance: f32,
+    /// Number of objects outside the frustum of their camera in the last `render`.
+    culled_object_count: usize,
+    skybox: Option<SkyboxStorage>,
+    environment: Option<Environment>,
     surface_color_format: TextureFormat,
     surface_depth_stencil_format: DepthStencilTextureFormat,
 }
 
 impl Scene {
+    /// Default size of each shadow map in texels, see `set_shadow_map_size`.
+    pub const DEFAULT_SHADOW_MAP_SIZE: u32 = 512;
+
     pub fn new(
         device: &wgpu::Device,
         surface_color_format: TextureFormat,
@@ -111,12 +238,51 @@ impl Scene {
         let camera_bind_group = CameraBindGroup::create(device);
         let (camera_wgpu_bind_group, camera_wgpu_bind_group_layout) =
             binding::create_wgpu_bind_group(device, &camera_bind_group);
+        let shadow_atlas = ShadowAtlas::create(device, Self::DEFAULT_SHADOW_MAP_SIZE);
+        let lights_bind_group = LightsBindGroup::create(
+            device,
+            shadow_atlas.view(),
+            shadow::create_shadow_sampler(device),
+        );
+        let (lights_wgpu_bind_group, lights_wgpu_bind_group_layout) =
+            binding::create_wgpu_bind_group(device, &lights_bind_group);
+        let shadow_camera_bind_group = CameraBindGroup::create(device);
+        let shadow_camera_wgpu_bind_group = binding::create_wgpu_bind_group_with_layout(
+            device,
+            &shadow_camera_bind_group,
+            &camera_wgpu_bind_group_layout,
+        );
+        let shadow_lights_wgpu_bind_group = binding::create_wgpu_bind_group_with_layout(
+            device,
+            &LightsBindGroup::create(
+                device,
+                ShadowAtlas::create(device, 1).view(),
+                shadow::create_shadow_sampler(device),
+            ),
+            &lights_wgpu_bind_group_layout,
+        );
         Self {
             camera_bind_group,
             camera_wgpu_bind_group,
             camera_wgpu_bind_group_layout,
             objects: Vec::new(),
             object_indices: HashMap::new(),
+            parents: HashMap::new(),
+            children: HashMap::new(),
+            lights: Vec::new(),
+            light_indices: HashMap::new(),
+            ambient_light: Rgba::new(0.0, 0.0, 0.0, 1.0),
+            lights_bind_group,
+            lights_wgpu_bind_group,
+            lights_wgpu_bind_group_layout,
+            shadow_camera_bind_group,
+            shadow_camera_wgpu_bind_group,
+            shadow_lights_wgpu_bind_group,
+            shadow_atlas,
+            shadow_distance: f32::INFINITY,
+            culled_object_count: 0,
+            skybox: None,
+            environment: None,
             surface_color_format,
             surface_depth_stencil_format,
         }
@@ -140,82 +306,582 @@ impl Scene {
     }
 
     /// Removes an object from the list of objects for rendering.
+    /// Its children become root objects.
     ///
     /// # Panics
     ///
     /// - if object wasn't in the list
     pub fn remove_object(&mut self, object: &ObjectRef) {
         let object_id = { object.lock().id };
-        let &index = self
+        let index = self
             .object_indices
-            .get(&object_id)
+            .remove(&object_id)
             .unwrap_or_else(|| panic!("object wasn't in the list"));
         self.objects[index] = None;
+        self.detach_from_parent(object_id);
+        for child_id in self.children.remove(&object_id).unwrap_or_default() {
+            self.parents.remove(&child_id);
+            self.object_by_id(child_id).lock().is_world_dirty = true;
+        }
     }
 
-    fn objects(&self) -> impl Iterator<Item = impl DerefMut<Target = ObjectStorage>> {
-        self.objects
-            .iter()
-            .filter_map(Option::as_ref)
-            .map(ObjectRef::lock)
+    /// Add light to the list of lights that light the scene.
+    /// Only the first `MAX_LIGHTS` lights that aren't hidden take effect.
+    ///
+    /// # Panics
+    ///
+    /// - if light was aready in the list
+    pub fn add_light(&mut self, light: LightRef) {
+        let id = { light.lock().id };
+        let index = self.lights.len();
+        match self.light_indices.entry(id) {
+            hash_map::Entry::Occupied(_) => panic!("light was already in the list"),
+            hash_map::Entry::Vacant(vacant_entry) => {
+                vacant_entry.insert(index);
+            }
+        }
+        self.lights.push(Some(light));
+    }
+
+    /// Removes a light from the list of lights that light the scene.
+    ///
+    /// # Panics
+    ///
+    /// - if light wasn't in the list
+    pub fn remove_light(&mut self, light: &LightRef) {
+        let light_id = { light.lock().id };
+        let index = self
+            .light_indices
+            .remove(&light_id)
+            .unwrap_or_else(|| panic!("light wasn't in the list"));
+        self.lights[index] = None;
+    }
+
+    /// Set the color of the light that reaches every surface regardless of lights.
+    /// Defaults to black.
+    pub fn set_ambient_light(&mut self, ambient_light: Rgba) {
+        self.ambient_light = ambient_light;
+    }
+
+    pub fn ambient_light(&self) -> Rgba {
+        self.ambient_light
+    }
+
+    /// Set the size of each shadow map in texels, which re-creates the shadow maps.
+    /// Defaults to `DEFAULT_SHADOW_MAP_SIZE`.
+    ///
+    /// # Panics
+    ///
+    /// - if shadow_map_size is 0
+    /// - if the shadow maps don't fit into one texture, whose size is `shadow_map_size` times 4
+    ///   and can be at most `max_texture_dimension_2d` of the device's limits
+    pub fn set_shadow_map_size(&mut self, device: &wgpu::Device, shadow_map_size: u32) {
+        assert!(shadow_map_size > 0, "shadow map size is 0");
+        let max_texture_dimension = device.limits().max_texture_dimension_2d;
+        assert!(
+            shadow_map_size
+                .checked_mul(shadow::ATLAS_TILES_PER_ROW)
+                .is_some_and(|atlas_size| atlas_size <= max_texture_dimension),
+            "shadow map size {shadow_map_size} times {} exceeds the maximum texture size \
+            {max_texture_dimension}",
+            shadow::ATLAS_TILES_PER_ROW,
+        );
+        self.shadow_atlas = ShadowAtlas::create(device, shadow_map_size);
+        self.lights_bind_group.shadow_atlas = self.shadow_atlas.view();
+        self.lights_wgpu_bind_group = binding::create_wgpu_bind_group_with_layout(
+            device,
+            &self.lights_bind_group,
+            &self.lights_wgpu_bind_group_layout,
+        );
+    }
+
+    pub fn shadow_map_size(&self) -> u32 {
+        self.shadow_atlas.shadow_map_size()
+    }
+
+    /// Set the distance from the camera up to which directional lights cast shadows.
+    /// Shorter distances give sharper shadows. Defaults to the far plane of the camera.
+    pub fn set_shadow_distance(&mut self, shadow_distance: f32) {
+        self.shadow_distance = shadow_distance;
+    }
+
+    pub fn shadow_distance(&self) -> f32 {
+        self.shadow_distance
+    }
+
+    /// Set the cube map drawn behind all objects, after opaque objects and before blended ones.
+    /// Defaults to `None`, which leaves the background cleared.
+    pub fn set_skybox(&mut self, device: &wgpu::Device, skybox: Option<Skybox>) {
+        self.skybox = skybox.map(|skybox| {
+            SkyboxStorage::new(
+                device,
+                skybox,
+                self.surface_color_format,
+                self.surface_depth_stencil_format,
+            )
+        });
+    }
+
+    pub fn skybox(&self) -> Option<&Skybox> {
+        self.skybox.as_ref().map(|skybox| &skybox.skybox)
+    }
+
+    /// Set the image-based lighting that lit materials add to their ambient term, usually from the
+    /// same cube map as the skybox. Defaults to `None`, which leaves only the ambient light.
+    pub fn set_environment(&mut self, device: &wgpu::Device, environment: Option<Environment>) {
+        self.environment = environment;
+        self.lights_bind_group
+            .set_environment(device, self.environment.as_ref());
+        self.lights_wgpu_bind_group = binding::create_wgpu_bind_group_with_layout(
+            device,
+            &self.lights_bind_group,
+            &self.lights_wgpu_bind_group_layout,
+        );
+    }
+
+    pub fn environment(&self) -> Option<&Environment> {
+        self.environment.as_ref()
+    }
+
+    /// Number of objects that the last `render` didn't draw because they were outside the frustum
+    /// of their camera. Hidden objects aren't counted.
+    pub fn culled_object_count(&self) -> usize {
+        self.culled_object_count
+    }
+
+    fn object_by_id(&self, id: u64) -> &ObjectRef {
+        let index = self.object_indices[&id];
+        self.objects[index].as_ref().unwrap()
+    }
+
+    fn detach_from_parent(&mut self, object_id: u64) {
+        if let Some(parent_id) = self.parents.remove(&object_id) {
+            let siblings = self.children.get_mut(&parent_id).unwrap();
+            siblings.retain(|&id| id != object_id);
+            if siblings.is_empty() {
+                self.children.remove(&parent_id);
+            }
+        }
+    }
+
+    /// Attaches an object to a parent object, so that its model matrix becomes relative to the
+    /// parent's. `None` makes it a root object.
+    ///
+    /// # Panics
+    ///
+    /// - if object or parent wasn't in the list
+    /// - if parent is object itself or one of its descendants
+    pub fn set_object_parent(&mut self, object: &ObjectRef, parent: Option<&ObjectRef>) {
+        let object_id = { object.lock().id };
+        assert!(
+            self.object_indices.contains_key(&object_id),
+            "object wasn't in the list"
+        );
+        let parent_id = parent.map(|parent| parent.lock().id);
+        if let Some(parent_id) = parent_id {
+            assert!(
+                self.object_indices.contains_key(&parent_id),
+                "parent wasn't in the list"
+            );
+            let mut ancestor_id = Some(parent_id);
+            while let Some(id) = ancestor_id {
+                assert!(
+                    id != object_id,
+                    "object cannot be parented to itself or its descendant"
+                );
+                ancestor_id = self.parents.get(&id).copied();
+            }
+        }
+        self.detach_from_parent(object_id);
+        if let Some(parent_id) = parent_id {
+            self.parents.insert(object_id, parent_id);
+            self.children.entry(parent_id).or_default().push(object_id);
+        }
+        object.lock().is_world_dirty = true;
+    }
+
+    /// Returns the parent of an object, `None` for root objects.
+    ///
+    /// # Panics
+    ///
+    /// - if object wasn't in the list
+    pub fn object_parent(&self, object: &ObjectRef) -> Option<ObjectRef> {
+        let object_id = { object.lock().id };
+        assert!(
+            self.object_indices.contains_key(&object_id),
+            "object wasn't in the list"
+        );
+        let &parent_id = self.parents.get(&object_id)?;
+        Some(self.object_by_id(parent_id).clone())
+    }
+
+    /// Returns the children of an object.
+    ///
+    /// # Panics
+    ///
+    /// - if object wasn't in the list
+    pub fn object_children(&self, object: &ObjectRef) -> Vec<ObjectRef> {
+        let object_id = { object.lock().id };
+        assert!(
+            self.object_indices.contains_key(&object_id),
+            "object wasn't in the list"
+        );
+        self.children
+            .get(&object_id)
+            .into_iter()
+            .flatten()
+            .map(|&child_id| self.object_by_id(child_id).clone())
+            .collect()
+    }
+
+    /// Returns the world matrix of an object, i.e. its model matrix combined with the ones of its
+    /// ancestors.
+    pub fn object_world_matrix(&self, object: &ObjectRef) -> Matrix4<f32> {
+        self.update_world_matrices();
+        object.lock().world
+    }
+
+    /// Re-computes world matrices of objects whose model matrix or ancestors changed, and which
+    /// objects are hidden through their ancestors.
+    /// Called by `render`.
+    pub fn update_world_matrices(&self) {
+        for object in self.objects.iter().filter_map(Option::as_ref) {
+            let object_id = { object.lock().id };
+            if !self.parents.contains_key(&object_id) {
+                self.update_world_matrix(object, Matrix4::identity(), false, false);
+            }
+        }
+    }
+
+    fn update_world_matrix(
+        &self,
+        object: &ObjectRef,
+        parent_world: Matrix4<f32>,
+        is_parent_changed: bool,
+        is_parent_hidden: bool,
+    ) {
+        let (object_id, world, is_changed, is_hidden) = {
+            let mut object = object.lock();
+            let is_changed = is_parent_changed || object.is_world_dirty;
+            if is_changed {
+                object.world = parent_world * object.model;
+                object.is_world_dirty = false;
+            }
+            object.is_hidden_in_hierarchy = is_parent_hidden || object.is_hidden;
+            (
+                object.id,
+                object.world,
+                is_changed,
+                object.is_hidden_in_hierarchy,
+            )
+        };
+        for &child_id in self.children.get(&object_id).into_iter().flatten() {
+            let child = self.object_by_id(child_id);
+            self.update_world_matrix(child, world, is_changed, is_hidden);
+        }
     }
 
     /// Renders the scene onto the surface with a camera.
+    /// `options` decides whether the existing content of the surface is cleared or kept.
     /// TODO: perhaps make cameras also registerable, similar to mesh, material and object
-    pub fn render(&self, context: &Context, surface: &SurfaceView) {
+    pub fn render(
+        &mut self,
+        context: &Context,
+        surface: &SurfaceView,
+        options: &RenderPassOptions,
+    ) {
         // For more intuitive panic site if texture format mismatch happens:
         debug_assert!(surface.format() == self.surface_color_format);
         debug_assert!(surface.depth_stencil_format() == self.surface_depth_stencil_format);
 
-        let mut render_pass = surface.render_pass(context.wgpu_device());
+        context
+            .pipeline_cache()
+            .reload_changed_shaders(context.wgpu_device());
+        self.update_world_matrices();
 
-        render_pass.set_bind_group(0, &self.camera_wgpu_bind_group, &[]);
+        // Cloned out of `self`, so that the shadow pass can borrow `self` mutably.
+        let object_refs: Vec<ObjectRef> = self.objects.iter().flatten().cloned().collect();
+        let mut objects: Vec<_> = object_refs
+            .iter()
+            .map(ObjectRef::lock)
+            .filter(|object| !object.is_hidden_in_hierarchy)
+            .filter(|object| {
+                let instance_count = object.instances.as_ref().map(InstanceBuffer::len);
+                instance_count != Some(0)
+            })
+            .collect();
 
-        for object in self.objects() {
-            let camera = object.camera.lock();
-            let mesh = object.mesh.lock();
-            let material = object.material.lock();
-            if object.is_hidden {
-                continue;
+        for object in &mut objects {
+            if let Some(instances) = &mut object.instances {
+                instances.upload(context.wgpu_device(), context.wgpu_queue());
             }
+        }
 
-            // Updates projection uniform.
-            let projection = camera.projection_matrix(surface.size_f32());
-            self.camera_bind_group
-                .projection
-                .write(projection.into(), context.wgpu_queue());
-
-            if let Some(model_view_uniform) = mesh.instance.model_view() {
-                let model_view = camera.view_matrix() * object.model;
-                let model_view_array: [[f32; 4]; 4] = model_view.into();
-                context.wgpu_queue().write_buffer(
-                    model_view_uniform,
-                    0,
-                    bytemuck::bytes_of(&model_view_array),
-                );
-            }
+        // Culled objects are still rendered into shadow maps, as their shadows may be visible.
+        let drawn_objects: Vec<&ObjectStorage> = objects
+            .iter()
+            .map(|object| &**object)
+            .filter(|object| object.is_in_frustum(surface.size_f32()))
+            .collect();
+        self.culled_object_count = objects.len() - drawn_objects.len();
+
+        // Opaque objects front to back, so that hidden fragments fail the depth test early, then
+        // blended objects back to front, so that they blend onto everything behind them. The sort
+        // is stable, so objects at the same depth keep the order they were added in.
+        let (mut opaque_objects, mut blended_objects): (Vec<_>, Vec<_>) = drawn_objects
+            .into_iter()
+            .map(|object| (object, object.view_depth()))
+            .partition(|(object, _)| object.material.lock().alpha_mode != AlphaMode::Blend);
+        opaque_objects.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        blended_objects.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        let opaque_object_count = opaque_objects.len();
+        let drawn_objects: Vec<&ObjectStorage> = opaque_objects
+            .into_iter()
+            .chain(blended_objects)
+            .map(|(object, _)| object)
+            .collect();
+
+        // All draws of this pass are submitted together, so each object gets its own slot in the
+        // uniform buffer instead of overwriting a shared one.
+        let camera_uniforms: Vec<CameraUniform> = drawn_objects
+            .iter()
+            .map(|object| {
+                let camera = object.camera.lock();
+                CameraUniform::new(
+                    camera.projection_matrix(surface.size_f32()),
+                    camera.view_matrix(),
+                    object.world,
+                )
+            })
+            .collect();
+        let object_uniforms: Vec<ObjectUniform> = drawn_objects
+            .iter()
+            .map(|object| {
+                ObjectUniform::new(object.receives_shadows, object.material.lock().alpha_mode)
+            })
+            .collect();
+        let reallocated = self.camera_bind_group.write(
+            context.wgpu_device(),
+            context.wgpu_queue(),
+            &camera_uniforms,
+            &object_uniforms,
+        );
+        if reallocated {
+            self.camera_wgpu_bind_group = binding::create_wgpu_bind_group_with_layout(
+                context.wgpu_device(),
+                &self.camera_bind_group,
+                &self.camera_wgpu_bind_group_layout,
+            );
+        }
+
+        let lights: Vec<_> = self
+            .lights
+            .iter()
+            .filter_map(Option::as_ref)
+            .map(LightRef::lock)
+            .filter(|light| !light.is_hidden)
+            .map(|light| (light.light, light.casts_shadows))
+            .take(MAX_LIGHTS)
+            .collect();
+        // Directional lights are fitted to the camera of the first object, as objects of a scene
+        // are expected to share a camera.
+        let shadow_camera = objects.first().map(|object| object.camera.lock().clone());
+        let mut shadow_maps: Vec<ShadowMap> = Vec::new();
+        let light_uniforms: Vec<_> = lights
+            .iter()
+            .map(|&(light, casts_shadows)| {
+                let first_shadow_map = shadow_maps.len();
+                let light_shadow_maps = match (light, &shadow_camera) {
+                    _ if !casts_shadows => vec![],
+                    (Light::Directional(light), Some(camera)) => ShadowMap::directional(
+                        &light,
+                        camera,
+                        surface.size_f32(),
+                        self.shadow_distance,
+                        self.shadow_atlas.shadow_map_size(),
+                    )
+                    .to_vec(),
+                    (Light::Spot(light), _) => ShadowMap::spot(&light).into_iter().collect(),
+                    _ => vec![],
+                };
+                let uniform = light.to_uniform();
+                if first_shadow_map + light_shadow_maps.len() > MAX_SHADOW_MAPS {
+                    return uniform;
+                }
+                shadow_maps.extend_from_slice(&light_shadow_maps);
+                uniform.with_shadow_maps(first_shadow_map, light_shadow_maps.len())
+            })
+            .collect();
+        self.lights_bind_group.lights.write(
+            LightsUniform::new(self.ambient_light, light_uniforms)
+                .with_environment(self.environment.as_ref()),
+            context.wgpu_queue(),
+        );
+        if !shadow_maps.is_empty() {
+            // Blended objects are translucent, which depth-only shadow maps can't represent.
+            let casters: Vec<&ObjectStorage> = objects
+                .iter()
+                .map(|object| &**object)
+                .filter(|object| object.casts_shadows)
+                .filter(|object| object.material.lock().alpha_mode != AlphaMode::Blend)
+                .collect();
+            self.render_shadow_maps(context, &casters, &shadow_maps);
+        }
 
+        if let Some(skybox) = &self.skybox {
+            skybox.update(context.wgpu_queue(), surface.size_f32());
+        }
+
+        let mut render_pass = surface.render_pass_with_options(context.wgpu_device(), options);
+
+        for (i, object) in drawn_objects.iter().enumerate() {
+            if i == opaque_object_count
+                && let Some(skybox) = &self.skybox
+            {
+                skybox.draw(render_pass.wgpu_render_pass_mut());
+            }
+            let mesh = object.mesh.lock();
+            let material = object.material.lock();
+            let offsets = self.camera_bind_group.offsets(i);
             let wgpu_render_pass = render_pass.wgpu_render_pass_mut();
-            wgpu_render_pass.set_pipeline(&object.pipeline);
+            wgpu_render_pass.set_pipeline(&object.pipeline.get());
+            wgpu_render_pass.set_bind_group(0, &self.camera_wgpu_bind_group, &offsets);
             wgpu_render_pass.set_bind_group(1, &mesh.wgpu_bind_group, &[]);
             wgpu_render_pass.set_bind_group(2, &material.wgpu_bind_group, &[]);
+            wgpu_render_pass.set_bind_group(3, &self.lights_wgpu_bind_group, &[]);
             wgpu_render_pass.set_vertex_buffer(0, mesh.vertex_buffer().slice(..));
             wgpu_render_pass.set_index_buffer(mesh.index_buffer().slice(..), mesh.index_format);
-            wgpu_render_pass.draw_indexed(0..mesh.index_buffer_length(), 0, 0..1);
+            let instance_count = match &object.instances {
+                Some(instances) => {
+                    let instance_buffer = instances.wgpu_buffer().unwrap();
+                    wgpu_render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+                    instances.len()
+                }
+                None => 1,
+            };
+            wgpu_render_pass.draw_indexed(0..mesh.index_buffer_length(), 0, 0..instance_count);
+        }
+        if opaque_object_count == drawn_objects.len()
+            && let Some(skybox) = &self.skybox
+        {
+            skybox.draw(render_pass.wgpu_render_pass_mut());
         }
 
         render_pass.finish(context.wgpu_queue());
     }
 
-    /// Set the model matrix for an object's mesh.
-    /// NOP for objects that doesn't use traditional model-view matrix (object with a mesh that
-    /// returns `None` for `AsMesh::model_view`).
+    /// Renders the objects that cast shadows into the tiles of the shadow atlas, and writes the
+    /// matrices for sampling them.
+    fn render_shadow_maps(
+        &mut self,
+        context: &Context,
+        casters: &[&ObjectStorage],
+        shadow_maps: &[ShadowMap],
+    ) {
+        let mut shadow_maps_uniform = ShadowMapsUniform::zeroed();
+        for (i, shadow_map) in shadow_maps.iter().enumerate() {
+            shadow_maps_uniform.shadow_maps[i] =
+                self.shadow_atlas.shadow_map_uniform(i, shadow_map);
+        }
+        self.lights_bind_group
+            .shadow_maps
+            .write(shadow_maps_uniform, context.wgpu_queue());
+
+        let camera_uniforms: Vec<CameraUniform> = shadow_maps
+            .iter()
+            .flat_map(|shadow_map| {
+                casters.iter().map(|object| {
+                    CameraUniform::new(shadow_map.projection, shadow_map.view, object.world)
+                })
+            })
+            .collect();
+        let object_uniforms: Vec<ObjectUniform> = shadow_maps
+            .iter()
+            .flat_map(|_| {
+                casters.iter().map(|object| {
+                    ObjectUniform::new(object.receives_shadows, object.material.lock().alpha_mode)
+                })
+            })
+            .collect();
+        let reallocated = self.shadow_camera_bind_group.write(
+            context.wgpu_device(),
+            context.wgpu_queue(),
+            &camera_uniforms,
+            &object_uniforms,
+        );
+        if reallocated {
+            self.shadow_camera_wgpu_bind_group = binding::create_wgpu_bind_group_with_layout(
+                context.wgpu_device(),
+                &self.shadow_camera_bind_group,
+                &self.camera_wgpu_bind_group_layout,
+            );
+        }
+
+        let atlas_view = self.shadow_atlas.view();
+        let mut encoder = context
+            .wgpu_device()
+            .create_command_encoder(&Default::default());
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: atlas_view.wgpu_texture_view(),
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        let size = self.shadow_atlas.shadow_map_size() as f32;
+        for i in 0..shadow_maps.len() {
+            let origin = self.shadow_atlas.tile_origin(i).map(|x| x as f32);
+            render_pass.set_viewport(origin.x, origin.y, size, size, 0.0, 1.0);
+            for (j, object) in casters.iter().enumerate() {
+                let mesh = object.mesh.lock();
+                let offsets = self.shadow_camera_bind_group.offsets(i * casters.len() + j);
+                render_pass.set_pipeline(&object.shadow_pipeline.get());
+                render_pass.set_bind_group(0, &self.shadow_camera_wgpu_bind_group, &offsets);
+                render_pass.set_bind_group(1, &mesh.wgpu_bind_group, &[]);
+                let material = object.material.lock();
+                if let AlphaMode::Mask { .. } = material.alpha_mode {
+                    render_pass.set_bind_group(2, &material.wgpu_bind_group, &[]);
+                    render_pass.set_bind_group(3, &self.shadow_lights_wgpu_bind_group, &[]);
+                }
+                render_pass.set_vertex_buffer(0, mesh.vertex_buffer().slice(..));
+                render_pass.set_index_buffer(mesh.index_buffer().slice(..), mesh.index_format);
+                let instance_count = match &object.instances {
+                    Some(instances) => {
+                        let instance_buffer = instances.wgpu_buffer().unwrap();
+                        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+                        instances.len()
+                    }
+                    None => 1,
+                };
+                render_pass.draw_indexed(0..mesh.index_buffer_length(), 0, 0..instance_count);
+            }
+        }
+        drop(render_pass);
+        context.wgpu_queue().submit([encoder.finish()]);
+    }
+
+    /// Set the model matrix for an object, relative to its parent if it has one.
+    /// No effect for objects whose mesh shader doesn't use the model-view matrix.
     pub fn set_object_model(&self, object: &ObjectRef, model: Matrix4<f32>) {
         let mut object = object.lock();
         object.model = model;
+        object.is_world_dirty = true;
+    }
+
+    /// Set the model matrix for an object from a translation, rotation and scale.
+    /// See `set_object_model`.
+    pub fn set_object_transform(&self, object: &ObjectRef, transform: LocalTransform) {
+        self.set_object_model(object, transform.matrix());
     }
 
-    /// Set whether an object is hidden.
+    /// Set whether an object is hidden. Hiding an object also hides its descendants.
     pub fn set_object_is_hidden(&self, object: &ObjectRef, is_hidden: bool) {
         let mut object = object.lock();
         object.is_hidden = is_hidden;
@@ -0,0 +1,259 @@
+use std::{borrow::Cow, collections::HashSet, error::Error, fmt, fs, path::PathBuf};
+
+use crate::{ShaderSource, include_shader};
+
+/// Shader modules of the engine that shaders can import, by name.
+const MODULES: &[(&str, ShaderSource)] = &[
+    (
+        "tbn::camera",
+        include_shader!("./shaders/modules/camera.wgsl"),
+    ),
+    (
+        "tbn::color",
+        include_shader!("./shaders/modules/color.wgsl"),
+    ),
+    (
+        "tbn::cube_map",
+        include_shader!("./shaders/modules/cube_map.wgsl"),
+    ),
+    (
+        "tbn::instance",
+        include_shader!("./shaders/modules/instance.wgsl"),
+    ),
+    (
+        "tbn::lights",
+        include_shader!("./shaders/modules/lights.wgsl"),
+    ),
+    (
+        "tbn::vertex_output",
+        include_shader!("./shaders/modules/vertex_output.wgsl"),
+    ),
+];
+
+/// Names of the shader modules that `#import` accepts.
+pub fn shader_module_names() -> impl Iterator<Item = &'static str> {
+    MODULES.iter().map(|&(name, _)| name)
+}
+
+/// Canonical paths of the files of the shader modules, for hot reloading. Modules whose file
+/// can't be found, e.g. because the binary runs on a different machine than it was built on, are
+/// left out.
+pub(crate) fn shader_module_paths() -> impl Iterator<Item = (&'static str, PathBuf)> {
+    MODULES.iter().filter_map(|&(name, source)| {
+        let path = fs::canonicalize(source.path()?).ok()?;
+        Some((name, path))
+    })
+}
+
+/// Expands the directives in a WGSL source, which are lines starting with `#`:
+///
+/// - `#import tbn::camera` inserts a module of the engine (see `shader_module_names`), unless it
+///   was already imported. Modules may import other modules.
+/// - `#define NAME` defines `NAME` for the rest of the shader, including modules imported after it.
+/// - `#ifdef NAME`, `#ifndef NAME`, `#else` and `#endif` keep or remove the lines between them
+///   depending on whether `NAME` is defined. They can be nested.
+///
+/// `defines` are defined before the first line.
+///
+/// ```
+/// # use tbn_engine::*;
+/// let source = "
+/// #import tbn::vertex_output
+/// #ifdef RED
+/// const COLOR = vec4<f32>(1.0, 0.0, 0.0, 1.0);
+/// #else
+/// const COLOR = vec4<f32>(1.0);
+/// #endif
+/// ";
+/// let wgsl = preprocess_wgsl(source, &["RED"]).unwrap();
+/// assert!(wgsl.contains("struct VertexOutput"));
+/// assert!(wgsl.contains("1.0, 0.0, 0.0"));
+/// ```
+pub fn preprocess_wgsl(source: &str, defines: &[&str]) -> Result<String, PreprocessError> {
+    let (wgsl, _) = preprocess_wgsl_with_imports(source, defines, false)?;
+    Ok(wgsl)
+}
+
+/// Like `preprocess_wgsl`, but also returns the names of the modules that were imported, directly
+/// or by other modules. If `read_module_files`, modules are read from their files for hot
+/// reloading, falling back to the embedded source if a file can't be read.
+pub(crate) fn preprocess_wgsl_with_imports(
+    source: &str,
+    defines: &[&str],
+    read_module_files: bool,
+) -> Result<(String, HashSet<&'static str>), PreprocessError> {
+    let mut preprocessor = Preprocessor {
+        defines: defines.iter().map(|define| define.to_string()).collect(),
+        imported: HashSet::new(),
+        read_module_files,
+        output: String::with_capacity(source.len()),
+    };
+    preprocessor.process(source, None)?;
+    Ok((preprocessor.output, preprocessor.imported))
+}
+
+/// An invalid directive in a shader. See `preprocess_wgsl`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreprocessError {
+    /// The imported module that contains the directive, `None` for the shader itself.
+    pub module: Option<&'static str>,
+    /// Line number of the directive, starting from 1.
+    pub line: usize,
+    pub kind: PreprocessErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PreprocessErrorKind {
+    UnknownDirective(String),
+    UnknownModule(String),
+    /// A directive that takes a name has none.
+    MissingName(String),
+    /// `#else` or `#endif` without an `#ifdef` or `#ifndef`.
+    UnmatchedDirective(&'static str),
+    /// `#ifdef` or `#ifndef` without an `#endif`.
+    UnterminatedCondition,
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.module {
+            Some(module) => write!(f, "line {} of {module}: ", self.line)?,
+            None => write!(f, "line {}: ", self.line)?,
+        }
+        match &self.kind {
+            PreprocessErrorKind::UnknownDirective(directive) => {
+                write!(f, "unknown directive `#{directive}`")
+            }
+            PreprocessErrorKind::UnknownModule(name) => {
+                write!(f, "unknown shader module `{name}`")
+            }
+            PreprocessErrorKind::MissingName(directive) => {
+                write!(f, "`#{directive}` needs a name")
+            }
+            PreprocessErrorKind::UnmatchedDirective(directive) => {
+                write!(f, "`#{directive}` without `#ifdef` or `#ifndef`")
+            }
+            PreprocessErrorKind::UnterminatedCondition => write!(f, "missing `#endif`"),
+        }
+    }
+}
+
+impl Error for PreprocessError {}
+
+struct Preprocessor {
+    defines: HashSet<String>,
+    imported: HashSet<&'static str>,
+    read_module_files: bool,
+    output: String,
+}
+
+/// State of an `#ifdef` or `#ifndef` block.
+struct Condition {
+    /// Whether the lines of the current branch are kept.
+    is_active: bool,
+    /// Whether the enclosing block is kept.
+    is_parent_active: bool,
+    has_else: bool,
+}
+
+impl Preprocessor {
+    fn module_wgsl(&self, source: ShaderSource) -> Cow<'static, str> {
+        if self.read_module_files
+            && let Some(wgsl) = source.path().and_then(|path| fs::read_to_string(path).ok())
+        {
+            return Cow::Owned(wgsl);
+        }
+        Cow::Borrowed(source.wgsl())
+    }
+
+    fn process(
+        &mut self,
+        source: &str,
+        module: Option<&'static str>,
+    ) -> Result<(), PreprocessError> {
+        let mut conditions: Vec<Condition> = Vec::new();
+        let mut last_line = 0;
+        for (i, line) in source.lines().enumerate() {
+            last_line = i + 1;
+            let error = |kind| PreprocessError {
+                module,
+                line: i + 1,
+                kind,
+            };
+            let is_active = conditions
+                .last()
+                .is_none_or(|condition| condition.is_active);
+            let Some(directive) = line.trim_start().strip_prefix('#') else {
+                if is_active {
+                    self.output.push_str(line);
+                    self.output.push('\n');
+                }
+                continue;
+            };
+            let (directive, argument) = directive
+                .split_once(char::is_whitespace)
+                .map_or((directive.trim_end(), ""), |(directive, argument)| {
+                    (directive, argument.trim())
+                });
+            let name = || match argument {
+                "" => Err(error(PreprocessErrorKind::MissingName(
+                    directive.to_string(),
+                ))),
+                name => Ok(name),
+            };
+            match directive {
+                "ifdef" | "ifndef" => {
+                    let is_defined = self.defines.contains(name()?);
+                    conditions.push(Condition {
+                        is_active: is_active && (is_defined == (directive == "ifdef")),
+                        is_parent_active: is_active,
+                        has_else: false,
+                    });
+                }
+                "else" => {
+                    let condition = conditions
+                        .last_mut()
+                        .filter(|condition| !condition.has_else)
+                        .ok_or_else(|| error(PreprocessErrorKind::UnmatchedDirective("else")))?;
+                    condition.is_active = condition.is_parent_active && !condition.is_active;
+                    condition.has_else = true;
+                }
+                "endif" => {
+                    conditions
+                        .pop()
+                        .ok_or_else(|| error(PreprocessErrorKind::UnmatchedDirective("endif")))?;
+                }
+                _ if !is_active => (),
+                "define" => {
+                    self.defines.insert(name()?.to_string());
+                }
+                "import" => {
+                    let name = name()?;
+                    let &(name, module_source) = MODULES
+                        .iter()
+                        .find(|&&(module_name, _)| module_name == name)
+                        .ok_or_else(|| {
+                            error(PreprocessErrorKind::UnknownModule(name.to_string()))
+                        })?;
+                    if self.imported.insert(name) {
+                        let module_source = self.module_wgsl(module_source);
+                        self.process(&module_source, Some(name))?;
+                    }
+                }
+                _ => {
+                    return Err(error(PreprocessErrorKind::UnknownDirective(
+                        directive.to_string(),
+                    )));
+                }
+            }
+        }
+        if !conditions.is_empty() {
+            return Err(PreprocessError {
+                module,
+                line: last_line,
+                kind: PreprocessErrorKind::UnterminatedCondition,
+            });
+        }
+        Ok(())
+    }
+}
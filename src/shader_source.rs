@@ -0,0 +1,176 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use notify::Watcher as _;
+
+/// WGSL source of a shader, and optionally the file it was read from.
+///
+/// Shaders with a file can be hot-reloaded, see `Context::enable_shader_hot_reload`. Use
+/// `include_shader!` to embed a file and remember where it came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShaderSource {
+    wgsl: &'static str,
+    path: Option<ShaderPath>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShaderPath {
+    Plain(&'static str),
+    /// A path relative to the file that included it, like the argument of `include_str!`.
+    Included {
+        manifest_dir: &'static str,
+        including_file: &'static str,
+        path: &'static str,
+    },
+}
+
+impl ShaderSource {
+    /// A shader without a file, which can't be hot-reloaded.
+    pub const fn new(wgsl: &'static str) -> Self {
+        Self { wgsl, path: None }
+    }
+
+    /// Sets the file to read the shader from again when it changes. Relative paths are relative to
+    /// the working directory.
+    pub const fn with_path(self, path: &'static str) -> Self {
+        Self {
+            path: Some(ShaderPath::Plain(path)),
+            ..self
+        }
+    }
+
+    #[doc(hidden)]
+    pub const fn __included(
+        wgsl: &'static str,
+        manifest_dir: &'static str,
+        including_file: &'static str,
+        path: &'static str,
+    ) -> Self {
+        Self {
+            wgsl,
+            path: Some(ShaderPath::Included {
+                manifest_dir,
+                including_file,
+                path,
+            }),
+        }
+    }
+
+    /// The WGSL source embedded into the binary.
+    pub const fn wgsl(&self) -> &'static str {
+        self.wgsl
+    }
+
+    /// The file of the shader, `None` if it has none or if the file of an `include_shader!` can't
+    /// be found, e.g. because the binary runs on a different machine than it was built on.
+    pub fn path(&self) -> Option<PathBuf> {
+        match self.path? {
+            ShaderPath::Plain(path) => Some(PathBuf::from(path)),
+            ShaderPath::Included {
+                manifest_dir,
+                including_file,
+                path,
+            } => {
+                let relative = Path::new(including_file).parent()?.join(path);
+                if relative.is_absolute() {
+                    return Some(relative);
+                }
+                // `file!()` is relative to the workspace root, which is the package itself or one
+                // of its ancestors.
+                Path::new(manifest_dir)
+                    .ancestors()
+                    .map(|root| root.join(&relative))
+                    .find(|path| path.is_file())
+            }
+        }
+    }
+}
+
+impl From<&'static str> for ShaderSource {
+    fn from(wgsl: &'static str) -> Self {
+        Self::new(wgsl)
+    }
+}
+
+/// Embeds a WGSL file like `include_str!`, as a `ShaderSource` that can be hot-reloaded from the
+/// file.
+///
+/// ```ignore
+/// impl AsMaterial for Outline {
+///     fn fragment_shader_source() -> Option<ShaderSource> {
+///         Some(include_shader!("shaders/outline.wgsl"))
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! include_shader {
+    ($path:literal) => {
+        $crate::ShaderSource::__included(
+            ::std::include_str!($path),
+            ::std::env!("CARGO_MANIFEST_DIR"),
+            ::std::file!(),
+            $path,
+        )
+    };
+}
+
+/// Watches the directories of shader files for changes.
+#[derive(Debug)]
+pub(crate) struct ShaderWatcher {
+    watcher: notify::RecommendedWatcher,
+    events: mpsc::Receiver<notify::Result<notify::Event>>,
+    watched_directories: HashSet<PathBuf>,
+}
+
+impl ShaderWatcher {
+    pub(crate) fn new() -> notify::Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let watcher = notify::recommended_watcher(sender)?;
+        Ok(Self {
+            watcher,
+            events,
+            watched_directories: HashSet::new(),
+        })
+    }
+
+    /// Watches the directory of the file rather than the file itself, as editors often save by
+    /// replacing the file.
+    pub(crate) fn watch(&mut self, path: &Path) {
+        let Some(directory) = path.parent() else {
+            return;
+        };
+        if self.watched_directories.contains(directory) {
+            return;
+        }
+        match self
+            .watcher
+            .watch(directory, notify::RecursiveMode::NonRecursive)
+        {
+            Ok(()) => {
+                self.watched_directories.insert(directory.to_owned());
+            }
+            Err(error) => log::error!(
+                "cannot watch {} for shader changes: {error}",
+                directory.display()
+            ),
+        }
+    }
+
+    /// Paths of the files that changed since the last call.
+    pub(crate) fn changed_files(&self) -> HashSet<PathBuf> {
+        let mut changed_files = HashSet::new();
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) if event.kind.is_create() || event.kind.is_modify() => {
+                    changed_files.extend(event.paths);
+                }
+                Ok(_) => (),
+                Err(error) => log::error!("error while watching shaders: {error}"),
+            }
+        }
+        changed_files
+    }
+}
@@ -0,0 +1,460 @@
+use std::{error::Error, fmt};
+
+use naga::{
+    AddressSpace, Binding, ImageClass, ImageDimension, Module, ScalarKind, ShaderStage, TypeInner,
+    proc::Layouter,
+    valid::{Capabilities, ModuleInfo, ValidationFlags, Validator},
+};
+
+use ShaderValidationErrorKind::*;
+
+use crate::PreprocessError;
+
+/// An invalid WGSL shader of a mesh or material, or a mismatch between them, found when creating
+/// the first object of its kind.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderValidationError {
+    /// Type name of the mesh or material whose shader doesn't match.
+    pub type_name: &'static str,
+    pub kind: ShaderValidationErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShaderValidationErrorKind {
+    /// The shader has an invalid preprocessor directive, see `preprocess_wgsl`.
+    Preprocess(PreprocessError),
+    /// The shader doesn't compile. Contains the formatted error.
+    Invalid(String),
+    /// The shader has no entry point of this name.
+    MissingEntryPoint(&'static str),
+    /// The shader uses a binding that the bind group doesn't have.
+    MissingBinding {
+        group: u32,
+        binding: u32,
+        name: String,
+    },
+    /// The shader uses a binding of a different type than the bind group has.
+    BindingTypeMismatch {
+        group: u32,
+        binding: u32,
+        name: String,
+        /// The binding type of the bind group.
+        expected: wgpu::BindingType,
+        /// The WGSL type of the binding.
+        found: String,
+    },
+    /// The shader uses a binding that the bind group doesn't make visible to its stage.
+    BindingNotVisible {
+        group: u32,
+        binding: u32,
+        name: String,
+        /// The stage of the shader.
+        stage: wgpu::ShaderStages,
+    },
+    /// The vertex shader takes an attribute that the vertex buffers don't have.
+    MissingVertexAttribute { location: u32, name: String },
+    /// The vertex shader takes an attribute of a different type than the vertex buffers have.
+    VertexAttributeTypeMismatch {
+        location: u32,
+        name: String,
+        /// The format of the attribute in the vertex buffers.
+        expected: wgpu::VertexFormat,
+        /// The WGSL type of the attribute.
+        found: String,
+    },
+}
+
+impl fmt::Display for ShaderValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let type_name = self.type_name;
+        match &self.kind {
+            ShaderValidationErrorKind::Preprocess(error) => {
+                write!(f, "shader of {type_name} cannot be preprocessed: {error}")
+            }
+            ShaderValidationErrorKind::Invalid(error) => {
+                write!(f, "shader of {type_name} is invalid:\n{error}")
+            }
+            ShaderValidationErrorKind::MissingEntryPoint(name) => {
+                write!(f, "shader of {type_name} has no entry point `{name}`")
+            }
+            ShaderValidationErrorKind::MissingBinding {
+                group,
+                binding,
+                name,
+            } => write!(
+                f,
+                "shader of {type_name} uses `{name}` at @group({group}) @binding({binding}), \
+                 which the bind group doesn't have",
+            ),
+            ShaderValidationErrorKind::BindingTypeMismatch {
+                group,
+                binding,
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "shader of {type_name} declares `{name}` at @group({group}) @binding({binding}) \
+                 as {found}, but the bind group has {expected:?}",
+            ),
+            ShaderValidationErrorKind::BindingNotVisible {
+                group,
+                binding,
+                name,
+                stage,
+            } => {
+                let stage = match *stage {
+                    wgpu::ShaderStages::VERTEX => "vertex",
+                    wgpu::ShaderStages::FRAGMENT => "fragment",
+                    _ => "compute",
+                };
+                write!(
+                    f,
+                    "{stage} shader of {type_name} uses `{name}` at @group({group}) \
+                     @binding({binding}), which the bind group doesn't make visible to it",
+                )
+            }
+            ShaderValidationErrorKind::MissingVertexAttribute { location, name } => write!(
+                f,
+                "vertex shader of {type_name} takes `{name}` at @location({location}), which the \
+                 vertex buffers don't have",
+            ),
+            ShaderValidationErrorKind::VertexAttributeTypeMismatch {
+                location,
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "vertex shader of {type_name} takes `{name}` at @location({location}) as {found}, \
+                 but the vertex buffers have {expected:?}",
+            ),
+        }
+    }
+}
+
+impl Error for ShaderValidationError {}
+
+/// Checks that a WGSL shader compiles, without checking it against bind groups or vertex buffers.
+pub(crate) fn parse_and_validate(
+    type_name: &'static str,
+    source: &str,
+) -> Result<(Module, ModuleInfo), ShaderValidationError> {
+    let error = |kind| ShaderValidationError { type_name, kind };
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|parse_error| error(Invalid(parse_error.emit_to_string(source))))?;
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|validation_error| error(Invalid(validation_error.emit_to_string(source))))?;
+    Ok((module, info))
+}
+
+/// Checks one stage of a WGSL shader against the bind groups of a pipeline (indexed by group) and,
+/// for vertex shaders, against the vertex buffer layouts.
+pub(crate) fn validate_shader(
+    type_name: &'static str,
+    source: &str,
+    stage: ShaderStage,
+    bind_groups: &[&[wgpu::BindGroupLayoutEntry]],
+    vertex_buffer_layouts: &[wgpu::VertexBufferLayout],
+) -> Result<(), ShaderValidationError> {
+    let error = |kind| ShaderValidationError { type_name, kind };
+    let (module, info) = parse_and_validate(type_name, source)?;
+    let entry_point_name = match stage {
+        ShaderStage::Vertex => "vs_main",
+        ShaderStage::Fragment => "fs_main",
+        ShaderStage::Compute => "cs_main",
+        _ => unreachable!("the engine only creates vertex and fragment stages"),
+    };
+    let entry_point_index = module
+        .entry_points
+        .iter()
+        .position(|entry_point| entry_point.stage == stage && entry_point.name == entry_point_name)
+        .ok_or_else(|| error(MissingEntryPoint(entry_point_name)))?;
+    validate_bindings(&module, &info, stage, entry_point_index, bind_groups).map_err(error)?;
+    if stage == ShaderStage::Vertex {
+        validate_vertex_attributes(&module, entry_point_index, vertex_buffer_layouts)
+            .map_err(error)?;
+    }
+    Ok(())
+}
+
+fn validate_bindings(
+    module: &Module,
+    info: &ModuleInfo,
+    stage: ShaderStage,
+    entry_point_index: usize,
+    bind_groups: &[&[wgpu::BindGroupLayoutEntry]],
+) -> Result<(), ShaderValidationErrorKind> {
+    let function_info = info.get_entry_point(entry_point_index);
+    let stage = match stage {
+        ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+        ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+        _ => wgpu::ShaderStages::COMPUTE,
+    };
+    let mut layouter = Layouter::default();
+    layouter
+        .update(module.to_ctx())
+        .expect("layouts of a validated module are computable");
+    for (handle, variable) in module.global_variables.iter() {
+        let Some(resource_binding) = &variable.binding else {
+            continue;
+        };
+        if function_info[handle].is_empty() {
+            continue;
+        }
+        let (group, binding) = (resource_binding.group, resource_binding.binding);
+        let name = variable.name.clone().unwrap_or_default();
+        let entry = bind_groups
+            .get(group as usize)
+            .and_then(|entries| entries.iter().find(|entry| entry.binding == binding))
+            .ok_or_else(|| MissingBinding {
+                group,
+                binding,
+                name: name.clone(),
+            })?;
+        if !entry.visibility.contains(stage) {
+            return Err(BindingNotVisible {
+                group,
+                binding,
+                name,
+                stage,
+            });
+        }
+        let size = layouter[variable.ty].size;
+        if !binding_type_matches(module, variable.space, variable.ty, size, &entry.ty) {
+            return Err(BindingTypeMismatch {
+                group,
+                binding,
+                name,
+                expected: entry.ty,
+                found: describe_binding(module, variable.space, variable.ty, size),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn binding_type_matches(
+    module: &Module,
+    space: AddressSpace,
+    ty: naga::Handle<naga::Type>,
+    size: u32,
+    expected: &wgpu::BindingType,
+) -> bool {
+    // Whether the shader reads no more bytes than the bind group guarantees.
+    let fits = |min_binding_size: Option<wgpu::BufferSize>| {
+        min_binding_size.is_none_or(|min_binding_size| u64::from(size) <= min_binding_size.get())
+    };
+    match (space, &module.types[ty].inner, expected) {
+        (
+            AddressSpace::Uniform,
+            _,
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                min_binding_size,
+                ..
+            },
+        ) => fits(*min_binding_size),
+        (
+            AddressSpace::Storage { access },
+            _,
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                min_binding_size,
+                ..
+            },
+        ) => *read_only != access.contains(naga::StorageAccess::STORE) && fits(*min_binding_size),
+        (
+            AddressSpace::Handle,
+            &TypeInner::Sampler { comparison },
+            wgpu::BindingType::Sampler(sampler),
+        ) => comparison == (*sampler == wgpu::SamplerBindingType::Comparison),
+        (
+            AddressSpace::Handle,
+            &TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            },
+            &wgpu::BindingType::Texture {
+                sample_type,
+                view_dimension,
+                multisampled,
+            },
+        ) => {
+            let class_matches = match class {
+                ImageClass::Sampled { kind, multi } => {
+                    let kind_matches = match kind {
+                        ScalarKind::Float => matches!(
+                            sample_type,
+                            wgpu::TextureSampleType::Float { .. } | wgpu::TextureSampleType::Depth
+                        ),
+                        ScalarKind::Sint => sample_type == wgpu::TextureSampleType::Sint,
+                        ScalarKind::Uint => sample_type == wgpu::TextureSampleType::Uint,
+                        _ => false,
+                    };
+                    kind_matches && multi == multisampled
+                }
+                ImageClass::Depth { multi } => {
+                    sample_type == wgpu::TextureSampleType::Depth && multi == multisampled
+                }
+                ImageClass::Storage { .. } => false,
+            };
+            class_matches && view_dimension_of(dim, arrayed) == view_dimension
+        }
+        (
+            AddressSpace::Handle,
+            &TypeInner::Image {
+                dim,
+                arrayed,
+                class: ImageClass::Storage { .. },
+            },
+            &wgpu::BindingType::StorageTexture { view_dimension, .. },
+        ) => view_dimension_of(dim, arrayed) == view_dimension,
+        _ => false,
+    }
+}
+
+fn view_dimension_of(dim: ImageDimension, arrayed: bool) -> wgpu::TextureViewDimension {
+    match (dim, arrayed) {
+        (ImageDimension::D1, _) => wgpu::TextureViewDimension::D1,
+        (ImageDimension::D2, false) => wgpu::TextureViewDimension::D2,
+        (ImageDimension::D2, true) => wgpu::TextureViewDimension::D2Array,
+        (ImageDimension::D3, _) => wgpu::TextureViewDimension::D3,
+        (ImageDimension::Cube, false) => wgpu::TextureViewDimension::Cube,
+        (ImageDimension::Cube, true) => wgpu::TextureViewDimension::CubeArray,
+    }
+}
+
+/// Describes a binding in WGSL terms, e.g. "texture_2d<f32>".
+fn describe_binding(
+    module: &Module,
+    space: AddressSpace,
+    ty: naga::Handle<naga::Type>,
+    size: u32,
+) -> String {
+    match (space, &module.types[ty].inner) {
+        (AddressSpace::Uniform, _) => format!("var<uniform> of {size} bytes"),
+        (AddressSpace::Storage { .. }, _) => format!("var<storage> of {size} bytes"),
+        (_, TypeInner::Sampler { comparison: false }) => "sampler".to_string(),
+        (_, TypeInner::Sampler { comparison: true }) => "sampler_comparison".to_string(),
+        (
+            _,
+            &TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            },
+        ) => {
+            let dimension = match view_dimension_of(dim, arrayed) {
+                wgpu::TextureViewDimension::D1 => "1d",
+                wgpu::TextureViewDimension::D2 => "2d",
+                wgpu::TextureViewDimension::D2Array => "2d_array",
+                wgpu::TextureViewDimension::D3 => "3d",
+                wgpu::TextureViewDimension::Cube => "cube",
+                wgpu::TextureViewDimension::CubeArray => "cube_array",
+            };
+            match class {
+                ImageClass::Sampled { kind, multi } => {
+                    let multisampled = if multi { "multisampled_" } else { "" };
+                    let scalar = describe_scalar_kind(kind);
+                    format!("texture_{multisampled}{dimension}<{scalar}>")
+                }
+                ImageClass::Depth { multi } => {
+                    let multisampled = if multi { "multisampled_" } else { "" };
+                    format!("texture_depth_{multisampled}{dimension}")
+                }
+                ImageClass::Storage { format, .. } => {
+                    format!("texture_storage_{dimension}<{format:?}>")
+                }
+            }
+        }
+        (_, inner) => format!("{inner:?}"),
+    }
+}
+
+fn describe_scalar_kind(kind: ScalarKind) -> &'static str {
+    match kind {
+        ScalarKind::Sint => "i32",
+        ScalarKind::Uint => "u32",
+        ScalarKind::Float => "f32",
+        ScalarKind::Bool => "bool",
+        ScalarKind::AbstractInt => "abstract-int",
+        ScalarKind::AbstractFloat => "abstract-float",
+    }
+}
+
+fn validate_vertex_attributes(
+    module: &Module,
+    entry_point_index: usize,
+    vertex_buffer_layouts: &[wgpu::VertexBufferLayout],
+) -> Result<(), ShaderValidationErrorKind> {
+    let function = &module.entry_points[entry_point_index].function;
+    // Inputs are either arguments, or members of structs that are arguments.
+    let mut inputs = Vec::new();
+    for argument in &function.arguments {
+        match (&argument.binding, &module.types[argument.ty].inner) {
+            (Some(binding), _) => inputs.push((argument.name.clone(), binding, argument.ty)),
+            (None, TypeInner::Struct { members, .. }) => {
+                for member in members {
+                    if let Some(binding) = &member.binding {
+                        inputs.push((member.name.clone(), binding, member.ty));
+                    }
+                }
+            }
+            (None, _) => (),
+        }
+    }
+    for (name, binding, ty) in inputs {
+        let &Binding::Location { location, .. } = binding else {
+            continue;
+        };
+        let name = name.unwrap_or_default();
+        let attribute = vertex_buffer_layouts
+            .iter()
+            .flat_map(|layout| layout.attributes)
+            .find(|attribute| attribute.shader_location == location)
+            .ok_or_else(|| MissingVertexAttribute {
+                location,
+                name: name.clone(),
+            })?;
+        let (kind, components) = match module.types[ty].inner {
+            TypeInner::Scalar(scalar) => (scalar.kind, 1),
+            TypeInner::Vector { size, scalar } => (scalar.kind, size as u32),
+            _ => (ScalarKind::Bool, 0),
+        };
+        // Like wgpu, only the kind of scalar has to match. Missing components are filled with 0
+        // and 1 for w, extra ones are dropped.
+        if vertex_format_scalar_kind(attribute.format) != kind {
+            let found = match components {
+                1 => describe_scalar_kind(kind).to_string(),
+                _ => format!("vec{components}<{}>", describe_scalar_kind(kind)),
+            };
+            return Err(VertexAttributeTypeMismatch {
+                location,
+                name,
+                expected: attribute.format,
+                found,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The kind of scalar a vertex format is read as in shaders. Normalized formats are read as
+/// floats.
+fn vertex_format_scalar_kind(format: wgpu::VertexFormat) -> ScalarKind {
+    use wgpu::VertexFormat as F;
+    match format {
+        F::Uint8 | F::Uint8x2 | F::Uint8x4 | F::Uint16 | F::Uint16x2 | F::Uint16x4 => {
+            ScalarKind::Uint
+        }
+        F::Uint32 | F::Uint32x2 | F::Uint32x3 | F::Uint32x4 => ScalarKind::Uint,
+        F::Sint8 | F::Sint8x2 | F::Sint8x4 | F::Sint16 | F::Sint16x2 | F::Sint16x4 => {
+            ScalarKind::Sint
+        }
+        F::Sint32 | F::Sint32x2 | F::Sint32x3 | F::Sint32x4 => ScalarKind::Sint,
+        _ => ScalarKind::Float,
+    }
+}
@@ -0,0 +1,34 @@
+#import tbn::cube_map
+
+const PI: f32 = 3.14159265358979323846;
+
+@group(0) @binding(0) var equirectangular: texture_2d<f32>;
+@group(0) @binding(1) var equirectangular_sampler: sampler;
+@group(0) @binding(2) var<uniform> face: u32;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    /// From (0, 0) in the top left corner of the face to (1, 1) in the bottom right corner.
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // A triangle covering the whole face.
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var result: VertexOutput;
+    result.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    result.uv = uv;
+    return result;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let direction = normalize(cube_face_direction(face, in.uv));
+    // +X is in the center of the image, and +Y at its top.
+    let uv = vec2<f32>(
+        atan2(direction.z, direction.x) / (2.0 * PI) + 0.5,
+        acos(clamp(direction.y, -1.0, 1.0)) / PI,
+    );
+    return textureSampleLevel(equirectangular, equirectangular_sampler, uv, 0.0);
+}
@@ -0,0 +1,175 @@
+// Precomputation of image-based lighting: the mip levels of an environment cube map, its diffuse
+// irradiance and GGX-prefiltered specular radiance, rendered into one face of a cube map at a
+// time, and the split-sum BRDF lookup texture.
+
+#import tbn::cube_map
+
+const PI: f32 = 3.14159265358979;
+const IRRADIANCE_AZIMUTH_STEPS: u32 = 64u;
+const IRRADIANCE_ELEVATION_STEPS: u32 = 16u;
+const SPECULAR_SAMPLE_COUNT: u32 = 256u;
+
+struct Convolution {
+    face: u32,
+    // Perceptual roughness that the mip level being rendered is prefiltered for.
+    roughness: f32,
+};
+
+@group(0) @binding(0) var environment: texture_cube<f32>;
+@group(0) @binding(1) var environment_sampler: sampler;
+@group(0) @binding(2) var<uniform> convolution: Convolution;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // From (0, 0) in the top left corner of the target to (1, 1) in the bottom right corner.
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // A triangle covering the whole target.
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var result: VertexOutput;
+    result.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    result.uv = uv;
+    return result;
+}
+
+// Rotates directions around +Z into directions around `normal`.
+fn tangent_basis(normal: vec3<f32>) -> mat3x3<f32> {
+    var up = vec3<f32>(0.0, 0.0, 1.0);
+    if abs(normal.z) > 0.999 {
+        up = vec3<f32>(1.0, 0.0, 0.0);
+    }
+    let tangent = normalize(cross(up, normal));
+    let bitangent = cross(normal, tangent);
+    return mat3x3<f32>(tangent, bitangent, normal);
+}
+
+// The `i`th of `count` points of the Hammersley set, evenly spread over [0, 1)^2.
+fn hammersley(i: u32, count: u32) -> vec2<f32> {
+    return vec2<f32>(f32(i) / f32(count), f32(reverseBits(i)) * 2.3283064365386963e-10);
+}
+
+// A half vector around +Z, distributed by the GGX normal distribution of `roughness`.
+fn ggx_half_vector(xi: vec2<f32>, roughness: f32) -> vec3<f32> {
+    let alpha = roughness * roughness;
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (alpha * alpha - 1.0) * xi.y));
+    let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    return vec3<f32>(sin_theta * cos(phi), sin_theta * sin(phi), cos_theta);
+}
+
+// Solid angle of a texel of the first mip level of the environment, about the same for all texels.
+fn texel_solid_angle() -> f32 {
+    let size = f32(textureDimensions(environment).x);
+    return 4.0 * PI / (6.0 * size * size);
+}
+
+// Samples the environment at the mip level whose texels cover about `solid_angle`, so that sparse
+// samples of a high-frequency environment average its texels instead of missing or hitting them
+// (filtered importance sampling, as in Karis 2013).
+fn sample_environment(direction: vec3<f32>, solid_angle: f32) -> vec3<f32> {
+    let lod = max(0.5 * log2(solid_angle / texel_solid_angle()), 0.0);
+    return textureSampleLevel(environment, environment_sampler, direction, lod).rgb;
+}
+
+// The first mip level of the environment in the direction of the texel, or when rendering the
+// next levels from the level above, the average of the 2x2 texels around it.
+@fragment
+fn fs_resample(in: VertexOutput) -> @location(0) vec4<f32> {
+    let direction = normalize(cube_face_direction(convolution.face, in.uv));
+    let color = textureSampleLevel(environment, environment_sampler, direction, 0.0);
+    return vec4<f32>(color.rgb, 1.0);
+}
+
+// Irradiance reaching a surface facing the direction of the texel, divided by π so that a
+// Lambertian surface reflects its albedo times it.
+@fragment
+fn fs_irradiance(in: VertexOutput) -> @location(0) vec4<f32> {
+    let normal = normalize(cube_face_direction(convolution.face, in.uv));
+    let basis = tangent_basis(normal);
+    let theta_step = 0.5 * PI / f32(IRRADIANCE_ELEVATION_STEPS);
+    let phi_step = 2.0 * PI / f32(IRRADIANCE_AZIMUTH_STEPS);
+    var sum = vec3<f32>(0.0);
+    for (var i = 0u; i < IRRADIANCE_AZIMUTH_STEPS; i += 1u) {
+        let phi = (f32(i) + 0.5) * phi_step;
+        for (var j = 0u; j < IRRADIANCE_ELEVATION_STEPS; j += 1u) {
+            let theta = (f32(j) + 0.5) * theta_step;
+            let direction = vec3<f32>(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            let solid_angle = sin(theta) * theta_step * phi_step;
+            sum += sample_environment(basis * direction, solid_angle) * cos(theta) * sin(theta);
+        }
+    }
+    let sample_count = f32(IRRADIANCE_AZIMUTH_STEPS * IRRADIANCE_ELEVATION_STEPS);
+    return vec4<f32>(PI * sum / sample_count, 1.0);
+}
+
+// Radiance reflected towards the direction of the texel by a surface facing it, weighted by the
+// GGX distribution. The view direction is assumed to equal the normal.
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    let normal = normalize(cube_face_direction(convolution.face, in.uv));
+    if convolution.roughness == 0.0 {
+        let color = textureSampleLevel(environment, environment_sampler, normal, 0.0);
+        return vec4<f32>(color.rgb, 1.0);
+    }
+    let basis = tangent_basis(normal);
+    let alpha = convolution.roughness * convolution.roughness;
+    var sum = vec3<f32>(0.0);
+    var weight = 0.0;
+    for (var i = 0u; i < SPECULAR_SAMPLE_COUNT; i += 1u) {
+        let xi = hammersley(i, SPECULAR_SAMPLE_COUNT);
+        let local_half_vector = ggx_half_vector(xi, convolution.roughness);
+        let half_vector = basis * local_half_vector;
+        let to_light = reflect(-normal, half_vector);
+        let n_dot_l = dot(normal, to_light);
+        if n_dot_l > 0.0 {
+            // With the view direction equal to the normal, the PDF of `to_light` is D / 4.
+            let n_dot_h = local_half_vector.z;
+            let denominator = n_dot_h * n_dot_h * (alpha * alpha - 1.0) + 1.0;
+            let distribution = alpha * alpha / (PI * denominator * denominator);
+            let pdf = distribution / 4.0;
+            let solid_angle = 1.0 / (f32(SPECULAR_SAMPLE_COUNT) * pdf + 1e-4);
+            sum += sample_environment(to_light, solid_angle) * n_dot_l;
+            weight += n_dot_l;
+        }
+    }
+    return vec4<f32>(sum / max(weight, 1e-4), 1.0);
+}
+
+// Smith's method with Schlick-GGX, with the remapping of roughness for image-based lighting.
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = roughness * roughness / 2.0;
+    let g_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let g_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return g_v * g_l;
+}
+
+// Scale (red) and bias (green) of F0 in the specular reflectance of a surface, with the cosine of
+// the view angle increasing to the right and the roughness increasing downwards.
+@fragment
+fn fs_brdf(in: VertexOutput) -> @location(0) vec4<f32> {
+    let n_dot_v = max(in.uv.x, 1e-4);
+    let roughness = in.uv.y;
+    let to_eye = vec3<f32>(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    var scale = 0.0;
+    var bias = 0.0;
+    for (var i = 0u; i < SPECULAR_SAMPLE_COUNT; i += 1u) {
+        let xi = hammersley(i, SPECULAR_SAMPLE_COUNT);
+        let half_vector = ggx_half_vector(xi, roughness);
+        let to_light = reflect(-to_eye, half_vector);
+        let n_dot_l = to_light.z;
+        if n_dot_l > 0.0 {
+            let n_dot_h = max(half_vector.z, 0.0);
+            let v_dot_h = max(dot(to_eye, half_vector), 0.0);
+            let visibility = geometry_smith(n_dot_v, n_dot_l, roughness) * v_dot_h
+                / max(n_dot_h * n_dot_v, 1e-4);
+            let fresnel = pow(1.0 - v_dot_h, 5.0);
+            scale += (1.0 - fresnel) * visibility;
+            bias += fresnel * visibility;
+        }
+    }
+    let sample_count = f32(SPECULAR_SAMPLE_COUNT);
+    return vec4<f32>(scale / sample_count, bias / sample_count, 0.0, 1.0);
+}
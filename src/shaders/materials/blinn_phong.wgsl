@@ -0,0 +1,34 @@
+// Requires a mesh whose vertex shader outputs the TBN basis in view space, e.g. `Mesh3DTbn`.
+#define VERTEX_OUTPUT_TBN
+#import tbn::vertex_output
+#import tbn::camera
+#import tbn::lights
+
+@group(2) @binding(0) var<uniform> diffuse_color: vec4<f32>;
+@group(2) @binding(1) var<uniform> specular_color: vec4<f32>;
+@group(2) @binding(2) var<uniform> shininess: f32;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let diffuse = diffuse_color * vertex.color;
+    alpha_mask(diffuse.a);
+    let normal = normalize(vertex.normal);
+    // The camera is at the origin in view space.
+    let to_eye = normalize(-vertex.view_position);
+
+    var color = (lights.ambient + environment_irradiance(normal)) * diffuse.rgb;
+    for (var i = 0u; i < min(lights.count, MAX_LIGHTS); i += 1u) {
+        let light = lights.lights[i];
+        let incidence = light_incidence(light, vertex.view_position);
+        let to_light = incidence.to_light;
+        let radiance = light.color * light.intensity * incidence.attenuation;
+        let lambert = max(dot(normal, to_light), 0.0);
+        let half_vector = normalize(to_light + to_eye);
+        var specular = 0.0;
+        if lambert > 0.0 {
+            specular = pow(max(dot(normal, half_vector), 0.0), shininess);
+        }
+        color += radiance * (diffuse.rgb * lambert + specular_color.rgb * specular);
+    }
+    return vec4<f32>(color, diffuse.a);
+}
@@ -0,0 +1,119 @@
+// Requires a mesh whose vertex shader outputs the TBN basis in view space, e.g. `Mesh3DTbn`.
+// With `NORMAL_MAP` defined, the normal is taken from `normal_texture`.
+#define VERTEX_OUTPUT_TBN
+#import tbn::vertex_output
+#import tbn::camera
+#import tbn::lights
+
+struct Factors {
+    base_color: vec4<f32>,
+    emissive: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+};
+
+const PI: f32 = 3.14159265358979;
+
+@group(2) @binding(0) var<uniform> factors: Factors;
+@group(2) @binding(1) var base_color_texture: texture_2d<f32>;
+@group(2) @binding(2) var base_color_sampler: sampler;
+// Roughness in the green channel, metallic in the blue channel.
+@group(2) @binding(3) var metallic_roughness_texture: texture_2d<f32>;
+@group(2) @binding(4) var metallic_roughness_sampler: sampler;
+@group(2) @binding(5) var normal_texture: texture_2d<f32>;
+@group(2) @binding(6) var normal_sampler: sampler;
+@group(2) @binding(7) var occlusion_texture: texture_2d<f32>;
+@group(2) @binding(8) var occlusion_sampler: sampler;
+@group(2) @binding(9) var emissive_texture: texture_2d<f32>;
+@group(2) @binding(10) var emissive_sampler: sampler;
+
+// Trowbridge-Reitz GGX normal distribution.
+fn distribution_ggx(n_dot_h: f32, alpha: f32) -> f32 {
+    let alpha2 = alpha * alpha;
+    let denominator = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    return alpha2 / (PI * denominator * denominator);
+}
+
+// Smith's method with Schlick-GGX for both the view and the light direction.
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    let g_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let g_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return g_v * g_l;
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (1.0 - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+// Fresnel-Schlick averaged over the microfacets of a rough surface, for light from all directions.
+fn fresnel_schlick_roughness(cos_theta: f32, f0: vec3<f32>, roughness: f32) -> vec3<f32> {
+    return f0 + (max(vec3<f32>(1.0 - roughness), f0) - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let base_color = factors.base_color * vertex.color
+        * textureSample(base_color_texture, base_color_sampler, vertex.uv);
+    alpha_mask(base_color.a);
+    let metallic_roughness =
+        textureSample(metallic_roughness_texture, metallic_roughness_sampler, vertex.uv);
+    let metallic = saturate(factors.metallic * metallic_roughness.b);
+    // Perceptual roughness, clamped to avoid a singular highlight.
+    let roughness = clamp(factors.roughness * metallic_roughness.g, 0.04, 1.0);
+    let alpha = roughness * roughness;
+    let occlusion = textureSample(occlusion_texture, occlusion_sampler, vertex.uv).r;
+    let emissive =
+        factors.emissive * textureSample(emissive_texture, emissive_sampler, vertex.uv).rgb;
+
+#ifdef NORMAL_MAP
+    var tangent_normal = textureSample(normal_texture, normal_sampler, vertex.uv).xyz * 2.0 - 1.0;
+    tangent_normal = vec3<f32>(tangent_normal.xy * factors.normal_scale, tangent_normal.z);
+    let tbn = mat3x3<f32>(
+        normalize(vertex.tangent),
+        normalize(vertex.bitangent),
+        normalize(vertex.normal),
+    );
+    let normal = normalize(tbn * tangent_normal);
+#else
+    let normal = normalize(vertex.normal);
+#endif
+    // The camera is at the origin in view space.
+    let to_eye = normalize(-vertex.view_position);
+    let n_dot_v = max(dot(normal, to_eye), 1e-4);
+
+    let f0 = mix(vec3<f32>(0.04), base_color.rgb, metallic);
+    let diffuse_color = base_color.rgb * (1.0 - metallic);
+
+    var color = vec3<f32>(0.0);
+    for (var i = 0u; i < min(lights.count, MAX_LIGHTS); i += 1u) {
+        let light = lights.lights[i];
+        let incidence = light_incidence(light, vertex.view_position);
+        let to_light = incidence.to_light;
+        let n_dot_l = dot(normal, to_light);
+        if n_dot_l <= 0.0 {
+            continue;
+        }
+        let half_vector = normalize(to_light + to_eye);
+        let n_dot_h = max(dot(normal, half_vector), 0.0);
+        let v_dot_h = max(dot(to_eye, half_vector), 0.0);
+
+        let fresnel = fresnel_schlick(v_dot_h, f0);
+        let specular = distribution_ggx(n_dot_h, alpha)
+            * geometry_smith(n_dot_v, n_dot_l, roughness) * fresnel / (4.0 * n_dot_v * n_dot_l);
+        let diffuse = (1.0 - fresnel) * diffuse_color / PI;
+        let radiance = light.color * light.intensity * incidence.attenuation;
+        color += (diffuse + specular) * radiance * n_dot_l;
+    }
+    let fresnel = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+    let brdf = environment_brdf(n_dot_v, roughness);
+    let environment_diffuse = (1.0 - fresnel) * diffuse_color * environment_irradiance(normal);
+    let environment_specular = environment_specular(reflect(-to_eye, normal), roughness)
+        * (fresnel * brdf.x + brdf.y);
+    let ambient = (lights.ambient * base_color.rgb + environment_diffuse + environment_specular)
+        * mix(1.0, occlusion, factors.occlusion_strength);
+    color += ambient + emissive;
+    return vec4<f32>(color, base_color.a);
+}
@@ -1,7 +1,5 @@
-struct VertexOutput {
-    @location(0) uv: vec2<f32>,
-    @builtin(position) position: vec4<f32>,
-};
+#import tbn::vertex_output
+#import tbn::camera
 
 @group(2) @binding(0) var<uniform> fill_color: vec4<f32>;
 @group(2) @binding(1) var<uniform> center: vec2<f32>;
@@ -13,5 +11,7 @@ fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
     let aaf = fwidth(vertex.uv.x);
     let aaf_half = aaf * 0.5;
     let alpha = smoothstep(-aaf_half, aaf_half, -sd);
-    return vec4<f32>(fill_color.rgb, fill_color.a * alpha);
+    let color = fill_color * vertex.color;
+    alpha_mask(color.a * alpha);
+    return vec4<f32>(color.rgb, color.a * alpha);
 }
@@ -1,12 +1,11 @@
-struct VertexOutput {
-    @location(0) uv: vec2<f32>,
-    @builtin(position) position: vec4<f32>,
-};
+#import tbn::vertex_output
+#import tbn::camera
 
 @group(2) @binding(0) var<uniform> color: vec4<f32>;
 
 @fragment
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
-    return color;
+    let result = color * vertex.color;
+    alpha_mask(result.a);
+    return result;
 }
-
@@ -0,0 +1,20 @@
+// `STORAGE_FORMAT` is replaced with the WGSL texel format of the texture.
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var destination: texture_storage_2d<STORAGE_FORMAT, write>;
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= textureDimensions(destination)) {
+        return;
+    }
+    // Averages the 2x2 texels of the source level under the target texel, like `mipmap.wgsl`.
+    let max_texel = vec2<i32>(textureDimensions(source)) - 1;
+    let texel = vec2<i32>(id.xy) * 2;
+    var sum = vec4<f32>(0.0);
+    for (var y = 0; y < 2; y++) {
+        for (var x = 0; x < 2; x++) {
+            sum += textureLoad(source, min(texel + vec2<i32>(x, y), max_texel), 0);
+        }
+    }
+    textureStore(destination, id.xy, sum * 0.25);
+}
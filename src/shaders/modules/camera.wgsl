@@ -0,0 +1,25 @@
+// Matches `CameraUniform`.
+struct Camera {
+    projection: mat4x4<f32>,
+    model_view: mat4x4<f32>,
+    normal: mat4x4<f32>,
+    view: mat4x4<f32>,
+    inverse_view: mat4x4<f32>,
+};
+
+// Matches `ObjectUniform`.
+struct Object {
+    receives_shadows: u32,
+    alpha_cutoff: f32,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+@group(0) @binding(1) var<uniform> object: Object;
+
+// Discards the fragment if its alpha is below the cutoff of an `AlphaMode::Mask` material. Only
+// callable from fragment shaders.
+fn alpha_mask(alpha: f32) {
+    if alpha < object.alpha_cutoff {
+        discard;
+    }
+}
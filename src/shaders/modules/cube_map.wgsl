@@ -0,0 +1,17 @@
+// Directions of the texels of cube maps, for shaders that render into their faces.
+
+// The direction that a point on a face of a cube map is sampled with. Faces are in the order +X,
+// -X, +Y, -Y, +Z, -Z, and `uv` goes from (0, 0) in the top left corner of the face to (1, 1) in the
+// bottom right corner.
+fn cube_face_direction(face: u32, uv: vec2<f32>) -> vec3<f32> {
+    let s = uv.x * 2.0 - 1.0;
+    let t = uv.y * 2.0 - 1.0;
+    switch face {
+        case 0u: { return vec3<f32>(1.0, -t, -s); }
+        case 1u: { return vec3<f32>(-1.0, -t, s); }
+        case 2u: { return vec3<f32>(s, 1.0, t); }
+        case 3u: { return vec3<f32>(s, -1.0, -t); }
+        case 4u: { return vec3<f32>(s, -t, 1.0); }
+        default: { return vec3<f32>(-s, -t, -1.0); }
+    }
+}
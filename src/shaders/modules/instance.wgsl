@@ -0,0 +1,13 @@
+// Matches `Instance::LAYOUT`.
+struct Instance {
+    @location(8) model_0: vec4<f32>,
+    @location(9) model_1: vec4<f32>,
+    @location(10) model_2: vec4<f32>,
+    @location(11) model_3: vec4<f32>,
+    @location(12) color: vec4<f32>,
+    @location(13) uv_offset: vec2<f32>,
+};
+
+fn instance_model(instance: Instance) -> mat4x4<f32> {
+    return mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+}
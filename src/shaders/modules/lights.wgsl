@@ -0,0 +1,160 @@
+// Lights and the environment of the scene in bind group 3, matching `LightsBindGroup`, and
+// functions for shading with them in view space.
+
+#import tbn::camera
+
+const LIGHT_DIRECTIONAL: u32 = 0u;
+const LIGHT_POINT: u32 = 1u;
+const LIGHT_SPOT: u32 = 2u;
+const MAX_LIGHTS: u32 = 16u;
+const MAX_SHADOW_MAPS: u32 = 16u;
+
+struct Light {
+    position: vec3<f32>,
+    kind: u32,
+    direction: vec3<f32>,
+    range: f32,
+    color: vec3<f32>,
+    intensity: f32,
+    cos_inner_angle: f32,
+    cos_outer_angle: f32,
+    first_shadow_map: u32,
+    shadow_map_count: u32,
+};
+
+struct Lights {
+    ambient: vec3<f32>,
+    count: u32,
+    lights: array<Light, MAX_LIGHTS>,
+    // 0 without an environment.
+    environment_intensity: f32,
+    // Mip level of `prefiltered_environment` for a roughness of 1.
+    prefiltered_max_lod: f32,
+};
+
+struct ShadowMap {
+    world_to_shadow_map: mat4x4<f32>,
+    // Minimum and maximum texture coordinates of the tile in the atlas.
+    tile_bounds: vec4<f32>,
+    split_far: f32,
+};
+
+struct ShadowMaps {
+    shadow_maps: array<ShadowMap, MAX_SHADOW_MAPS>,
+};
+
+@group(3) @binding(0) var<uniform> lights: Lights;
+@group(3) @binding(1) var<uniform> shadow_maps: ShadowMaps;
+@group(3) @binding(2) var shadow_atlas: texture_depth_2d;
+@group(3) @binding(3) var shadow_sampler: sampler_comparison;
+@group(3) @binding(4) var irradiance_environment: texture_cube<f32>;
+@group(3) @binding(5) var prefiltered_environment: texture_cube<f32>;
+@group(3) @binding(6) var brdf_lut: texture_2d<f32>;
+@group(3) @binding(7) var environment_sampler: sampler;
+
+// Smoothly fades out to zero at `range`, with inverse-square falloff before that.
+fn distance_attenuation(distance: f32, range: f32) -> f32 {
+    let ratio = distance / range;
+    let window = saturate(1.0 - ratio * ratio * ratio * ratio);
+    return window * window / (distance * distance + 1.0);
+}
+
+// Fraction of the light that reaches the fragment, filtered over 3x3 texels of the shadow map.
+fn shadow_factor(light: Light, view_depth: f32, world_position: vec3<f32>) -> f32 {
+    if light.shadow_map_count == 0u || object.receives_shadows == 0u {
+        return 1.0;
+    }
+    // Cascades of directional lights are ordered from near to far.
+    var index = light.first_shadow_map;
+    let last = light.first_shadow_map + light.shadow_map_count - 1u;
+    while index < last && view_depth > shadow_maps.shadow_maps[index].split_far {
+        index += 1u;
+    }
+    let shadow_map = shadow_maps.shadow_maps[index];
+    if view_depth > shadow_map.split_far {
+        return 1.0;
+    }
+    let position = shadow_map.world_to_shadow_map * vec4<f32>(world_position, 1.0);
+    if position.w <= 0.0 {
+        return 1.0;
+    }
+    let coords = position.xyz / position.w;
+    let outside_tile = any(coords.xy < shadow_map.tile_bounds.xy)
+        || any(coords.xy > shadow_map.tile_bounds.zw);
+    if outside_tile || coords.z > 1.0 {
+        return 1.0;
+    }
+    // Clamped so that the filter doesn't sample neighbouring tiles.
+    let texel = 1.0 / vec2<f32>(textureDimensions(shadow_atlas));
+    let min_coords = shadow_map.tile_bounds.xy + texel * 0.5;
+    let max_coords = shadow_map.tile_bounds.zw - texel * 0.5;
+    var lit = 0.0;
+    for (var y = -1; y <= 1; y += 1) {
+        for (var x = -1; x <= 1; x += 1) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel;
+            let sample_coords = clamp(coords.xy + offset, min_coords, max_coords);
+            lit += textureSampleCompareLevel(shadow_atlas, shadow_sampler, sample_coords, coords.z);
+        }
+    }
+    return lit / 9.0;
+}
+
+
+// How a light reaches a fragment.
+struct LightIncidence {
+    // Direction from the fragment to the light in view space.
+    to_light: vec3<f32>,
+    // Attenuation by distance, by the cone of spot lights, and by shadows.
+    attenuation: f32,
+};
+
+fn light_incidence(light: Light, view_position: vec3<f32>) -> LightIncidence {
+    let direction = normalize((camera.view * vec4<f32>(light.direction, 0.0)).xyz);
+    var result: LightIncidence;
+    result.attenuation = 1.0;
+    if light.kind == LIGHT_DIRECTIONAL {
+        result.to_light = -direction;
+    } else {
+        let position = (camera.view * vec4<f32>(light.position, 1.0)).xyz;
+        let offset = position - view_position;
+        let distance = length(offset);
+        result.to_light = offset / distance;
+        result.attenuation = distance_attenuation(distance, light.range);
+        if light.kind == LIGHT_SPOT {
+            let cos_angle = dot(-result.to_light, direction);
+            result.attenuation *=
+                smoothstep(light.cos_outer_angle, light.cos_inner_angle, cos_angle);
+        }
+    }
+    let world_position = (camera.inverse_view * vec4<f32>(view_position, 1.0)).xyz;
+    result.attenuation *= shadow_factor(light, -view_position.z, world_position);
+    return result;
+}
+
+fn view_to_world_direction(direction: vec3<f32>) -> vec3<f32> {
+    return (camera.inverse_view * vec4<f32>(direction, 0.0)).xyz;
+}
+
+// Diffuse light of the environment reaching a surface with a normal in view space, already
+// divided by π, so that a Lambertian surface reflects its albedo times it.
+fn environment_irradiance(normal: vec3<f32>) -> vec3<f32> {
+    let direction = view_to_world_direction(normal);
+    let irradiance =
+        textureSampleLevel(irradiance_environment, environment_sampler, direction, 0.0);
+    return irradiance.rgb * lights.environment_intensity;
+}
+
+// Specular light of the environment reflected in a view space direction by a surface of a
+// perceptual roughness.
+fn environment_specular(reflection: vec3<f32>, roughness: f32) -> vec3<f32> {
+    let direction = view_to_world_direction(reflection);
+    let lod = roughness * lights.prefiltered_max_lod;
+    let radiance = textureSampleLevel(prefiltered_environment, environment_sampler, direction, lod);
+    return radiance.rgb * lights.environment_intensity;
+}
+
+// Scale (x) and bias (y) of F0 in the split-sum approximation of the specular reflectance.
+fn environment_brdf(n_dot_v: f32, roughness: f32) -> vec2<f32> {
+    let uv = vec2<f32>(n_dot_v, roughness);
+    return textureSampleLevel(brdf_lut, environment_sampler, uv, 0.0).rg;
+}
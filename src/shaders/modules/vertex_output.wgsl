@@ -0,0 +1,17 @@
+// Output of the vertex shaders of meshes, and input of the fragment shaders of materials.
+//
+// With `VERTEX_OUTPUT_TBN` defined, it also has the TBN basis in view space, which e.g. `Mesh3DTbn`
+// outputs.
+struct VertexOutput {
+    @location(0) uv: vec2<f32>,
+    // Tint from the instance, white for non-instanced objects.
+    @location(1) color: vec4<f32>,
+#ifdef VERTEX_OUTPUT_TBN
+    // Position, normal, tangent and bitangent in view space.
+    @location(2) view_position: vec3<f32>,
+    @location(3) normal: vec3<f32>,
+    @location(4) tangent: vec3<f32>,
+    @location(5) bitangent: vec3<f32>,
+#endif
+    @builtin(position) position: vec4<f32>,
+};
@@ -0,0 +1,18 @@
+#import tbn::vertex_output
+#import tbn::camera
+#import tbn::instance
+
+@vertex
+fn vs_main(
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    instance: Instance,
+) -> VertexOutput {
+    let model = instance_model(instance);
+    var result: VertexOutput;
+    result.color = instance.color;
+    result.uv = uv + instance.uv_offset;
+    result.position =
+        camera.projection * camera.model_view * model * vec4<f32>(position.xyz, 1.0);
+    return result;
+}
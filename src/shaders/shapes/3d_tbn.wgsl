@@ -0,0 +1,25 @@
+#define VERTEX_OUTPUT_TBN
+#import tbn::vertex_output
+
+#import tbn::camera
+
+@vertex
+fn vs_main(
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) tangent: vec4<f32>,
+) -> VertexOutput {
+    let view_position = camera.model_view * vec4<f32>(position, 1.0);
+    let view_normal = normalize((camera.normal * vec4<f32>(normal, 0.0)).xyz);
+    let view_tangent = normalize((camera.model_view * vec4<f32>(tangent.xyz, 0.0)).xyz);
+    var result: VertexOutput;
+    result.color = vec4<f32>(1.0);
+    result.uv = uv;
+    result.view_position = view_position.xyz;
+    result.normal = view_normal;
+    result.tangent = view_tangent;
+    result.bitangent = cross(view_normal, view_tangent) * tangent.w;
+    result.position = camera.projection * view_position;
+    return result;
+}
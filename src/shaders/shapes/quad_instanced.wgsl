@@ -0,0 +1,17 @@
+#import tbn::vertex_output
+#import tbn::camera
+#import tbn::instance
+
+@group(1) @binding(0) var<uniform> uv_transform: mat4x4<f32>;
+
+@vertex
+fn vs_main(@location(0) position: vec2<f32>, instance: Instance) -> VertexOutput {
+    let model = instance_model(instance);
+    var result: VertexOutput;
+    result.color = instance.color;
+    let uv = uv_transform * vec4<f32>(position.x, 1.0 - position.y, 0.0, 0.0);
+    result.uv = uv.xy + instance.uv_offset;
+    result.position =
+        camera.projection * camera.model_view * model * vec4<f32>(position.xy, 0.0, 1.0);
+    return result;
+}
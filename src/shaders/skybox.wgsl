@@ -0,0 +1,30 @@
+struct Skybox {
+    /// Inverse of the camera's projection times its view matrix without translation.
+    inverse_view_projection: mat4x4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> skybox: Skybox;
+@group(0) @binding(1) var environment: texture_cube<f32>;
+@group(0) @binding(2) var environment_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // A triangle covering the whole surface, at the maximum depth.
+    let ndc = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u)) * 2.0 - 1.0;
+    var result: VertexOutput;
+    result.position = vec4<f32>(ndc, 1.0, 1.0);
+    result.ndc = ndc;
+    return result;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let far = skybox.inverse_view_projection * vec4<f32>(in.ndc, 1.0, 1.0);
+    let direction = far.xyz / far.w;
+    return vec4<f32>(textureSample(environment, environment_sampler, direction).rgb, 1.0);
+}
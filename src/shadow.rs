@@ -0,0 +1,256 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::*;
+
+use crate::{
+    Camera, ComparingSampler, DepthStencilTexture2d, DepthStencilTextureFormat,
+    DepthStencilTextureView2d, DirectionalLight, SpotLight,
+};
+
+/// Number of cascades the shadow of a directional light is split into along the camera frustum.
+pub const SHADOW_CASCADE_COUNT: usize = 3;
+
+/// Maximum number of shadow maps in a scene. A directional light takes `SHADOW_CASCADE_COUNT`
+/// shadow maps, a spot light takes one. Lights that don't fit cast no shadows.
+pub const MAX_SHADOW_MAPS: usize = 16;
+
+/// Shadow maps are tiles of one atlas texture, with this many tiles along each side.
+pub(crate) const ATLAS_TILES_PER_ROW: u32 = 4;
+
+pub(crate) const SHADOW_MAP_FORMAT: DepthStencilTextureFormat =
+    DepthStencilTextureFormat::Depth32Float;
+
+/// How far behind the camera frustum objects still cast shadows from directional lights.
+const DIRECTIONAL_CASTER_MARGIN: f32 = 50.0;
+
+/// Ratio between logarithmic and uniform splits of cascades. Logarithmic splits give the areas near
+/// the camera more resolution.
+const CASCADE_SPLIT_LAMBDA: f32 = 0.5;
+
+/// `cgmath` produces projections for OpenGL's depth range of [-1, 1], this remaps it to [0, 1].
+#[rustfmt::skip]
+const OPENGL_TO_WGPU: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+/// One shadow map to render, i.e. one cascade of a directional light, or a spot light.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ShadowMap {
+    pub(crate) view: Matrix4<f32>,
+    pub(crate) projection: Matrix4<f32>,
+    /// View-space depth up to which this cascade is used, `f32::MAX` for spot lights.
+    pub(crate) split_far: f32,
+}
+
+impl ShadowMap {
+    /// Shadow maps of the cascades of a directional light, fitted to the slices of the camera
+    /// frustum between `camera.near` and `shadow_distance` (or `camera.far` if nearer).
+    pub(crate) fn directional(
+        light: &DirectionalLight,
+        camera: &Camera,
+        viewport_size: Vector2<f32>,
+        shadow_distance: f32,
+        shadow_map_size: u32,
+    ) -> [Self; SHADOW_CASCADE_COUNT] {
+        let near = camera.near;
+        let far = camera.far.min(shadow_distance).max(near);
+        let split = |i: usize| {
+            let ratio = i as f32 / SHADOW_CASCADE_COUNT as f32;
+            let logarithmic = near * (far / near).powf(ratio);
+            let uniform = near + (far - near) * ratio;
+            CASCADE_SPLIT_LAMBDA * logarithmic + (1.0 - CASCADE_SPLIT_LAMBDA) * uniform
+        };
+        let camera_world = camera
+            .view_matrix()
+            .invert()
+            .unwrap_or_else(Matrix4::identity);
+        let direction = light.direction.normalize();
+        let rotation = Matrix4::look_to_rh(Point3::origin(), direction, up_for(direction));
+        std::array::from_fn(|i| {
+            let (slice_near, slice_far) = (split(i), split(i + 1));
+            let corners = frustum_slice_corners(camera, viewport_size, slice_near, slice_far)
+                .map(|corner| camera_world.transform_point(corner));
+            // Bounding sphere instead of a tight box, so that the size of the shadow map in world
+            // space doesn't change when the camera rotates.
+            let center = Point3::centroid(&corners);
+            let radius = corners
+                .iter()
+                .map(|corner| corner.distance(center))
+                .fold(0.0, f32::max);
+            // Snap the center to whole texels, so that shadow edges don't shimmer when the camera
+            // moves.
+            let texel_size = 2.0 * radius / shadow_map_size as f32;
+            let center_in_light = rotation.transform_point(center);
+            let snapped = point3(
+                (center_in_light.x / texel_size).round() * texel_size,
+                (center_in_light.y / texel_size).round() * texel_size,
+                center_in_light.z,
+            );
+            let center = rotation
+                .invert()
+                .unwrap_or_else(Matrix4::identity)
+                .transform_point(snapped);
+            Self {
+                view: Matrix4::look_to_rh(center, direction, up_for(direction)),
+                projection: OPENGL_TO_WGPU
+                    * cgmath::ortho(
+                        -radius,
+                        radius,
+                        -radius,
+                        radius,
+                        -radius - DIRECTIONAL_CASTER_MARGIN,
+                        radius,
+                    ),
+                split_far: slice_far,
+            }
+        })
+    }
+
+    /// `None` for lights that light nothing, whose cone has no angle, range or direction.
+    pub(crate) fn spot(light: &SpotLight) -> Option<Self> {
+        if light.outer_angle.0 <= 0.0 || light.range <= 0.0 || light.direction.is_zero() {
+            return None;
+        }
+        let direction = light.direction.normalize();
+        let max_fov = Rad::from(Deg(170.0));
+        let fov = Rad((light.outer_angle.0 * 2.0).min(max_fov.0));
+        Some(Self {
+            view: Matrix4::look_to_rh(light.position, direction, up_for(direction)),
+            projection: OPENGL_TO_WGPU
+                * cgmath::perspective(fov, 1.0, light.range * 0.01, light.range),
+            split_far: f32::MAX,
+        })
+    }
+
+    pub(crate) fn view_projection(&self) -> Matrix4<f32> {
+        self.projection * self.view
+    }
+}
+
+fn up_for(direction: Vector3<f32>) -> Vector3<f32> {
+    if direction.y.abs() > 0.99 {
+        Vector3::unit_z()
+    } else {
+        Vector3::unit_y()
+    }
+}
+
+/// Corners of the part of the camera frustum between two view-space depths, in view space.
+fn frustum_slice_corners(
+    camera: &Camera,
+    viewport_size: Vector2<f32>,
+    near: f32,
+    far: f32,
+) -> [Point3<f32>; 8] {
+    let half_extent = |depth: f32| {
+        if camera.fov.0.is_zero() {
+            viewport_size / 2.0
+        } else {
+            let half_height = depth * (camera.fov / 2.0).tan();
+            vec2(half_height * viewport_size.x / viewport_size.y, half_height)
+        }
+    };
+    let (near_extent, far_extent) = (half_extent(near), half_extent(far));
+    [
+        point3(-near_extent.x, -near_extent.y, -near),
+        point3(near_extent.x, -near_extent.y, -near),
+        point3(-near_extent.x, near_extent.y, -near),
+        point3(near_extent.x, near_extent.y, -near),
+        point3(-far_extent.x, -far_extent.y, -far),
+        point3(far_extent.x, -far_extent.y, -far),
+        point3(-far_extent.x, far_extent.y, -far),
+        point3(far_extent.x, far_extent.y, -far),
+    ]
+}
+
+/// Shadow maps of all lights of a scene, as tiles of one depth texture.
+#[derive(Debug, Clone)]
+pub(crate) struct ShadowAtlas {
+    texture: DepthStencilTexture2d,
+    shadow_map_size: u32,
+}
+
+impl ShadowAtlas {
+    pub(crate) fn create(device: &wgpu::Device, shadow_map_size: u32) -> Self {
+        let texture = DepthStencilTexture2d::create(
+            device,
+            vec2(shadow_map_size, shadow_map_size) * ATLAS_TILES_PER_ROW,
+            SHADOW_MAP_FORMAT,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+        Self {
+            texture,
+            shadow_map_size,
+        }
+    }
+
+    pub(crate) fn view(&self) -> DepthStencilTextureView2d {
+        self.texture.view(wgpu::TextureSampleType::Depth)
+    }
+
+    pub(crate) fn shadow_map_size(&self) -> u32 {
+        self.shadow_map_size
+    }
+
+    /// Position of the top left corner of a tile in texels.
+    pub(crate) fn tile_origin(&self, index: usize) -> Vector2<u32> {
+        let index = index as u32;
+        vec2(index % ATLAS_TILES_PER_ROW, index / ATLAS_TILES_PER_ROW) * self.shadow_map_size
+    }
+
+    /// The uniform of a shadow map rendered into the tile at `index`.
+    pub(crate) fn shadow_map_uniform(
+        &self,
+        index: usize,
+        shadow_map: &ShadowMap,
+    ) -> ShadowMapUniform {
+        let tile_scale = 1.0 / ATLAS_TILES_PER_ROW as f32;
+        let origin = self.tile_origin(index).map(|x| x as f32) / self.texture.size().x as f32;
+        // From clip space to texture coordinates, which have Y pointing down, then into the tile.
+        let clip_to_tile = Matrix4::from_translation(vec3(origin.x, origin.y, 0.0))
+            * Matrix4::from_nonuniform_scale(tile_scale, tile_scale, 1.0)
+            * Matrix4::from_translation(vec3(0.5, 0.5, 0.0))
+            * Matrix4::from_nonuniform_scale(0.5, -0.5, 1.0);
+        ShadowMapUniform {
+            world_to_shadow_map: (clip_to_tile * shadow_map.view_projection()).into(),
+            tile_bounds: [
+                origin.x,
+                origin.y,
+                origin.x + tile_scale,
+                origin.y + tile_scale,
+            ],
+            split_far: shadow_map.split_far,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+/// Matches `struct ShadowMap` in the lit material shaders.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub(crate) struct ShadowMapUniform {
+    world_to_shadow_map: [[f32; 4]; 4],
+    /// Minimum and maximum texture coordinates of the tile in the atlas.
+    tile_bounds: [f32; 4],
+    split_far: f32,
+    _padding: [f32; 3],
+}
+
+/// Matches `struct ShadowMaps` in the lit material shaders.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub(crate) struct ShadowMapsUniform {
+    pub(crate) shadow_maps: [ShadowMapUniform; MAX_SHADOW_MAPS],
+}
+
+pub(crate) fn create_shadow_sampler(device: &wgpu::Device) -> ComparingSampler {
+    ComparingSampler::create(
+        device,
+        wgpu::AddressMode::ClampToEdge,
+        wgpu::FilterMode::Linear,
+        wgpu::FilterMode::Linear,
+        wgpu::CompareFunction::LessEqual,
+    )
+}
@@ -0,0 +1,146 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::*;
+
+use crate::{
+    AsBindGroup, CameraRef, DepthStencilTextureFormat, Sampler, TextureFormat, TextureViewCube,
+    UniformBuffer, binding, create_wgsl_shader_module,
+};
+
+/// A cube map drawn behind all objects of a scene, in the direction that the camera looks at. Only
+/// the rotation of the camera matters, so the environment appears infinitely far away. See
+/// `Scene::set_skybox`.
+///
+/// The cube map must have a filterable float format, e.g. one converted from a HDR panorama with
+/// `TextureCube::from_equirectangular`.
+#[derive(Debug, Clone)]
+pub struct Skybox {
+    camera: CameraRef,
+    texture: TextureViewCube,
+    sampler: Sampler,
+}
+
+impl Skybox {
+    pub fn new(camera: CameraRef, texture: TextureViewCube, sampler: Sampler) -> Self {
+        Self {
+            camera,
+            texture,
+            sampler,
+        }
+    }
+
+    pub fn camera(&self) -> &CameraRef {
+        &self.camera
+    }
+
+    pub fn texture(&self) -> &TextureViewCube {
+        &self.texture
+    }
+
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub(crate) struct SkyboxUniform {
+    pub(crate) inverse_view_projection: [[f32; 4]; 4],
+}
+
+#[derive(Debug, Clone, AsBindGroup)]
+pub(crate) struct SkyboxBindGroup {
+    #[binding(0, fragment)]
+    pub(crate) uniform: UniformBuffer<SkyboxUniform>,
+    #[binding(1, fragment)]
+    pub(crate) texture: TextureViewCube,
+    #[binding(2, fragment)]
+    pub(crate) sampler: Sampler,
+}
+
+/// A `Skybox` with its GPU resources, created for the surface formats of a scene.
+#[derive(Debug, Clone)]
+pub(crate) struct SkyboxStorage {
+    pub(crate) skybox: Skybox,
+    bind_group: SkyboxBindGroup,
+    wgpu_bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl SkyboxStorage {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        skybox: Skybox,
+        color_format: TextureFormat,
+        depth_stencil_format: DepthStencilTextureFormat,
+    ) -> Self {
+        let bind_group = SkyboxBindGroup {
+            uniform: UniformBuffer::create_init(device, SkyboxUniform::zeroed()),
+            texture: skybox.texture.clone(),
+            sampler: skybox.sampler.clone(),
+        };
+        let (wgpu_bind_group, bind_group_layout) =
+            binding::create_wgpu_bind_group(device, &bind_group);
+        let shader =
+            create_wgsl_shader_module(device, "skybox", include_str!("./shaders/skybox.wgsl"));
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("skybox"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("skybox"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::TextureFormat::from(color_format).into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // Drawn at the maximum depth, so only where no opaque object has been drawn.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: depth_stencil_format.into(),
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        Self {
+            skybox,
+            bind_group,
+            wgpu_bind_group,
+            pipeline,
+        }
+    }
+
+    /// Writes the rotation and projection of the camera for the next draw.
+    pub(crate) fn update(&self, queue: &wgpu::Queue, viewport_size: Vector2<f32>) {
+        let camera = self.skybox.camera.lock();
+        let mut rotation = camera.view_matrix();
+        rotation.w = vec4(0.0, 0.0, 0.0, 1.0);
+        let view_projection = camera.projection_matrix(viewport_size) * rotation;
+        let inverse_view_projection = view_projection.invert().unwrap_or_else(Matrix4::identity);
+        self.bind_group.uniform.write(
+            SkyboxUniform {
+                inverse_view_projection: inverse_view_projection.into(),
+            },
+            queue,
+        );
+    }
+
+    pub(crate) fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.wgpu_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -4,7 +4,7 @@ use cgmath::*;
 use winit::{dpi::PhysicalSize, window::Window};
 
 use crate::{
-    DepthStencilTexture2d, DepthStencilTextureFormat, DepthStencilTextureView2d, Texture2d,
+    DepthStencilTexture2d, DepthStencilTextureFormat, DepthStencilTextureView2d, Rgba, Texture2d,
     TextureFormat, TextureView2d, TextureView2d_,
 };
 
@@ -129,13 +129,17 @@ impl Surface {
                 device,
                 size,
                 format,
-                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
             ),
             depth_stencil_texture: DepthStencilTexture2d::create(
                 device,
                 size,
                 DepthStencilTextureFormat::Depth32Float,
-                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
             ),
         }
     }
@@ -198,22 +202,34 @@ impl SurfaceView {
         }
     }
 
+    /// Begins a render pass that clears the surface. See `RenderPassOptions::default`.
     pub fn render_pass(&self, device: &wgpu::Device) -> RenderPass {
+        self.render_pass_with_options(device, &RenderPassOptions::default())
+    }
+
+    pub fn render_pass_with_options(
+        &self,
+        device: &wgpu::Device,
+        options: &RenderPassOptions,
+    ) -> RenderPass {
         self.render_pass_with_descriptor(device, &wgpu::RenderPassDescriptor {
             label: None,
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                 view: self.color_texture.wgpu_texture_view(),
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                    store: wgpu::StoreOp::Store,
+                    load: match options.color_load {
+                        wgpu::LoadOp::Clear(color) => wgpu::LoadOp::Clear(color.into()),
+                        wgpu::LoadOp::Load => wgpu::LoadOp::Load,
+                    },
+                    store: options.color_store,
                 },
             })],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                 view: self.depth_stencil_texture.wgpu_texture_view(),
                 depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
-                    store: wgpu::StoreOp::Store,
+                    load: options.depth_load,
+                    store: options.depth_store,
                 }),
                 stencil_ops: None,
             }),
@@ -251,9 +267,10 @@ impl SurfaceView {
     /// # Examples
     ///
     /// ```rust
+    /// # use tbn_engine::SurfaceView;
     /// fn f(surface_view: SurfaceView) {
-    /// let (color_texture, depth_stencil_texture) =
-    ///     surface_view.into_color_depth_stencil_textures();
+    ///     let (color_texture, depth_stencil_texture) =
+    ///         surface_view.into_color_depth_stencil_textures();
     /// }
     /// ```
     pub fn into_color_depth_stencil_textures(self) -> (TextureView2d, DepthStencilTextureView2d) {
@@ -261,6 +278,57 @@ impl SurfaceView {
     }
 }
 
+/// How a render pass treats the existing content of a surface.
+///
+/// Loading instead of clearing allows several scenes to be rendered onto one surface in sequence,
+/// e.g. a HUD on top of the world, with `RenderPassOptions::overlay`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderPassOptions {
+    pub color_load: wgpu::LoadOp<Rgba>,
+    pub color_store: wgpu::StoreOp,
+    pub depth_load: wgpu::LoadOp<f32>,
+    pub depth_store: wgpu::StoreOp,
+}
+
+impl Default for RenderPassOptions {
+    /// Clears color to black and depth to 1.0, and stores both.
+    fn default() -> Self {
+        Self::clear(Rgba::new(0.0, 0.0, 0.0, 1.0))
+    }
+}
+
+impl RenderPassOptions {
+    /// Clears color to `clear_color` and depth to 1.0, and stores both.
+    pub const fn clear(clear_color: Rgba) -> Self {
+        Self {
+            color_load: wgpu::LoadOp::Clear(clear_color),
+            color_store: wgpu::StoreOp::Store,
+            depth_load: wgpu::LoadOp::Clear(1.0),
+            depth_store: wgpu::StoreOp::Store,
+        }
+    }
+
+    /// Keeps both the existing color and depth, so that objects are depth-tested against what was
+    /// rendered before.
+    pub const fn load() -> Self {
+        Self {
+            color_load: wgpu::LoadOp::Load,
+            color_store: wgpu::StoreOp::Store,
+            depth_load: wgpu::LoadOp::Load,
+            depth_store: wgpu::StoreOp::Store,
+        }
+    }
+
+    /// Keeps the existing color but clears depth, so that objects are drawn on top of what was
+    /// rendered before.
+    pub const fn overlay() -> Self {
+        Self {
+            depth_load: wgpu::LoadOp::Clear(1.0),
+            ..Self::load()
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct RenderPass {
     wgpu_encoder: wgpu::CommandEncoder,
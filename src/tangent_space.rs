@@ -0,0 +1,133 @@
+use bevy_mikktspace::{Geometry, TangentSpace};
+use cgmath::*;
+
+use crate::Vertex3dNormalTangentUV;
+
+/// Sets the normal of every vertex to the average normal of the triangles that share it, weighted
+/// by the areas of the triangles.
+///
+/// Vertices that aren't part of any non-degenerate triangle get a zero normal.
+///
+/// # Panics
+///
+/// - if `indices.len()` is not a multiple of 3
+/// - if an index is out of range
+pub fn compute_smooth_normals(vertices: &mut [Vertex3dNormalTangentUV], indices: &[u32]) {
+    assert!(
+        indices.len().is_multiple_of(3),
+        "indices must form triangles"
+    );
+    let mut normals = vec![Vector3::zero(); vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        // The cross product's length is twice the area of the triangle.
+        let normal = triangle_cross(vertices, triangle);
+        for &index in triangle {
+            normals[index as usize] += normal;
+        }
+    }
+    for (vertex, normal) in vertices.iter_mut().zip(normals) {
+        vertex.normal = normalize_or_zero(normal).into();
+    }
+}
+
+/// Gives every triangle its own three vertices, with their normals set to the normal of the
+/// triangle. Returns the new vertices and indices.
+///
+/// # Panics
+///
+/// - if `indices.len()` is not a multiple of 3
+/// - if an index is out of range
+pub fn compute_flat_normals(
+    vertices: &[Vertex3dNormalTangentUV],
+    indices: &[u32],
+) -> (Vec<Vertex3dNormalTangentUV>, Vec<u32>) {
+    assert!(
+        indices.len().is_multiple_of(3),
+        "indices must form triangles"
+    );
+    let mut flat_vertices = Vec::with_capacity(indices.len());
+    for triangle in indices.chunks_exact(3) {
+        let normal = normalize_or_zero(triangle_cross(vertices, triangle));
+        flat_vertices.extend(triangle.iter().map(|&index| Vertex3dNormalTangentUV {
+            normal: normal.into(),
+            ..vertices[index as usize]
+        }));
+    }
+    let flat_indices = (0..flat_vertices.len() as u32).collect();
+    (flat_vertices, flat_indices)
+}
+
+/// Computes tangents from UVs and normals with the MikkTSpace algorithm, so that normal maps baked
+/// by other MikkTSpace tools are reproduced exactly.
+///
+/// Normals must already be set. Vertices that MikkTSpace splits (e.g. along mirrored UV seams)
+/// must already be separate vertices, otherwise the tangent of the last triangle wins.
+///
+/// # Panics
+///
+/// - if `indices.len()` is not a multiple of 3
+/// - if an index is out of range
+pub fn compute_tangents(vertices: &mut [Vertex3dNormalTangentUV], indices: &[u32]) {
+    assert!(
+        indices.len().is_multiple_of(3),
+        "indices must form triangles"
+    );
+    let mut geometry = MikkTSpaceGeometry { vertices, indices };
+    // The error type has no variants as of now.
+    bevy_mikktspace::generate_tangents(&mut geometry).unwrap();
+}
+
+fn triangle_cross(vertices: &[Vertex3dNormalTangentUV], triangle: &[u32]) -> Vector3<f32> {
+    let [a, b, c] = [0, 1, 2].map(|i| Vector3::from(vertices[triangle[i] as usize].position));
+    (b - a).cross(c - a)
+}
+
+fn normalize_or_zero(vector: Vector3<f32>) -> Vector3<f32> {
+    if vector.magnitude2() > 0.0 {
+        vector.normalize()
+    } else {
+        Vector3::zero()
+    }
+}
+
+struct MikkTSpaceGeometry<'a> {
+    vertices: &'a mut [Vertex3dNormalTangentUV],
+    indices: &'a [u32],
+}
+
+impl MikkTSpaceGeometry<'_> {
+    fn vertex(&self, face: usize, vert: usize) -> &Vertex3dNormalTangentUV {
+        &self.vertices[self.indices[face * 3 + vert] as usize]
+    }
+}
+
+impl Geometry for MikkTSpaceGeometry<'_> {
+    fn num_faces(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).position
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).normal
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.vertex(face, vert).uv
+    }
+
+    fn set_tangent(&mut self, tangent_space: Option<TangentSpace>, face: usize, vert: usize) {
+        let index = self.indices[face * 3 + vert] as usize;
+        // `None` for degenerate triangles without neighbours to take the tangent from.
+        self.vertices[index].tangent = tangent_space
+            .map_or([1.0, 0.0, 0.0, 1.0], |tangent_space| {
+                tangent_space.tangent_encoded()
+            });
+    }
+}
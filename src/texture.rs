@@ -1,7 +1,9 @@
+use std::ops::Range;
+
 use cgmath::*;
 use wgpu::{ShaderStages, TextureUsages, util::DeviceExt as _};
 
-use crate::{Bindable, Context};
+use crate::{Bindable, Context, GenerateMipmapsError, MipmapPass};
 
 /// A trait that `TextureFormat`, `DepthStencilTextureFormat`, and `wgpu::TextureFormat` all implements.
 /// This is for easier code reuse for texture and texture view structs.
@@ -289,6 +291,7 @@ pub struct Texture2d_<Format: TextureFormatTrait> {
     format: Format,
     size: Vector2<u32>,
     usage: wgpu::TextureUsages,
+    mip_level_count: u32,
 }
 
 pub type Texture2d = Texture2d_<TextureFormat>;
@@ -304,16 +307,34 @@ impl<Format: TextureFormatTrait> Texture2d_<Format> {
         }
     }
 
+    /// Number of mip levels in a full mip chain of a texture of this size, down to 1x1.
+    pub fn max_mip_level_count(size: Vector2<u32>) -> u32 {
+        Self::extent(size).max_mips(wgpu::TextureDimension::D2)
+    }
+
+    /// Creates a 2D texture with a single mip level.
     pub fn create(
         device: &wgpu::Device,
         size: Vector2<u32>,
         format: Format,
         usage: wgpu::TextureUsages,
+    ) -> Self {
+        Self::create_with_mip_levels(device, size, format, usage, 1)
+    }
+
+    /// Creates a 2D texture with `mip_level_count` mip levels, which must be between 1 and
+    /// `max_mip_level_count(size)`.
+    pub fn create_with_mip_levels(
+        device: &wgpu::Device,
+        size: Vector2<u32>,
+        format: Format,
+        usage: wgpu::TextureUsages,
+        mip_level_count: u32,
     ) -> Self {
         let wgpu_texture = device.create_texture(&wgpu::TextureDescriptor {
             label: None,
             size: Self::extent(size),
-            mip_level_count: 1,
+            mip_level_count,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: format.into(),
@@ -325,12 +346,24 @@ impl<Format: TextureFormatTrait> Texture2d_<Format> {
             format,
             size,
             usage,
+            mip_level_count,
         }
     }
 
     /// Creates a 2D texture of usage (COPY_DST | TEXTURE_BINDING) and then initialize it with data.
     pub fn create_init(context: &Context, size: Vector2<u32>, format: Format, data: &[u8]) -> Self {
         let usage = TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING;
+        Self::create_init_with_usage(context, size, format, usage, data)
+    }
+
+    /// Like `create_init`, with additional usages. `usage` must include COPY_DST.
+    pub fn create_init_with_usage(
+        context: &Context,
+        size: Vector2<u32>,
+        format: Format,
+        usage: wgpu::TextureUsages,
+        data: &[u8],
+    ) -> Self {
         let descriptor = wgpu::TextureDescriptor {
             label: None,
             size: Self::extent(size),
@@ -352,9 +385,52 @@ impl<Format: TextureFormatTrait> Texture2d_<Format> {
             format,
             size,
             usage,
+            mip_level_count: 1,
         }
     }
 
+    /// Creates a 2D texture with a full mip chain (see `max_mip_level_count`), initializes level 0
+    /// with data, and generates the other levels from it (see `generate_mipmaps`).
+    ///
+    /// The texture has usages (COPY_DST | TEXTURE_BINDING) in addition to `usage`, and also
+    /// RENDER_ATTACHMENT if its mip levels are generated in a render pass.
+    pub fn create_init_mipmapped(
+        context: &Context,
+        size: Vector2<u32>,
+        format: Format,
+        usage: wgpu::TextureUsages,
+        data: &[u8],
+    ) -> Result<Self, GenerateMipmapsError> {
+        let wgpu_format = format.into();
+        let Some(pass) = MipmapPass::for_format(context.wgpu_device(), wgpu_format) else {
+            return Err(GenerateMipmapsError::UnsupportedFormat(wgpu_format));
+        };
+        let usage = usage | TextureUsages::COPY_DST | pass.required_usage();
+        let texture = Self::create_with_mip_levels(
+            context.wgpu_device(),
+            size,
+            format,
+            usage,
+            Self::max_mip_level_count(size),
+        );
+        let bytes_per_texel = texture
+            .wgpu_format()
+            .block_copy_size(None)
+            .expect("uncompressed color formats have a block copy size");
+        context.wgpu_queue().write_texture(
+            texture.wgpu_texture().as_image_copy(),
+            data,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(size.x * bytes_per_texel),
+                rows_per_image: Some(size.y),
+            },
+            Self::extent(size),
+        );
+        texture.generate_mipmaps(context)?;
+        Ok(texture)
+    }
+
     pub fn wgpu_format(&self) -> wgpu::TextureFormat {
         self.format().into()
     }
@@ -363,7 +439,27 @@ impl<Format: TextureFormatTrait> Texture2d_<Format> {
         &self.wgpu_texture
     }
 
+    /// A view of all mip levels.
     pub fn view(&self, sample_type: wgpu::TextureSampleType) -> TextureView2d_<Format> {
+        self.view_mip_levels(sample_type, 0..self.mip_level_count)
+    }
+
+    /// A view of a range of mip levels, where level `mip_levels.start` of the texture becomes level
+    /// 0 of the view.
+    ///
+    /// # Panics
+    ///
+    /// - if the range is empty or extends past the mip levels of the texture
+    pub fn view_mip_levels(
+        &self,
+        sample_type: wgpu::TextureSampleType,
+        mip_levels: Range<u32>,
+    ) -> TextureView2d_<Format> {
+        assert!(
+            !mip_levels.is_empty() && mip_levels.end <= self.mip_level_count,
+            "mip levels {mip_levels:?} out of range for a texture with {} mip levels",
+            self.mip_level_count,
+        );
         let wgpu_texture_view = self
             .wgpu_texture()
             .create_view(&wgpu::TextureViewDescriptor {
@@ -372,15 +468,15 @@ impl<Format: TextureFormatTrait> Texture2d_<Format> {
                 dimension: Some(wgpu::TextureViewDimension::D2),
                 usage: Some(self.usage),
                 aspect: wgpu::TextureAspect::All,
-                base_mip_level: 0,
-                mip_level_count: None,
+                base_mip_level: mip_levels.start,
+                mip_level_count: Some(mip_levels.len() as u32),
                 base_array_layer: 0,
                 array_layer_count: None,
             });
         TextureView2d_ {
             wgpu_texture_view,
             format: self.format,
-            size: self.size,
+            size: self.mip_level_size(mip_levels.start),
             sample_type,
         }
     }
@@ -397,12 +493,22 @@ impl<Format: TextureFormatTrait> Texture2d_<Format> {
         self.usage
     }
 
+    pub fn mip_level_count(&self) -> u32 {
+        self.mip_level_count
+    }
+
+    /// Size of a mip level, halved (rounding down) for every level but at least 1x1.
+    pub fn mip_level_size(&self, mip_level: u32) -> Vector2<u32> {
+        self.size.map(|length| (length >> mip_level).max(1))
+    }
+
     pub fn into_generic_texture(self) -> GenericTexture2d {
         GenericTexture2d {
             wgpu_texture: self.wgpu_texture,
             format: self.format.into(),
             size: self.size,
             usage: self.usage,
+            mip_level_count: self.mip_level_count,
         }
     }
 }
@@ -474,7 +580,7 @@ impl<Format: TextureFormatTrait> Bindable for TextureView2d_<Format> {
         }
     }
 
-    fn bind_group_entry(&self, binding: u32) -> wgpu::BindGroupEntry {
+    fn bind_group_entry(&self, binding: u32) -> wgpu::BindGroupEntry<'_> {
         wgpu::BindGroupEntry {
             binding,
             resource: wgpu::BindingResource::TextureView(&self.wgpu_texture_view),
@@ -482,32 +588,924 @@ impl<Format: TextureFormatTrait> Bindable for TextureView2d_<Format> {
     }
 }
 
+/// A cube map of six square faces, in the order +X, -X, +Y, -Y, +Z, -Z. Sampling it with a
+/// direction reads from the face that the direction points at.
 #[derive(Debug, Clone)]
-pub struct Sampler {
-    wgpu_sampler: wgpu::Sampler,
+pub struct TextureCube {
+    wgpu_texture: wgpu::Texture,
+    format: TextureFormat,
+    size: u32,
+    usage: wgpu::TextureUsages,
+    mip_level_count: u32,
 }
 
-impl Sampler {
+impl TextureCube {
+    fn extent(size: u32) -> wgpu::Extent3d {
+        wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        }
+    }
+
+    /// Creates a cube map with faces of `size` by `size` texels and a single mip level.
     pub fn create(
-        context: &Context,
-        address_mode: wgpu::AddressMode,
-        mag_filter: wgpu::FilterMode,
-        min_filter: wgpu::FilterMode,
+        device: &wgpu::Device,
+        size: u32,
+        format: TextureFormat,
+        usage: wgpu::TextureUsages,
     ) -> Self {
-        let wgpu_sampler = context.wgpu_device().create_sampler(&wgpu::SamplerDescriptor {
+        Self::create_with_mip_levels(device, size, format, usage, 1)
+    }
+
+    /// Creates a cube map with `mip_level_count` mip levels, which must be between 1 and
+    /// `Texture2d::max_mip_level_count(vec2(size, size))`.
+    pub fn create_with_mip_levels(
+        device: &wgpu::Device,
+        size: u32,
+        format: TextureFormat,
+        usage: wgpu::TextureUsages,
+        mip_level_count: u32,
+    ) -> Self {
+        let wgpu_texture = device.create_texture(&wgpu::TextureDescriptor {
             label: None,
-            address_mode_u: address_mode,
-            address_mode_v: address_mode,
-            address_mode_w: address_mode,
-            mag_filter,
-            min_filter,
-            mipmap_filter: wgpu::FilterMode::Linear,
-            lod_min_clamp: 0.0,
-            lod_max_clamp: 0.0,
-            compare: None,
-            anisotropy_clamp: 1,
-            border_color: None,
+            size: Self::extent(size),
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: format.into(),
+            usage,
+            view_formats: &[],
         });
+        Self {
+            wgpu_texture,
+            format,
+            size,
+            usage,
+            mip_level_count,
+        }
+    }
+
+    /// Creates a cube map of usage (COPY_DST | TEXTURE_BINDING) and then initialize its faces with
+    /// data, in the order +X, -X, +Y, -Y, +Z, -Z.
+    pub fn create_init(
+        context: &Context,
+        size: u32,
+        format: TextureFormat,
+        faces: [&[u8]; 6],
+    ) -> Self {
+        let usage = TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING;
+        let descriptor = wgpu::TextureDescriptor {
+            label: None,
+            size: Self::extent(size),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: format.into(),
+            usage,
+            view_formats: &[],
+        };
+        let wgpu_texture = context.wgpu_device().create_texture_with_data(
+            context.wgpu_queue(),
+            &descriptor,
+            Default::default(),
+            &faces.concat(),
+        );
+        Self {
+            wgpu_texture,
+            format,
+            size,
+            usage,
+            mip_level_count: 1,
+        }
+    }
+
+    pub fn wgpu_texture(&self) -> &wgpu::Texture {
+        &self.wgpu_texture
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn wgpu_format(&self) -> wgpu::TextureFormat {
+        self.format().into()
+    }
+
+    /// Width and height of each face.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn usage(&self) -> wgpu::TextureUsages {
+        self.usage
+    }
+
+    pub fn mip_level_count(&self) -> u32 {
+        self.mip_level_count
+    }
+
+    /// Width and height of each face at a mip level, halved (rounding down) for every level but at
+    /// least 1.
+    pub fn mip_level_size(&self, mip_level: u32) -> u32 {
+        (self.size >> mip_level).max(1)
+    }
+
+    /// A cube view of all faces and mip levels.
+    pub fn view(&self, sample_type: wgpu::TextureSampleType) -> TextureViewCube {
+        self.view_mip_levels(sample_type, 0..self.mip_level_count)
+    }
+
+    /// A cube view of all faces and a range of mip levels, where level `mip_levels.start` of the
+    /// texture becomes level 0 of the view.
+    ///
+    /// # Panics
+    ///
+    /// - if the range is empty or extends past the mip levels of the texture
+    pub fn view_mip_levels(
+        &self,
+        sample_type: wgpu::TextureSampleType,
+        mip_levels: Range<u32>,
+    ) -> TextureViewCube {
+        assert!(
+            !mip_levels.is_empty() && mip_levels.end <= self.mip_level_count,
+            "mip levels {mip_levels:?} out of range for a texture with {} mip levels",
+            self.mip_level_count,
+        );
+        let wgpu_texture_view = self
+            .wgpu_texture()
+            .create_view(&wgpu::TextureViewDescriptor {
+                label: None,
+                format: Some(self.wgpu_format()),
+                dimension: Some(wgpu::TextureViewDimension::Cube),
+                usage: Some(self.usage),
+                aspect: wgpu::TextureAspect::All,
+                base_mip_level: mip_levels.start,
+                mip_level_count: Some(mip_levels.len() as u32),
+                base_array_layer: 0,
+                array_layer_count: Some(6),
+            });
+        TextureViewCube {
+            wgpu_texture_view,
+            format: self.format,
+            size: self.mip_level_size(mip_levels.start),
+            sample_type,
+        }
+    }
+
+    /// A 2D view of one face at one mip level, e.g. for rendering into it.
+    ///
+    /// # Panics
+    ///
+    /// - if `face` isn't below 6, or the texture doesn't have this mip level
+    pub fn face_view(
+        &self,
+        sample_type: wgpu::TextureSampleType,
+        face: u32,
+        mip_level: u32,
+    ) -> TextureView2d {
+        assert!(face < 6, "cube maps have 6 faces, not {}", face + 1);
+        assert!(
+            mip_level < self.mip_level_count,
+            "mip level {mip_level} out of range for a texture with {} mip levels",
+            self.mip_level_count,
+        );
+        let wgpu_texture_view = self
+            .wgpu_texture()
+            .create_view(&wgpu::TextureViewDescriptor {
+                label: None,
+                format: Some(self.wgpu_format()),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                usage: Some(self.usage),
+                aspect: wgpu::TextureAspect::All,
+                base_mip_level: mip_level,
+                mip_level_count: Some(1),
+                base_array_layer: face,
+                array_layer_count: Some(1),
+            });
+        let size = self.mip_level_size(mip_level);
+        TextureView2d::from_raw(
+            wgpu_texture_view,
+            self.format,
+            vec2(size, size),
+            sample_type,
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TextureViewCube {
+    wgpu_texture_view: wgpu::TextureView,
+    format: TextureFormat,
+    size: u32,
+    sample_type: wgpu::TextureSampleType,
+}
+
+impl TextureViewCube {
+    pub fn wgpu_texture_view(&self) -> &wgpu::TextureView {
+        &self.wgpu_texture_view
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn wgpu_format(&self) -> wgpu::TextureFormat {
+        self.format().into()
+    }
+
+    /// Width and height of each face.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+}
+
+impl Bindable for TextureViewCube {
+    fn bind_group_layout_entry(&self, binding: u32) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::all(),
+            ty: wgpu::BindingType::Texture {
+                sample_type: self.sample_type,
+                view_dimension: wgpu::TextureViewDimension::Cube,
+                multisampled: false,
+            },
+            count: None,
+        }
+    }
+
+    fn bind_group_entry(&self, binding: u32) -> wgpu::BindGroupEntry<'_> {
+        wgpu::BindGroupEntry {
+            binding,
+            resource: wgpu::BindingResource::TextureView(&self.wgpu_texture_view),
+        }
+    }
+}
+
+/// Size in bytes of an array layer or depth slice of `size` texels.
+fn layer_byte_count(format: wgpu::TextureFormat, size: Vector2<u32>) -> usize {
+    let bytes_per_texel = format
+        .block_copy_size(None)
+        .expect("uncompressed color formats have a block copy size");
+    (size.x * size.y * bytes_per_texel) as usize
+}
+
+#[track_caller]
+fn assert_layer_len(layer: u32, data: &[u8], layer_len: usize) {
+    assert!(
+        data.len() == layer_len,
+        "layer {layer} has {} bytes of data instead of {layer_len}",
+        data.len(),
+    );
+}
+
+/// Uploads `data` into one array layer or depth slice of the first mip level of a texture, whose
+/// layers are `size` texels large.
+#[track_caller]
+fn write_texture_layer(
+    queue: &wgpu::Queue,
+    wgpu_texture: &wgpu::Texture,
+    size: Vector2<u32>,
+    layer: u32,
+    data: &[u8],
+) {
+    assert_layer_len(layer, data, layer_byte_count(wgpu_texture.format(), size));
+    let bytes_per_texel = wgpu_texture
+        .format()
+        .block_copy_size(None)
+        .expect("uncompressed color formats have a block copy size");
+    queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture: wgpu_texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d {
+                x: 0,
+                y: 0,
+                z: layer,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        data,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(size.x * bytes_per_texel),
+            rows_per_image: Some(size.y),
+        },
+        wgpu::Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+/// An array of 2D textures of the same size and format, e.g. the tiles of a tile map. Shaders
+/// select a layer by its index, and filtering never blends between layers.
+///
+/// # GL
+///
+/// The GL backend picks the kind of a texture from its size alone: a single layer becomes a plain
+/// 2D texture and square layers in a multiple of 6 become a cube map (array), neither of which can
+/// be sampled as a 2D array. On GL, the wgpu texture of such arrays has an extra layer past
+/// `layer_count` to avoid both, which views leave out.
+#[derive(Debug, Clone)]
+pub struct Texture2dArray {
+    wgpu_texture: wgpu::Texture,
+    format: TextureFormat,
+    size: Vector2<u32>,
+    layer_count: u32,
+    usage: wgpu::TextureUsages,
+    mip_level_count: u32,
+}
+
+impl Texture2dArray {
+    fn extent(context: &Context, size: Vector2<u32>, layer_count: u32) -> wgpu::Extent3d {
+        wgpu::Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: Self::padded_layer_count(context, size, layer_count),
+        }
+    }
+
+    /// Number of layers of the wgpu texture, with a padding layer if GL would otherwise not make
+    /// it a 2D array, see the GL section of `Texture2dArray`.
+    fn padded_layer_count(context: &Context, size: Vector2<u32>, layer_count: u32) -> u32 {
+        let is_gl = context.adapter_info().backend == wgpu::Backend::Gl;
+        if is_gl && (layer_count == 1 || (size.x == size.y && layer_count.is_multiple_of(6))) {
+            layer_count + 1
+        } else {
+            layer_count
+        }
+    }
+
+    /// Creates an array of `layer_count` layers of `size` texels, with a single mip level.
+    pub fn create(
+        context: &Context,
+        size: Vector2<u32>,
+        layer_count: u32,
+        format: TextureFormat,
+        usage: wgpu::TextureUsages,
+    ) -> Self {
+        Self::create_with_mip_levels(context, size, layer_count, format, usage, 1)
+    }
+
+    /// Creates an array with `mip_level_count` mip levels, which must be between 1 and
+    /// `Texture2d::max_mip_level_count(size)`.
+    pub fn create_with_mip_levels(
+        context: &Context,
+        size: Vector2<u32>,
+        layer_count: u32,
+        format: TextureFormat,
+        usage: wgpu::TextureUsages,
+        mip_level_count: u32,
+    ) -> Self {
+        let wgpu_texture = context
+            .wgpu_device()
+            .create_texture(&wgpu::TextureDescriptor {
+                label: None,
+                size: Self::extent(context, size, layer_count),
+                mip_level_count,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: format.into(),
+                usage,
+                view_formats: &[],
+            });
+        Self {
+            wgpu_texture,
+            format,
+            size,
+            layer_count,
+            usage,
+            mip_level_count,
+        }
+    }
+
+    /// Creates an array of usage (COPY_DST | TEXTURE_BINDING) with a layer for each element of
+    /// `layers`, and then initialize the layers with their data.
+    ///
+    /// # Panics
+    ///
+    /// - if `layers` is empty
+    /// - if the data of a layer isn't exactly `size.x * size.y` texels of `format`
+    pub fn create_init(
+        context: &Context,
+        size: Vector2<u32>,
+        format: TextureFormat,
+        layers: &[&[u8]],
+    ) -> Self {
+        assert!(!layers.is_empty(), "texture arrays need at least one layer");
+        let layer_len = layer_byte_count(format.into(), size);
+        for (layer, data) in layers.iter().enumerate() {
+            assert_layer_len(layer as u32, data, layer_len);
+        }
+        let usage = TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING;
+        let layer_count = layers.len() as u32;
+        let padding_layer_count =
+            Self::padded_layer_count(context, size, layer_count) - layer_count;
+        let mut data = layers.concat();
+        data.resize(data.len() + padding_layer_count as usize * layer_len, 0);
+        let descriptor = wgpu::TextureDescriptor {
+            label: None,
+            size: Self::extent(context, size, layer_count),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: format.into(),
+            usage,
+            view_formats: &[],
+        };
+        let wgpu_texture = context.wgpu_device().create_texture_with_data(
+            context.wgpu_queue(),
+            &descriptor,
+            Default::default(),
+            &data,
+        );
+        Self {
+            wgpu_texture,
+            format,
+            size,
+            layer_count,
+            usage,
+            mip_level_count: 1,
+        }
+    }
+
+    /// Replaces the first mip level of one layer with data. The array must have the COPY_DST
+    /// usage.
+    ///
+    /// # Panics
+    ///
+    /// - if `layer` isn't below `layer_count`
+    /// - if `data` isn't exactly `size.x * size.y` texels of the format of the array
+    pub fn write_layer(&self, queue: &wgpu::Queue, layer: u32, data: &[u8]) {
+        assert!(
+            layer < self.layer_count,
+            "layer {layer} out of range for a texture array with {} layers",
+            self.layer_count,
+        );
+        write_texture_layer(queue, &self.wgpu_texture, self.size, layer, data);
+    }
+
+    /// The underlying texture, which on GL may have a padding layer past `layer_count`, see the GL
+    /// section of `Texture2dArray`.
+    pub fn wgpu_texture(&self) -> &wgpu::Texture {
+        &self.wgpu_texture
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn wgpu_format(&self) -> wgpu::TextureFormat {
+        self.format().into()
+    }
+
+    /// Width and height of each layer.
+    pub fn size(&self) -> Vector2<u32> {
+        self.size
+    }
+
+    pub fn layer_count(&self) -> u32 {
+        self.layer_count
+    }
+
+    pub fn usage(&self) -> wgpu::TextureUsages {
+        self.usage
+    }
+
+    pub fn mip_level_count(&self) -> u32 {
+        self.mip_level_count
+    }
+
+    /// Width and height of each layer at a mip level, halved (rounding down) for every level but
+    /// at least 1.
+    pub fn mip_level_size(&self, mip_level: u32) -> Vector2<u32> {
+        self.size.map(|length| (length >> mip_level).max(1))
+    }
+
+    /// An array view of all layers and mip levels.
+    pub fn view(&self, sample_type: wgpu::TextureSampleType) -> TextureView2dArray {
+        let wgpu_texture_view = self
+            .wgpu_texture()
+            .create_view(&wgpu::TextureViewDescriptor {
+                label: None,
+                format: Some(self.wgpu_format()),
+                dimension: Some(wgpu::TextureViewDimension::D2Array),
+                usage: Some(self.usage),
+                aspect: wgpu::TextureAspect::All,
+                base_mip_level: 0,
+                mip_level_count: None,
+                base_array_layer: 0,
+                array_layer_count: Some(self.layer_count),
+            });
+        TextureView2dArray {
+            wgpu_texture_view,
+            format: self.format,
+            size: self.size,
+            layer_count: self.layer_count,
+            sample_type,
+        }
+    }
+
+    /// A 2D view of all mip levels of one layer, e.g. for rendering into it. The GL backend can
+    /// only sample the array through `view`, as it has no views of a single layer.
+    ///
+    /// # Panics
+    ///
+    /// - if `layer` isn't below `layer_count`
+    pub fn layer_view(&self, sample_type: wgpu::TextureSampleType, layer: u32) -> TextureView2d {
+        assert!(
+            layer < self.layer_count,
+            "layer {layer} out of range for a texture array with {} layers",
+            self.layer_count,
+        );
+        let wgpu_texture_view = self
+            .wgpu_texture()
+            .create_view(&wgpu::TextureViewDescriptor {
+                label: None,
+                format: Some(self.wgpu_format()),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                usage: Some(self.usage),
+                aspect: wgpu::TextureAspect::All,
+                base_mip_level: 0,
+                mip_level_count: None,
+                base_array_layer: layer,
+                array_layer_count: Some(1),
+            });
+        TextureView2d::from_raw(wgpu_texture_view, self.format, self.size, sample_type)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TextureView2dArray {
+    wgpu_texture_view: wgpu::TextureView,
+    format: TextureFormat,
+    size: Vector2<u32>,
+    layer_count: u32,
+    sample_type: wgpu::TextureSampleType,
+}
+
+impl TextureView2dArray {
+    pub fn wgpu_texture_view(&self) -> &wgpu::TextureView {
+        &self.wgpu_texture_view
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn wgpu_format(&self) -> wgpu::TextureFormat {
+        self.format().into()
+    }
+
+    /// Width and height of each layer.
+    pub fn size(&self) -> Vector2<u32> {
+        self.size
+    }
+
+    pub fn layer_count(&self) -> u32 {
+        self.layer_count
+    }
+}
+
+impl Bindable for TextureView2dArray {
+    fn bind_group_layout_entry(&self, binding: u32) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::all(),
+            ty: wgpu::BindingType::Texture {
+                sample_type: self.sample_type,
+                view_dimension: wgpu::TextureViewDimension::D2Array,
+                multisampled: false,
+            },
+            count: None,
+        }
+    }
+
+    fn bind_group_entry(&self, binding: u32) -> wgpu::BindGroupEntry<'_> {
+        wgpu::BindGroupEntry {
+            binding,
+            resource: wgpu::BindingResource::TextureView(&self.wgpu_texture_view),
+        }
+    }
+}
+
+/// A volume of texels, e.g. a density field. Unlike `Texture2dArray`, filtering blends between
+/// neighbouring depth slices.
+#[derive(Debug, Clone)]
+pub struct Texture3d {
+    wgpu_texture: wgpu::Texture,
+    format: TextureFormat,
+    size: Vector3<u32>,
+    usage: wgpu::TextureUsages,
+    mip_level_count: u32,
+}
+
+impl Texture3d {
+    fn extent(size: Vector3<u32>) -> wgpu::Extent3d {
+        wgpu::Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: size.z,
+        }
+    }
+
+    /// Number of mip levels in a full mip chain of a texture of this size, down to 1x1x1.
+    pub fn max_mip_level_count(size: Vector3<u32>) -> u32 {
+        Self::extent(size).max_mips(wgpu::TextureDimension::D3)
+    }
+
+    /// Creates a 3D texture with a single mip level.
+    pub fn create(
+        device: &wgpu::Device,
+        size: Vector3<u32>,
+        format: TextureFormat,
+        usage: wgpu::TextureUsages,
+    ) -> Self {
+        Self::create_with_mip_levels(device, size, format, usage, 1)
+    }
+
+    /// Creates a 3D texture with `mip_level_count` mip levels, which must be between 1 and
+    /// `max_mip_level_count(size)`.
+    pub fn create_with_mip_levels(
+        device: &wgpu::Device,
+        size: Vector3<u32>,
+        format: TextureFormat,
+        usage: wgpu::TextureUsages,
+        mip_level_count: u32,
+    ) -> Self {
+        let wgpu_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: Self::extent(size),
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: format.into(),
+            usage,
+            view_formats: &[],
+        });
+        Self {
+            wgpu_texture,
+            format,
+            size,
+            usage,
+            mip_level_count,
+        }
+    }
+
+    /// Creates a 3D texture of usage (COPY_DST | TEXTURE_BINDING) and then initialize it with
+    /// data, one depth slice after another.
+    ///
+    /// # Panics
+    ///
+    /// - if `data` isn't exactly `size.x * size.y * size.z` texels of `format`
+    pub fn create_init(
+        context: &Context,
+        size: Vector3<u32>,
+        format: TextureFormat,
+        data: &[u8],
+    ) -> Self {
+        let len = layer_byte_count(format.into(), size.truncate()) * size.z as usize;
+        assert!(
+            data.len() == len,
+            "3D texture has {} bytes of data instead of {len}",
+            data.len(),
+        );
+        let usage = TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING;
+        let descriptor = wgpu::TextureDescriptor {
+            label: None,
+            size: Self::extent(size),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: format.into(),
+            usage,
+            view_formats: &[],
+        };
+        let wgpu_texture = context.wgpu_device().create_texture_with_data(
+            context.wgpu_queue(),
+            &descriptor,
+            Default::default(),
+            data,
+        );
+        Self {
+            wgpu_texture,
+            format,
+            size,
+            usage,
+            mip_level_count: 1,
+        }
+    }
+
+    /// Replaces one depth slice of the first mip level with data. The texture must have the
+    /// COPY_DST usage.
+    ///
+    /// # Panics
+    ///
+    /// - if `slice` isn't below the depth of the texture
+    /// - if `data` isn't exactly `size.x * size.y` texels of the format of the texture
+    pub fn write_slice(&self, queue: &wgpu::Queue, slice: u32, data: &[u8]) {
+        assert!(
+            slice < self.size.z,
+            "slice {slice} out of range for a 3D texture with a depth of {}",
+            self.size.z,
+        );
+        write_texture_layer(queue, &self.wgpu_texture, self.size.truncate(), slice, data);
+    }
+
+    pub fn wgpu_texture(&self) -> &wgpu::Texture {
+        &self.wgpu_texture
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn wgpu_format(&self) -> wgpu::TextureFormat {
+        self.format().into()
+    }
+
+    /// Width, height and depth.
+    pub fn size(&self) -> Vector3<u32> {
+        self.size
+    }
+
+    pub fn usage(&self) -> wgpu::TextureUsages {
+        self.usage
+    }
+
+    pub fn mip_level_count(&self) -> u32 {
+        self.mip_level_count
+    }
+
+    /// Size at a mip level, halved (rounding down) in every dimension for every level but at
+    /// least 1.
+    pub fn mip_level_size(&self, mip_level: u32) -> Vector3<u32> {
+        self.size.map(|length| (length >> mip_level).max(1))
+    }
+
+    /// A 3D view of all mip levels.
+    pub fn view(&self, sample_type: wgpu::TextureSampleType) -> TextureView3d {
+        let wgpu_texture_view = self
+            .wgpu_texture()
+            .create_view(&wgpu::TextureViewDescriptor {
+                label: None,
+                format: Some(self.wgpu_format()),
+                dimension: Some(wgpu::TextureViewDimension::D3),
+                usage: Some(self.usage),
+                aspect: wgpu::TextureAspect::All,
+                base_mip_level: 0,
+                mip_level_count: None,
+                base_array_layer: 0,
+                array_layer_count: None,
+            });
+        TextureView3d {
+            wgpu_texture_view,
+            format: self.format,
+            size: self.size,
+            sample_type,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TextureView3d {
+    wgpu_texture_view: wgpu::TextureView,
+    format: TextureFormat,
+    size: Vector3<u32>,
+    sample_type: wgpu::TextureSampleType,
+}
+
+impl TextureView3d {
+    pub fn wgpu_texture_view(&self) -> &wgpu::TextureView {
+        &self.wgpu_texture_view
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn wgpu_format(&self) -> wgpu::TextureFormat {
+        self.format().into()
+    }
+
+    /// Width, height and depth.
+    pub fn size(&self) -> Vector3<u32> {
+        self.size
+    }
+}
+
+impl Bindable for TextureView3d {
+    fn bind_group_layout_entry(&self, binding: u32) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::all(),
+            ty: wgpu::BindingType::Texture {
+                sample_type: self.sample_type,
+                view_dimension: wgpu::TextureViewDimension::D3,
+                multisampled: false,
+            },
+            count: None,
+        }
+    }
+
+    fn bind_group_entry(&self, binding: u32) -> wgpu::BindGroupEntry<'_> {
+        wgpu::BindGroupEntry {
+            binding,
+            resource: wgpu::BindingResource::TextureView(&self.wgpu_texture_view),
+        }
+    }
+}
+
+/// Options for `Sampler::create_with_options`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerOptions {
+    /// How to address texture coordinates outside of [0, 1] horizontally.
+    pub address_mode_u: wgpu::AddressMode,
+    /// How to address texture coordinates outside of [0, 1] vertically.
+    pub address_mode_v: wgpu::AddressMode,
+    /// How to address texture coordinates outside of [0, 1] in depth, for 3D textures.
+    pub address_mode_w: wgpu::AddressMode,
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+    /// How to blend between mip levels.
+    pub mipmap_filter: wgpu::FilterMode,
+    /// The lowest mip level sampled from. Fractional values clamp between levels.
+    pub lod_min_clamp: f32,
+    /// The highest mip level sampled from. 0 to only ever sample the first mip level.
+    pub lod_max_clamp: f32,
+    /// The maximum ratio of anisotropic filtering, from 1 (off) to 16. Values above 1 require the
+    /// mag, min and mipmap filters to all be `Linear`.
+    pub anisotropy_clamp: u16,
+}
+
+impl Default for SamplerOptions {
+    /// Repeats, filters linearly, samples all mip levels, and doesn't filter anisotropically.
+    fn default() -> Self {
+        Self {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 32.0,
+            anisotropy_clamp: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    wgpu_sampler: wgpu::Sampler,
+}
+
+impl Sampler {
+    /// Creates a sampler that blends linearly between all mip levels, without anisotropic
+    /// filtering. See `create_with_options`.
+    pub fn create(
+        context: &Context,
+        address_mode: wgpu::AddressMode,
+        mag_filter: wgpu::FilterMode,
+        min_filter: wgpu::FilterMode,
+    ) -> Self {
+        Self::create_with_options(
+            context,
+            &SamplerOptions {
+                address_mode_u: address_mode,
+                address_mode_v: address_mode,
+                address_mode_w: address_mode,
+                mag_filter,
+                min_filter,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// # Panics
+    ///
+    /// - if `options.anisotropy_clamp` is above 1 while any of the filters is `Nearest`
+    pub fn create_with_options(context: &Context, options: &SamplerOptions) -> Self {
+        let wgpu_sampler = context.wgpu_device().create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: options.address_mode_u,
+            address_mode_v: options.address_mode_v,
+            address_mode_w: options.address_mode_w,
+            mag_filter: options.mag_filter,
+            min_filter: options.min_filter,
+            mipmap_filter: options.mipmap_filter,
+            lod_min_clamp: options.lod_min_clamp,
+            lod_max_clamp: options.lod_max_clamp,
+            compare: None,
+            anisotropy_clamp: options.anisotropy_clamp,
+            border_color: None,
+        });
+        Self { wgpu_sampler }
+    }
+
+    pub(crate) fn from_raw(wgpu_sampler: wgpu::Sampler) -> Self {
         Self { wgpu_sampler }
     }
 
@@ -526,7 +1524,7 @@ impl Bindable for Sampler {
         }
     }
 
-    fn bind_group_entry(&self, binding: u32) -> wgpu::BindGroupEntry {
+    fn bind_group_entry(&self, binding: u32) -> wgpu::BindGroupEntry<'_> {
         wgpu::BindGroupEntry {
             binding,
             resource: wgpu::BindingResource::Sampler(self.wgpu_sampler()),
@@ -579,7 +1577,7 @@ impl Bindable for ComparingSampler {
         }
     }
 
-    fn bind_group_entry(&self, binding: u32) -> wgpu::BindGroupEntry {
+    fn bind_group_entry(&self, binding: u32) -> wgpu::BindGroupEntry<'_> {
         wgpu::BindGroupEntry {
             binding,
             resource: wgpu::BindingResource::Sampler(self.wgpu_sampler()),
@@ -0,0 +1,64 @@
+use cgmath::*;
+
+/// A translation, rotation and scale, applied in the order of scale, rotation, then translation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalTransform {
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Default for LocalTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl LocalTransform {
+    pub const IDENTITY: Self = Self {
+        translation: Vector3::new(0.0, 0.0, 0.0),
+        rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+        scale: Vector3::new(1.0, 1.0, 1.0),
+    };
+
+    pub fn new(translation: Vector3<f32>, rotation: Quaternion<f32>, scale: Vector3<f32>) -> Self {
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn from_translation(translation: Vector3<f32>) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn from_rotation(rotation: Quaternion<f32>) -> Self {
+        Self {
+            rotation,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn from_scale(scale: f32) -> Self {
+        Self {
+            scale: vec3(scale, scale, scale),
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+}
+
+impl From<LocalTransform> for Matrix4<f32> {
+    fn from(value: LocalTransform) -> Self {
+        value.matrix()
+    }
+}
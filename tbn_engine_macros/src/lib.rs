@@ -0,0 +1,176 @@
+//! Derive macros of `tbn_engine`. Use them through their re-exports in `tbn_engine`.
+
+use std::collections::HashSet;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Fields, Ident, LitInt, Member, Token,
+    parse::{Parse, ParseStream},
+    parse_macro_input,
+};
+
+/// Derives `AsBindGroup` for a struct whose fields implement `Bindable`.
+///
+/// Every field that should be bound takes a `#[binding(N)]` attribute, where `N` is its binding
+/// index. The shader stages that can see the binding may follow the index, e.g.
+/// `#[binding(0, vertex, fragment)]`; without them, the binding is visible to all stages. Fields
+/// without the attribute are ignored.
+///
+/// ```ignore
+/// #[derive(AsBindGroup)]
+/// struct Textured {
+///     #[binding(0, fragment)]
+///     texture_view: TextureView2d,
+///     #[binding(1, fragment)]
+///     sampler: Sampler,
+/// }
+/// ```
+#[proc_macro_derive(AsBindGroup, attributes(binding))]
+pub fn derive_as_bind_group(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    as_bind_group(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Arguments of a `#[binding(...)]` attribute.
+struct BindingArgs {
+    index: LitInt,
+    stages: Vec<Ident>,
+}
+
+impl Parse for BindingArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let index = input.parse()?;
+        let mut stages = Vec::new();
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let stage: Ident = input.parse()?;
+            if !matches!(
+                stage.to_string().as_str(),
+                "vertex" | "fragment" | "compute"
+            ) {
+                return Err(syn::Error::new(
+                    stage.span(),
+                    "expected `vertex`, `fragment` or `compute`",
+                ));
+            }
+            stages.push(stage);
+        }
+        Ok(Self { index, stages })
+    }
+}
+
+struct Binding {
+    member: Member,
+    index: u32,
+    visibility: TokenStream2,
+}
+
+fn as_bind_group(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`AsBindGroup` can only be derived for structs",
+        ));
+    };
+
+    let mut bindings = Vec::new();
+    let mut bound_types = Vec::new();
+    let mut used_indices = HashSet::new();
+    let fields = match &data.fields {
+        Fields::Named(fields) => fields.named.iter().collect(),
+        Fields::Unnamed(fields) => fields.unnamed.iter().collect(),
+        Fields::Unit => Vec::new(),
+    };
+    for (i, field) in fields.into_iter().enumerate() {
+        let mut attributes = field
+            .attrs
+            .iter()
+            .filter(|attribute| attribute.path().is_ident("binding"));
+        let Some(attribute) = attributes.next() else {
+            continue;
+        };
+        if let Some(extra_attribute) = attributes.next() {
+            return Err(syn::Error::new_spanned(
+                extra_attribute,
+                "a field can only have one `#[binding(...)]` attribute",
+            ));
+        }
+        let args: BindingArgs = attribute.parse_args()?;
+        let index: u32 = args.index.base10_parse()?;
+        if !used_indices.insert(index) {
+            return Err(syn::Error::new(
+                args.index.span(),
+                format!("binding index {index} is used by more than one field"),
+            ));
+        }
+        let visibility = if args.stages.is_empty() {
+            quote! { ::tbn_engine::wgpu::ShaderStages::all() }
+        } else {
+            let stages = args.stages.iter().map(|stage| {
+                let stage = Ident::new(&stage.to_string().to_uppercase(), stage.span());
+                quote! { ::tbn_engine::wgpu::ShaderStages::#stage }
+            });
+            quote! { #(#stages)|* }
+        };
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(i.into()),
+        };
+        bindings.push(Binding {
+            member,
+            index,
+            visibility,
+        });
+        bound_types.push(&field.ty);
+    }
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let mut where_clause = where_clause
+        .cloned()
+        .unwrap_or_else(|| syn::parse_quote!(where));
+    for ty in bound_types {
+        where_clause
+            .predicates
+            .push(syn::parse_quote!(#ty: ::tbn_engine::Bindable));
+    }
+    let layout_entries = bindings.iter().map(
+        |Binding {
+             member,
+             index,
+             visibility,
+         }| {
+            quote! {
+                ::tbn_engine::wgpu::BindGroupLayoutEntry {
+                    visibility: #visibility,
+                    ..::tbn_engine::Bindable::bind_group_layout_entry(&self.#member, #index)
+                }
+            }
+        },
+    );
+    let entries = bindings.iter().map(|Binding { member, index, .. }| {
+        quote! { ::tbn_engine::Bindable::bind_group_entry(&self.#member, #index) }
+    });
+    Ok(quote! {
+        impl #impl_generics ::tbn_engine::AsBindGroup for #name #type_generics #where_clause {
+            fn bind_group_layout_entries(
+                &self,
+            ) -> ::std::vec::Vec<::tbn_engine::wgpu::BindGroupLayoutEntry> {
+                ::std::vec![#(#layout_entries),*]
+            }
+
+            fn bind_group_entries(
+                &self,
+            ) -> ::std::vec::Vec<::tbn_engine::wgpu::BindGroupEntry<'_>> {
+                ::std::vec![#(#entries),*]
+            }
+        }
+    })
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use cgmath::*;
+use tbn_engine::{materials::UniformFill, *};
+
+use common::*;
+
+struct Setup {
+    surface: Surface,
+    scene: Scene,
+    camera: CameraRef,
+}
+
+fn setup() -> Setup {
+    let surface = create_surface();
+    let scene = create_scene(&surface);
+    Setup {
+        surface,
+        scene,
+        camera: create_camera(),
+    }
+}
+
+impl Setup {
+    /// Adds a quad covering the center of the surface at depth `z`.
+    fn add_quad(&mut self, material: UniformFill, z: f32) {
+        add_object(
+            &mut self.scene,
+            &self.camera,
+            quad_mesh(),
+            context().create_material(&material),
+            Matrix4::from_translation(vec3(-0.5, -0.5, z)),
+        );
+    }
+
+    /// Renders the scene and returns the color of the pixel in the center.
+    fn render(&mut self) -> [u8; 4] {
+        let options = RenderPassOptions::default();
+        self.scene.render(context(), &self.surface.view(), &options);
+        let pixels = self.surface.read_pixels(context()).unwrap();
+        let index = ((SIZE.y / 2 * SIZE.x + SIZE.x / 2) * 4) as usize;
+        pixels.bytes()[index..index + 4].try_into().unwrap()
+    }
+}
+
+fn assert_rgb_near(actual: [u8; 4], expected: [u8; 3]) {
+    assert!(
+        actual
+            .iter()
+            .zip(expected)
+            .all(|(&actual, expected)| actual.abs_diff(expected) <= 2),
+        "expected {expected:?}, got {actual:?}",
+    );
+}
+
+#[test]
+fn blended_objects_are_drawn_back_to_front() {
+    let mut setup = setup();
+    let red = UniformFill::create(context(), Rgba::new(1.0, 0.0, 0.0, 0.5));
+    let blue = UniformFill::create(context(), Rgba::new(0.0, 0.0, 1.0, 0.5));
+    assert_eq!(red.alpha_mode(), AlphaMode::Blend);
+    // The front one is added first.
+    setup.add_quad(red, 0.0);
+    setup.add_quad(blue, -1.0);
+    assert_rgb_near(setup.render(), [128, 0, 64]);
+}
+
+#[test]
+fn opaque_objects_hide_blended_objects_behind_them() {
+    let mut setup = setup();
+    let red = UniformFill::create(context(), Rgba::new(1.0, 0.0, 0.0, 0.5));
+    let green = UniformFill::create(context(), Rgba::new(0.0, 1.0, 0.0, 1.0));
+    assert_eq!(green.alpha_mode(), AlphaMode::Opaque);
+    setup.add_quad(red, -1.0);
+    setup.add_quad(green, 0.0);
+    assert_rgb_near(setup.render(), [0, 255, 0]);
+}
+
+#[test]
+fn masked_objects_discard_fragments_below_cutoff() {
+    let mut setup = setup();
+    let color = Rgba::new(1.0, 0.0, 0.0, 0.3);
+    let discarded =
+        UniformFill::create(context(), color).with_alpha_mode(AlphaMode::Mask { cutoff: 0.5 });
+    setup.add_quad(discarded, 0.0);
+    assert_rgb_near(setup.render(), [0, 0, 0]);
+
+    // Fragments that pass are opaque.
+    let kept =
+        UniformFill::create(context(), color).with_alpha_mode(AlphaMode::Mask { cutoff: 0.2 });
+    setup.add_quad(kept, -1.0);
+    assert_rgb_near(setup.render(), [255, 0, 0]);
+}
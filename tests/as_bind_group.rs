@@ -0,0 +1,55 @@
+use std::marker::PhantomData;
+
+use bytemuck::Pod;
+use tbn_engine::*;
+
+#[derive(AsBindGroup)]
+struct Generic<T: Pod> {
+    #[binding(0, vertex)]
+    value: UniformBuffer<T>,
+    #[binding(2)]
+    scale: UniformBuffer<f32>,
+    #[binding(1, vertex, fragment)]
+    offset: UniformBuffer<[f32; 2]>,
+    _marker: PhantomData<T>,
+}
+
+#[derive(AsBindGroup)]
+struct Tuple(#[binding(3, compute)] UniformBuffer<u32>);
+
+#[test]
+fn derived_entries() {
+    let context = Context::builder().build().unwrap();
+    let device = context.wgpu_device();
+    let generic = Generic {
+        value: UniformBuffer::create_init(device, [1.0f32; 4]),
+        scale: UniformBuffer::create_init(device, 2.0),
+        offset: UniformBuffer::create_init(device, [0.0; 2]),
+        _marker: PhantomData::<[f32; 4]>,
+    };
+    let layout_entries = generic.bind_group_layout_entries();
+    let bindings: Vec<_> = layout_entries
+        .iter()
+        .map(|entry| (entry.binding, entry.visibility))
+        .collect();
+    assert_eq!(
+        bindings,
+        [
+            (0, wgpu::ShaderStages::VERTEX),
+            (2, wgpu::ShaderStages::all()),
+            (1, wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT),
+        ],
+    );
+    let entries: Vec<_> = generic
+        .bind_group_entries()
+        .iter()
+        .map(|entry| entry.binding)
+        .collect();
+    assert_eq!(entries, [0, 2, 1]);
+
+    let tuple = Tuple(UniformBuffer::create_init(device, 0));
+    let layout_entries = tuple.bind_group_layout_entries();
+    assert_eq!(layout_entries.len(), 1);
+    assert_eq!(layout_entries[0].binding, 3);
+    assert_eq!(layout_entries[0].visibility, wgpu::ShaderStages::COMPUTE);
+}
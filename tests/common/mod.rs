@@ -0,0 +1,302 @@
+//! Golden-image test harness.
+//!
+//! Scenes are rendered headlessly into an offscreen `Surface`, read back, and compared against the
+//! PNGs in `tests/golden/`. On mismatch, the rendered image and a diff image are written to
+//! `target/golden-diff/`.
+//!
+//! Run with `TBN_UPDATE_GOLDEN=1` to (re)generate the reference images.
+//!
+//! The fixtures are also shared by the other test binaries, each of which uses only some of them.
+
+#![allow(dead_code)]
+
+use std::{
+    env,
+    fs::{self, File},
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+};
+
+use cgmath::*;
+use tbn_engine::*;
+
+pub const SIZE: Vector2<u32> = Vector2::new(64, 64);
+pub const FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+/// A `Context` shared by all tests in the binary.
+pub fn context() -> &'static Context {
+    static CONTEXT: OnceLock<Context> = OnceLock::new();
+    CONTEXT.get_or_init(|| {
+        Context::builder()
+            .build()
+            .unwrap_or_else(|error| panic!("golden tests need an adapter: {error}"))
+    })
+}
+
+pub fn create_surface() -> Surface {
+    Surface::create(context().wgpu_device(), SIZE, FORMAT)
+}
+
+pub fn create_scene(surface: &Surface) -> Scene {
+    Scene::new(
+        context().wgpu_device(),
+        surface.format(),
+        surface.depth_stencil_texture().format(),
+    )
+}
+
+/// A perspective camera at (0, 0, 4) looking at the origin.
+pub fn camera() -> Camera {
+    Camera::new(
+        point3(0.0, 0.0, 4.0),
+        vec3(0.0, 1.0, 0.0),
+        CameraDirection::LookAt(point3(0.0, 0.0, 0.0)),
+        Deg(60.0),
+        0.1,
+        100.0,
+    )
+}
+
+/// `camera`, created in the shared context.
+pub fn create_camera() -> CameraRef {
+    context().create_camera(camera())
+}
+
+pub fn add_object(
+    scene: &mut Scene,
+    camera: &CameraRef,
+    mesh: MeshRef,
+    material: MaterialRef,
+    model: Matrix4<f32>,
+) -> ObjectRef {
+    let object = context().create_object(scene, camera.clone(), mesh, material);
+    scene.set_object_model(&object, model);
+    scene.add_object(object.clone());
+    object
+}
+
+pub fn quad_mesh() -> MeshRef {
+    context().create_mesh(Arc::new(meshes::Quad::create(context())))
+}
+
+/// Vertices and indices of a unit cube spanning from (0, 0, 0) to (1, 1, 1).
+pub fn cube_geometry() -> ([Vertex3dUV; 24], [u32; 36]) {
+    #[rustfmt::skip]
+    let vertices = [
+        // South (+Z)
+        Vertex3dUV::new([0., 0., 1.], [0.0, 1.0]),
+        Vertex3dUV::new([1., 0., 1.], [1.0, 1.0]),
+        Vertex3dUV::new([1., 1., 1.], [1.0, 0.0]),
+        Vertex3dUV::new([0., 1., 1.], [0.0, 0.0]),
+        // North (-Z)
+        Vertex3dUV::new([0., 0., 0.], [1.0, 1.0]),
+        Vertex3dUV::new([0., 1., 0.], [1.0, 0.0]),
+        Vertex3dUV::new([1., 1., 0.], [0.0, 0.0]),
+        Vertex3dUV::new([1., 0., 0.], [0.0, 1.0]),
+        // East (+X)
+        Vertex3dUV::new([1., 0., 0.], [1.0, 1.0]),
+        Vertex3dUV::new([1., 1., 0.], [1.0, 0.0]),
+        Vertex3dUV::new([1., 1., 1.], [0.0, 0.0]),
+        Vertex3dUV::new([1., 0., 1.], [0.0, 1.0]),
+        // West (-X)
+        Vertex3dUV::new([0., 1., 0.], [0.0, 0.0]),
+        Vertex3dUV::new([0., 0., 0.], [0.0, 1.0]),
+        Vertex3dUV::new([0., 0., 1.], [1.0, 1.0]),
+        Vertex3dUV::new([0., 1., 1.], [1.0, 0.0]),
+        // Up (+Y)
+        Vertex3dUV::new([1., 1., 0.], [0.0, 1.0]),
+        Vertex3dUV::new([0., 1., 0.], [1.0, 1.0]),
+        Vertex3dUV::new([0., 1., 1.], [1.0, 0.0]),
+        Vertex3dUV::new([1., 1., 1.], [0.0, 0.0]),
+        // Down (-Y)
+        Vertex3dUV::new([0., 0., 0.], [0.0, 1.0]),
+        Vertex3dUV::new([1., 0., 0.], [1.0, 1.0]),
+        Vertex3dUV::new([1., 0., 1.], [1.0, 0.0]),
+        Vertex3dUV::new([0., 0., 1.], [0.0, 0.0]),
+    ];
+    #[rustfmt::skip]
+    let indices = [
+        0, 1, 2, 2, 3, 0,
+        4, 5, 6, 6, 7, 4,
+        8, 9, 10, 10, 11, 8,
+        12, 13, 14, 14, 15, 12,
+        16, 17, 18, 18, 19, 16,
+        20, 21, 22, 22, 23, 20,
+    ];
+    (vertices, indices)
+}
+
+pub fn cube_mesh() -> MeshRef {
+    let (vertices, indices) = cube_geometry();
+    context().create_mesh(Arc::new(meshes::Mesh3D::create(
+        context(),
+        &vertices,
+        &indices,
+    )))
+}
+
+/// `cube_geometry` with flat normals and tangents.
+pub fn cube_mesh_tbn() -> MeshRef {
+    let (vertices, indices) = cube_geometry();
+    let vertices = vertices.map(Vertex3dNormalTangentUV::from);
+    let (mut vertices, indices) = compute_flat_normals(&vertices, &indices);
+    compute_tangents(&mut vertices, &indices);
+    context().create_mesh(Arc::new(meshes::Mesh3DTbn::create(
+        context(),
+        &vertices,
+        &indices,
+    )))
+}
+
+/// An 8x8 checkerboard alternating between `a` and `b`.
+pub fn checkerboard_texture(a: [u8; 4], b: [u8; 4]) -> Texture2d {
+    let size = vec2(8, 8);
+    let data: Vec<u8> = (0..size.y)
+        .flat_map(|y| (0..size.x).map(move |x| if (x + y) % 2 == 0 { a } else { b }))
+        .flatten()
+        .collect();
+    Texture2d::create_init(context(), size, TextureFormat::Rgba8Unorm, &data)
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.png"))
+}
+
+fn diff_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/golden-diff")
+}
+
+fn load_png(path: &PathBuf) -> (Vector2<u32>, Vec<u8>) {
+    let file = File::open(path).unwrap_or_else(|error| {
+        panic!(
+            "unable to open golden image {}: {error}\n\
+             (run with TBN_UPDATE_GOLDEN=1 to generate it)",
+            path.display(),
+        )
+    });
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().unwrap();
+    let mut bytes = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut bytes).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    assert_eq!(info.bit_depth, png::BitDepth::Eight);
+    bytes.truncate(info.buffer_size());
+    (vec2(info.width, info.height), bytes)
+}
+
+/// Renders the scene and compares it against `tests/golden/{name}.png`.
+///
+/// A pixel matches if every channel differs by at most `tolerance`.
+#[track_caller]
+pub fn assert_golden(name: &str, scene: &mut Scene, surface: &Surface, tolerance: u8) {
+    scene.render(context(), &surface.view(), &RenderPassOptions::default());
+    assert_golden_surface(name, surface, tolerance);
+}
+
+/// Compares what was already rendered onto the surface against `tests/golden/{name}.png`.
+/// See `assert_golden`.
+#[track_caller]
+pub fn assert_golden_surface(name: &str, surface: &Surface, tolerance: u8) {
+    let pixels = surface.read_pixels(context()).unwrap();
+
+    let golden_path = golden_path(name);
+    if env::var_os("TBN_UPDATE_GOLDEN").is_some() {
+        pixels.save_png(&golden_path).unwrap();
+        return;
+    }
+
+    let (golden_size, golden) = load_png(&golden_path);
+    assert_eq!(
+        golden_size,
+        pixels.size(),
+        "size mismatch against {name}.png"
+    );
+
+    let mut mismatches = 0usize;
+    let diff: Vec<u8> = pixels
+        .bytes()
+        .chunks_exact(4)
+        .zip(golden.chunks_exact(4))
+        .flat_map(|(actual, expected)| {
+            let matches = actual
+                .iter()
+                .zip(expected)
+                .all(|(&a, &b)| a.abs_diff(b) <= tolerance);
+            if matches {
+                // Dimmed version of the expected image, so mismatches stand out.
+                [expected[0] / 4, expected[1] / 4, expected[2] / 4, 255]
+            } else {
+                mismatches += 1;
+                [255, 0, 255, 255]
+            }
+        })
+        .collect();
+
+    if mismatches != 0 {
+        let diff_dir = diff_dir();
+        fs::create_dir_all(&diff_dir).unwrap();
+        pixels
+            .save_png(diff_dir.join(format!("{name}.actual.png")))
+            .unwrap();
+        let diff_file = File::create(diff_dir.join(format!("{name}.diff.png"))).unwrap();
+        let mut encoder = png::Encoder::new(diff_file, golden_size.x, golden_size.y);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&diff)
+            .unwrap();
+        panic!(
+            "{mismatches} pixel(s) differ from {name}.png by more than {tolerance}, \
+             see {} for the rendered and diff images",
+            diff_dir.display(),
+        );
+    }
+}
+
+/// A UV sphere of radius 1 centered at the origin, with smooth normals and tangents.
+pub fn sphere_geometry(segments: u32, rings: u32) -> (Vec<Vertex3dNormalTangentUV>, Vec<u32>) {
+    let mut vertices = Vec::new();
+    for ring in 0..=rings {
+        let v = ring as f32 / rings as f32;
+        let theta = v * std::f32::consts::PI;
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let phi = u * std::f32::consts::TAU;
+            let position = [
+                theta.sin() * phi.sin(),
+                theta.cos(),
+                theta.sin() * phi.cos(),
+            ];
+            vertices.push(Vertex3dNormalTangentUV::new(
+                position,
+                [u, v],
+                position,
+                [0.0; 4],
+            ));
+        }
+    }
+    let mut indices = Vec::new();
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let a = ring * (segments + 1) + segment;
+            let b = a + segments + 1;
+            indices.extend([a, b, a + 1, a + 1, b, b + 1]);
+        }
+    }
+    compute_tangents(&mut vertices, &indices);
+    (vertices, indices)
+}
+
+pub fn sphere_mesh() -> MeshRef {
+    let (vertices, indices) = sphere_geometry(32, 16);
+    context().create_mesh(Arc::new(meshes::Mesh3DTbn::create(
+        context(),
+        &vertices,
+        &indices,
+    )))
+}
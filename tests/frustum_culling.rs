@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use cgmath::*;
+use tbn_engine::*;
+
+fn camera() -> Camera {
+    Camera::new(
+        point3(0.0, 0.0, 4.0),
+        vec3(0.0, 1.0, 0.0),
+        CameraDirection::LookAt(point3(0.0, 0.0, 0.0)),
+        Deg(60.0),
+        0.1,
+        100.0,
+    )
+}
+
+#[test]
+fn bounds_from_points() {
+    let bounds = Bounds::from_points([
+        point3(-1.0, 0.0, 2.0),
+        point3(3.0, 2.0, 2.0),
+        point3(1.0, 1.0, 0.0),
+    ])
+    .unwrap();
+    assert_eq!(
+        bounds.aabb,
+        Aabb::new(point3(-1.0, 0.0, 0.0), point3(3.0, 2.0, 2.0))
+    );
+    assert_eq!(bounds.sphere.center, point3(1.0, 1.0, 1.0));
+    assert_eq!(bounds.sphere.radius, 6.0f32.sqrt());
+    assert_eq!(Bounds::from_points([]), None);
+
+    let transformed = bounds.transform(
+        Matrix4::from_translation(vec3(1.0, 0.0, 0.0))
+            * Matrix4::from_nonuniform_scale(2.0, 1.0, 1.0),
+    );
+    assert_eq!(
+        transformed.aabb,
+        Aabb::new(point3(-1.0, 0.0, 0.0), point3(7.0, 2.0, 2.0))
+    );
+    assert_eq!(transformed.sphere.center, point3(3.0, 1.0, 1.0));
+    assert_eq!(transformed.sphere.radius, 2.0 * 6.0f32.sqrt());
+}
+
+#[test]
+fn camera_frustum() {
+    let frustum = camera().frustum(vec2(100.0, 100.0));
+    assert!(frustum.contains_point(point3(0.0, 0.0, 0.0)));
+    assert!(frustum.contains_point(point3(0.0, 0.0, -90.0)));
+    // Behind the camera, before the near plane, beyond the far plane, and beside the field of view.
+    assert!(!frustum.contains_point(point3(0.0, 0.0, 5.0)));
+    assert!(!frustum.contains_point(point3(0.0, 0.0, 3.95)));
+    assert!(!frustum.contains_point(point3(0.0, 0.0, -97.0)));
+    assert!(!frustum.contains_point(point3(3.0, 0.0, 0.0)));
+    assert!(!frustum.contains_point(point3(0.0, -3.0, 0.0)));
+
+    let sphere = |x| BoundingSphere::new(point3(x, 0.0, 0.0), 1.0);
+    assert!(frustum.intersects_sphere(&sphere(2.5)));
+    assert!(!frustum.intersects_sphere(&sphere(4.0)));
+    let aabb = |x| Aabb::new(point3(x, -0.5, -0.5), point3(x + 1.0, 0.5, 0.5));
+    assert!(frustum.intersects_aabb(&aabb(1.5)));
+    assert!(!frustum.intersects_aabb(&aabb(3.0)));
+}
+
+/// Whether the pixel at `position` isn't black.
+fn is_lit(pixels: &Pixels<TextureFormat>, size: Vector2<u32>, position: Vector2<u32>) -> bool {
+    let index = ((position.y * size.x + position.x) * 4) as usize;
+    pixels.bytes()[index..index + 3] != [0, 0, 0]
+}
+
+#[test]
+fn objects_outside_the_frustum_are_culled() {
+    let context = Context::builder().build().unwrap();
+    let surface = Surface::create(
+        context.wgpu_device(),
+        vec2(16, 16),
+        TextureFormat::Rgba8Unorm,
+    );
+    let mut scene = Scene::new(
+        context.wgpu_device(),
+        surface.format(),
+        surface.depth_stencil_texture().format(),
+    );
+    let camera = context.create_camera(camera());
+    let mesh = context.create_mesh(Arc::new(meshes::Quad::create(&context)));
+    let material = context.create_material(&materials::UniformFill::create(
+        &context,
+        Rgba::new(1.0, 1.0, 1.0, 1.0),
+    ));
+    let create_quad = |scene: &mut Scene, translation: Vector3<f32>| {
+        let object = context.create_object(scene, camera.clone(), mesh.clone(), material.clone());
+        scene.set_object_model(&object, Matrix4::from_translation(translation));
+        scene.add_object(object.clone());
+        object
+    };
+    let visible = create_quad(&mut scene, vec3(-0.5, -0.5, 0.0));
+    let behind = create_quad(&mut scene, vec3(-0.5, -0.5, 8.0));
+    create_quad(&mut scene, vec3(10.0, 0.0, 0.0));
+    // Inside the frustum only through its parent's transform.
+    let child = create_quad(&mut scene, vec3(0.0, 0.0, 0.0));
+    let parent = create_quad(&mut scene, vec3(20.0, 0.0, 0.0));
+    scene.set_object_parent(&child, Some(&parent));
+    scene.set_object_model(&child, Matrix4::from_translation(vec3(-20.5, -0.5, 0.0)));
+
+    let options = RenderPassOptions::default();
+    scene.render(&context, &surface.view(), &options);
+    assert_eq!(scene.culled_object_count(), 3);
+    let pixels = surface.read_pixels(&context).unwrap();
+    assert!(is_lit(&pixels, surface.size(), vec2(8, 8)));
+
+    // Hidden objects aren't counted as culled.
+    scene.set_object_is_hidden(&behind, true);
+    scene.set_object_is_hidden(&visible, true);
+    scene.render(&context, &surface.view(), &options);
+    assert_eq!(scene.culled_object_count(), 2);
+    let pixels = surface.read_pixels(&context).unwrap();
+    assert!(is_lit(&pixels, surface.size(), vec2(8, 8)));
+}
@@ -0,0 +1,206 @@
+mod common;
+
+use cgmath::*;
+use tbn_engine::*;
+
+use common::*;
+
+/// A triangle without UVs, under a translated parent node, and a camera.
+const TRIANGLE: &str = concat!(
+    r#"{
+    "asset": { "version": "2.0" },
+    "extensionsUsed": ["EXT_unknown"],
+    "scene": 0,
+    "scenes": [{ "nodes": [0] }],
+    "nodes": [
+        { "translation": [1.0, 2.0, 3.0], "children": [1, 2] },
+        { "mesh": 0, "scale": [2.0, 2.0, 2.0] },
+        { "camera": 0, "translation": [0.0, 0.0, 5.0] }
+    ],
+    "cameras": [{ "type": "perspective", "perspective": { "yfov": 1.0, "znear": 0.1 } }],
+    "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1 }] }],
+    "buffers": [{
+        "byteLength": 44,
+        "uri": "data:application/octet-stream;base64,"#,
+    "AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAA=",
+    r#""
+    }],
+    "bufferViews": [
+        { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+        { "buffer": 0, "byteOffset": 36, "byteLength": 6 }
+    ],
+    "accessors": [
+        {
+            "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+            "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]
+        },
+        { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+    ]
+}"#,
+);
+
+/// A textured triangle whose texture uses the second set of texture coordinates, with an
+/// extension that doesn't change how it's imported.
+const TEXTURED_TRIANGLE: &str = concat!(
+    r#"{
+    "asset": { "version": "2.0" },
+    "extensionsUsed": ["KHR_materials_unlit", "EXT_unknown"],
+    "scene": 0,
+    "scenes": [{ "nodes": [0] }],
+    "nodes": [{ "mesh": 0 }],
+    "meshes": [{
+        "primitives": [{
+            "attributes": { "POSITION": 0, "TEXCOORD_1": 1 },
+            "indices": 2,
+            "material": 0
+        }]
+    }],
+    "materials": [{
+        "pbrMetallicRoughness": { "baseColorTexture": { "index": 0, "texCoord": 1 } },
+        "extensions": { "KHR_materials_unlit": {} }
+    }],
+    "textures": [{ "source": 0, "sampler": 0 }],
+    "samplers": [{ "wrapS": 33071, "wrapT": 10497 }],
+    "images": [{ "bufferView": 3, "mimeType": "image/png" }],
+    "buffers": [{
+        "byteLength": 138,
+        "uri": "data:application/octet-stream;base64,"#,
+    "AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAAAAAAAAAAAAIA/AAAAAAAAAAAAAIA/AAABAAIA",
+    "AACJUE5HDQoaCgAAAA1JSERSAAAAAQAAAAEIBgAAAB8VxIkAAAANSURBVHicY/jPwPAfAAUAAf+JmT0dAAAAAElF",
+    "TkSuQmCC",
+    r#""
+    }],
+    "bufferViews": [
+        { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+        { "buffer": 0, "byteOffset": 36, "byteLength": 24 },
+        { "buffer": 0, "byteOffset": 60, "byteLength": 6 },
+        { "buffer": 0, "byteOffset": 68, "byteLength": 70 }
+    ],
+    "accessors": [
+        {
+            "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+            "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]
+        },
+        { "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC2" },
+        { "bufferView": 2, "componentType": 5123, "count": 3, "type": "SCALAR" }
+    ]
+}"#,
+);
+
+/// A triangle with normals and tangents, moved to cover the center of the view.
+const TRIANGLE_WITH_NORMALS: &str = concat!(
+    r#"{
+    "asset": { "version": "2.0" },
+    "scene": 0,
+    "scenes": [{ "nodes": [0] }],
+    "nodes": [{ "mesh": 0, "translation": [-0.25, -0.25, 0.0] }],
+    "meshes": [{
+        "primitives": [{
+            "attributes": { "POSITION": 0, "NORMAL": 1, "TANGENT": 2 },
+            "indices": 3
+        }]
+    }],
+    "buffers": [{
+        "byteLength": 128,
+        "uri": "data:application/octet-stream;base64,"#,
+    "AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAAAAAAAAAAAAIA/AAAAAAAAAAAAAIA/AAAAAAAA",
+    "AAAAAIA/AACAPwAAAAAAAAAAAACAPwAAgD8AAAAAAAAAAAAAgD8AAIA/AAAAAAAAAAAAAIA/AAABAAIAAAA=",
+    r#""
+    }],
+    "bufferViews": [
+        { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+        { "buffer": 0, "byteOffset": 36, "byteLength": 36 },
+        { "buffer": 0, "byteOffset": 72, "byteLength": 48 },
+        { "buffer": 0, "byteOffset": 120, "byteLength": 6 }
+    ],
+    "accessors": [
+        {
+            "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+            "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]
+        },
+        { "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3" },
+        { "bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC4" },
+        { "bufferView": 3, "componentType": 5123, "count": 3, "type": "SCALAR" }
+    ]
+}"#,
+);
+
+#[test]
+fn import_triangle_and_camera() {
+    let mut scene = create_scene(&create_surface());
+    let camera = create_camera();
+    let import =
+        GltfImport::from_slice(context(), &mut scene, camera, TRIANGLE.as_bytes()).unwrap();
+
+    assert_eq!(import.objects.len(), 1);
+    assert_eq!(import.cameras.len(), 1);
+    let imported_camera = &import.cameras[0];
+    assert_eq!(imported_camera.position, point3(1.0, 2.0, 8.0));
+    assert_eq!(
+        imported_camera.direction,
+        CameraDirection::LookTo(vec3(0.0, 0.0, -1.0)),
+    );
+    assert_eq!(imported_camera.fov, Rad(1.0));
+
+    assert!(import.warnings.contains(&GltfWarning::MissingUvs {
+        mesh: 0,
+        primitive: 0,
+        set: 0,
+    }));
+    assert!(
+        import
+            .warnings
+            .contains(&GltfWarning::UnsupportedExtension {
+                name: "EXT_unknown".to_owned(),
+                required: false,
+            })
+    );
+}
+
+#[test]
+fn import_texture_coordinates_of_the_base_color_texture() {
+    let mut scene = create_scene(&create_surface());
+    let camera = create_camera();
+    let import =
+        GltfImport::from_slice(context(), &mut scene, camera, TEXTURED_TRIANGLE.as_bytes())
+            .unwrap();
+
+    assert_eq!(import.objects.len(), 1);
+    assert_eq!(
+        import.warnings,
+        [GltfWarning::UnsupportedExtension {
+            name: "EXT_unknown".to_owned(),
+            required: false,
+        }],
+    );
+}
+
+#[test]
+fn file_without_scene_is_an_error() {
+    let mut scene = create_scene(&create_surface());
+    let camera = create_camera();
+    let source = r#"{ "asset": { "version": "2.0" } }"#;
+    let error =
+        GltfImport::from_slice(context(), &mut scene, camera, source.as_bytes()).unwrap_err();
+    assert!(matches!(error, ImportGltfError::NoScene));
+}
+
+#[test]
+fn import_normals_and_tangents() {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let import = GltfImport::from_slice(
+        context(),
+        &mut scene,
+        create_camera(),
+        TRIANGLE_WITH_NORMALS.as_bytes(),
+    )
+    .unwrap();
+    assert_eq!(import.objects.len(), 1);
+
+    // The default material is white.
+    scene.render(context(), &surface.view(), &RenderPassOptions::default());
+    let pixels = surface.read_pixels(context()).unwrap();
+    let index = ((SIZE.y / 2 * SIZE.x + SIZE.x / 2) * 4) as usize;
+    assert_eq!(pixels.bytes()[index..index + 4], [255, 255, 255, 255]);
+}
@@ -0,0 +1,548 @@
+mod common;
+
+use cgmath::*;
+use tbn_engine::*;
+
+use common::*;
+
+#[test]
+fn uniform_fill_quad() {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let camera = create_camera();
+    let material = context().create_material(&materials::UniformFill::create(
+        context(),
+        Rgba::new(1.0, 0.5, 0.0, 1.0),
+    ));
+    add_object(
+        &mut scene,
+        &camera,
+        quad_mesh(),
+        material,
+        Matrix4::from_scale(2.0) * Matrix4::from_translation(vec3(-0.5, -0.5, 0.0)),
+    );
+    assert_golden("uniform_fill_quad", &mut scene, &surface, 2);
+}
+
+#[test]
+fn sdf_circle_quad() {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let camera = create_camera();
+    let material = context().create_material(&materials::SdfCircle::create(
+        context(),
+        Rgba::new(0.2, 0.6, 1.0, 1.0),
+    ));
+    add_object(
+        &mut scene,
+        &camera,
+        quad_mesh(),
+        material,
+        Matrix4::from_scale(3.0) * Matrix4::from_translation(vec3(-0.5, -0.5, 0.0)),
+    );
+    // Anti-aliased edges depend on `fwidth`, which varies slightly between adapters.
+    assert_golden("sdf_circle_quad", &mut scene, &surface, 8);
+}
+
+#[test]
+fn textured_quad() {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let camera = create_camera();
+    let texture = checkerboard_texture([255, 255, 255, 255], [40, 40, 200, 255]);
+    let sampler = Sampler::create(
+        context(),
+        wgpu::AddressMode::ClampToEdge,
+        wgpu::FilterMode::Nearest,
+        wgpu::FilterMode::Nearest,
+    );
+    let material = context().create_material(&materials::Textured::create(
+        texture.view(Default::default()),
+        sampler,
+    ));
+    add_object(
+        &mut scene,
+        &camera,
+        quad_mesh(),
+        material,
+        Matrix4::from_scale(3.0) * Matrix4::from_translation(vec3(-0.5, -0.5, 0.0)),
+    );
+    assert_golden("textured_quad", &mut scene, &surface, 2);
+}
+
+#[test]
+fn uniform_fill_mesh_3d() {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let camera = create_camera();
+    let material = context().create_material(&materials::UniformFill::create(
+        context(),
+        Rgba::new(0.7, 0.4, 1.0, 1.0),
+    ));
+    add_object(
+        &mut scene,
+        &camera,
+        cube_mesh(),
+        material,
+        Matrix4::from_angle_x(Deg(30.0))
+            * Matrix4::from_angle_y(Deg(40.0))
+            * Matrix4::from_scale(1.5)
+            * Matrix4::from_translation([-0.5; 3].into()),
+    );
+    assert_golden("uniform_fill_mesh_3d", &mut scene, &surface, 2);
+}
+
+#[test]
+fn textured_mesh_3d() {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let camera = create_camera();
+    let texture = checkerboard_texture([255, 200, 0, 255], [20, 120, 20, 255]);
+    let sampler = Sampler::create(
+        context(),
+        wgpu::AddressMode::ClampToEdge,
+        wgpu::FilterMode::Nearest,
+        wgpu::FilterMode::Nearest,
+    );
+    let material = context().create_material(&materials::Textured::create(
+        texture.view(Default::default()),
+        sampler,
+    ));
+    add_object(
+        &mut scene,
+        &camera,
+        cube_mesh(),
+        material,
+        Matrix4::from_angle_x(Deg(30.0))
+            * Matrix4::from_angle_y(Deg(40.0))
+            * Matrix4::from_scale(1.5)
+            * Matrix4::from_translation([-0.5; 3].into()),
+    );
+    assert_golden("textured_mesh_3d", &mut scene, &surface, 2);
+}
+
+/// Objects sharing a mesh must each be drawn with their own model matrix.
+#[test]
+fn shared_mesh() {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let camera = create_camera();
+    let mesh = quad_mesh();
+    let colors = [
+        Rgba::new(1.0, 0.0, 0.0, 1.0),
+        Rgba::new(0.0, 1.0, 0.0, 1.0),
+        Rgba::new(0.0, 0.0, 1.0, 1.0),
+    ];
+    for (i, color) in colors.into_iter().enumerate() {
+        let material = context().create_material(&materials::UniformFill::create(context(), color));
+        add_object(
+            &mut scene,
+            &camera,
+            mesh.clone(),
+            material,
+            Matrix4::from_translation(vec3(i as f32 - 1.5, -0.5, 0.0)),
+        );
+    }
+    assert_golden("shared_mesh", &mut scene, &surface, 2);
+}
+
+/// Children are drawn relative to their parent, and hidden along with it.
+#[test]
+fn hierarchy() {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let camera = create_camera();
+    let mesh = quad_mesh();
+    let fill = |color| context().create_material(&materials::UniformFill::create(context(), color));
+
+    let parent = add_object(
+        &mut scene,
+        &camera,
+        mesh.clone(),
+        fill(Rgba::new(1.0, 1.0, 1.0, 1.0)),
+        Matrix4::from_translation(vec3(-1.0, -0.5, 0.0)) * Matrix4::from_angle_z(Deg(30.0)),
+    );
+    let child = add_object(
+        &mut scene,
+        &camera,
+        mesh.clone(),
+        fill(Rgba::new(1.0, 0.0, 0.0, 1.0)),
+        Matrix4::from_translation(vec3(1.0, 0.0, 0.0)) * Matrix4::from_scale(0.5),
+    );
+    scene.set_object_parent(&child, Some(&parent));
+
+    let hidden_parent = add_object(
+        &mut scene,
+        &camera,
+        mesh.clone(),
+        fill(Rgba::new(0.0, 1.0, 0.0, 1.0)),
+        Matrix4::from_translation(vec3(0.5, 0.5, 0.0)),
+    );
+    let hidden_child = add_object(
+        &mut scene,
+        &camera,
+        mesh.clone(),
+        fill(Rgba::new(0.0, 0.0, 1.0, 1.0)),
+        Matrix4::from_translation(vec3(0.0, -1.0, 0.0)),
+    );
+    scene.set_object_parent(&hidden_child, Some(&hidden_parent));
+    scene.set_object_is_hidden(&hidden_parent, true);
+
+    assert_golden("hierarchy", &mut scene, &surface, 2);
+}
+
+#[test]
+fn instanced_quads() {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let camera = create_camera();
+    let material = context().create_material(&materials::UniformFill::create(
+        context(),
+        Rgba::new(1.0, 1.0, 1.0, 1.0),
+    ));
+    let object = context().create_instanced_object(&scene, camera.clone(), quad_mesh(), material);
+    scene.set_object_model(&object, Matrix4::from_translation(vec3(-1.5, -1.5, 0.0)));
+    scene.add_object(object.clone());
+
+    let colors = [
+        Rgba::new(1.0, 0.0, 0.0, 1.0),
+        Rgba::new(0.0, 1.0, 0.0, 1.0),
+        Rgba::new(0.0, 0.0, 1.0, 1.0),
+    ];
+    let mut instance_ids = Vec::new();
+    for (y, color) in colors.into_iter().enumerate() {
+        for x in 0..3 {
+            let model =
+                Matrix4::from_translation(vec3(x as f32, y as f32, 0.0)) * Matrix4::from_scale(0.8);
+            instance_ids.push(object.add_instance(Instance::new(model).with_color(color)));
+        }
+    }
+    // The middle one turns yellow, the bottom-left one is removed.
+    object.update_instance(
+        instance_ids[4],
+        Instance::new(Matrix4::from_translation(vec3(1.0, 1.0, 0.0)) * Matrix4::from_scale(0.8))
+            .with_color(Rgba::new(1.0, 1.0, 0.0, 1.0)),
+    );
+    object.remove_instance(instance_ids[0]);
+
+    assert_golden("instanced_quads", &mut scene, &surface, 2);
+}
+
+#[test]
+fn composited_scenes() {
+    let surface = create_surface();
+    let camera = create_camera();
+    let mesh = quad_mesh();
+    let fill = |color| context().create_material(&materials::UniformFill::create(context(), color));
+
+    // The HUD quad is further away than the world quad, it's only drawn on top because the overlay
+    // pass clears depth.
+    let mut world = create_scene(&surface);
+    add_object(
+        &mut world,
+        &camera,
+        mesh.clone(),
+        fill(Rgba::new(1.0, 0.0, 0.0, 1.0)),
+        Matrix4::from_translation(vec3(-1.0, -1.0, 0.0)) * Matrix4::from_scale(1.5),
+    );
+    let mut hud = create_scene(&surface);
+    add_object(
+        &mut hud,
+        &camera,
+        mesh.clone(),
+        fill(Rgba::new(0.0, 1.0, 0.0, 1.0)),
+        Matrix4::from_translation(vec3(0.0, 0.0, -1.0)),
+    );
+
+    let view = surface.view();
+    world.render(
+        context(),
+        &view,
+        &RenderPassOptions::clear(Rgba::new(0.0, 0.0, 0.5, 1.0)),
+    );
+    hud.render(context(), &view, &RenderPassOptions::overlay());
+
+    assert_golden_surface("composited_scenes", &surface, 2);
+}
+
+/// Shows the view space normals output by `Mesh3DTbn`.
+#[derive(AsBindGroup)]
+struct ViewNormal;
+
+impl AsMaterial for ViewNormal {
+    fn fragment_shader_source() -> Option<ShaderSource> {
+        Some(include_shader!("shaders/view_normal.wgsl"))
+    }
+}
+
+#[test]
+fn flat_normals_mesh_3d_tbn() {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let camera = create_camera();
+
+    let (vertices, indices) = cube_geometry();
+    let vertices: Vec<Vertex3dNormalTangentUV> = vertices.into_iter().map(Into::into).collect();
+    let (mut vertices, indices) = compute_flat_normals(&vertices, &indices);
+    compute_tangents(&mut vertices, &indices);
+    let mesh = context().create_mesh(std::sync::Arc::new(meshes::Mesh3DTbn::create(
+        context(),
+        &vertices,
+        &indices,
+    )));
+    let material = context().create_material(&ViewNormal);
+    add_object(
+        &mut scene,
+        &camera,
+        mesh,
+        material,
+        Matrix4::from_angle_x(Deg(30.0))
+            * Matrix4::from_angle_y(Deg(-40.0))
+            * Matrix4::from_scale(1.5)
+            * Matrix4::from_translation(vec3(-0.5, -0.5, -0.5)),
+    );
+
+    assert_golden("flat_normals_mesh_3d_tbn", &mut scene, &surface, 2);
+}
+
+#[test]
+fn blinn_phong_lights() {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let camera = create_camera();
+    let material = context().create_material(&materials::BlinnPhong::create(
+        context(),
+        Rgba::new(0.8, 0.8, 0.8, 1.0),
+        Rgba::new(1.0, 1.0, 1.0, 1.0),
+        32.0,
+    ));
+    add_object(
+        &mut scene,
+        &camera,
+        sphere_mesh(),
+        material,
+        Matrix4::from_scale(1.5),
+    );
+    scene.set_ambient_light(Rgba::new(0.05, 0.05, 0.05, 1.0));
+
+    let sun = context().create_light(DirectionalLight {
+        direction: vec3(1.0, -1.0, -1.0),
+        color: Rgba::new(1.0, 0.0, 0.0, 1.0),
+        intensity: 1.0,
+    });
+    let bulb = context().create_light(PointLight {
+        position: point3(1.5, 1.0, 2.0),
+        color: Rgba::new(0.0, 1.0, 0.0, 1.0),
+        intensity: 4.0,
+        range: 5.0,
+    });
+    let spot = context().create_light(SpotLight {
+        position: point3(0.0, -1.0, 3.0),
+        direction: vec3(0.0, 0.3, -1.0),
+        color: Rgba::new(0.0, 0.0, 1.0, 1.0),
+        intensity: 8.0,
+        range: 10.0,
+        inner_angle: Deg(10.0).into(),
+        outer_angle: Deg(20.0).into(),
+    });
+    let hidden = context().create_light(DirectionalLight {
+        direction: vec3(0.0, 0.0, -1.0),
+        color: Rgba::new(1.0, 1.0, 1.0, 1.0),
+        intensity: 1.0,
+    });
+    hidden.set_is_hidden(true);
+    for light in [&sun, &bulb, &spot, &hidden] {
+        scene.add_light(light.clone());
+    }
+    assert_golden("blinn_phong_lights", &mut scene, &surface, 2);
+
+    // Move the point light to the other side and remove the sun.
+    let Light::Point(mut bulb_light) = bulb.get() else {
+        unreachable!()
+    };
+    bulb_light.position = point3(-1.5, 1.0, 2.0);
+    bulb.set(bulb_light);
+    scene.remove_light(&sun);
+    assert_golden("blinn_phong_lights_moved", &mut scene, &surface, 2);
+}
+
+#[test]
+fn pbr_spheres() {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let camera = create_camera();
+    let sphere = sphere_mesh();
+    // Rough dielectric, smooth metal, and a textured base color with an emissive glow.
+    let checkerboard = checkerboard_texture([255, 255, 255, 255], [64, 64, 64, 255]);
+    let materials = [
+        materials::Pbr::builder()
+            .base_color(Rgba::new(0.8, 0.2, 0.2, 1.0))
+            .metallic(0.0)
+            .roughness(0.9)
+            .build(context()),
+        materials::Pbr::builder()
+            .base_color(Rgba::new(1.0, 0.8, 0.4, 1.0))
+            .metallic(1.0)
+            .roughness(0.25)
+            .build(context()),
+        materials::Pbr::builder()
+            .base_color_texture(
+                checkerboard.view(Default::default()),
+                Sampler::create(
+                    context(),
+                    wgpu::AddressMode::Repeat,
+                    wgpu::FilterMode::Nearest,
+                    wgpu::FilterMode::Nearest,
+                ),
+            )
+            .metallic(0.0)
+            .roughness(0.5)
+            .emissive(Rgba::new(0.0, 0.0, 0.3, 1.0))
+            .build(context()),
+    ];
+    for (i, material) in materials.iter().enumerate() {
+        add_object(
+            &mut scene,
+            &camera,
+            sphere.clone(),
+            context().create_material(material),
+            Matrix4::from_translation(vec3(i as f32 * 2.2 - 2.2, 0.0, 0.0)),
+        );
+    }
+    scene.set_ambient_light(Rgba::new(0.03, 0.03, 0.03, 1.0));
+    scene.add_light(context().create_light(DirectionalLight {
+        direction: vec3(-1.0, -1.0, -1.0),
+        color: Rgba::new(1.0, 1.0, 1.0, 1.0),
+        intensity: 3.0,
+    }));
+    scene.add_light(context().create_light(PointLight {
+        position: point3(0.0, 2.0, 2.0),
+        color: Rgba::new(1.0, 1.0, 1.0, 1.0),
+        intensity: 6.0,
+        range: 8.0,
+    }));
+    assert_golden("pbr_spheres", &mut scene, &surface, 2);
+}
+
+#[test]
+fn pbr_spheres_environment() {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let camera = create_camera();
+    let sphere = sphere_mesh();
+    // Rough dielectric, smooth metal, and rough metal, lit only by the environment.
+    let materials = [
+        materials::Pbr::builder()
+            .base_color(Rgba::new(0.8, 0.2, 0.2, 1.0))
+            .metallic(0.0)
+            .roughness(0.9)
+            .build(context()),
+        materials::Pbr::builder()
+            .base_color(Rgba::new(1.0, 0.8, 0.4, 1.0))
+            .metallic(1.0)
+            .roughness(0.1)
+            .build(context()),
+        materials::Pbr::builder()
+            .base_color(Rgba::new(0.9, 0.9, 0.9, 1.0))
+            .metallic(1.0)
+            .roughness(0.6)
+            .build(context()),
+    ];
+    for (i, material) in materials.iter().enumerate() {
+        add_object(
+            &mut scene,
+            &camera,
+            sphere.clone(),
+            context().create_material(material),
+            Matrix4::from_translation(vec3(i as f32 * 2.2 - 2.2, 0.0, 0.0)),
+        );
+    }
+    // A blue sky above a brown ground, with grey and white horizons.
+    let faces = [
+        [160, 160, 160, 255],
+        [160, 160, 160, 255],
+        [120, 170, 255, 255],
+        [80, 50, 30, 255],
+        [255, 255, 255, 255],
+        [40, 40, 40, 255],
+    ]
+    .map(|color: [u8; 4]| color.repeat(16));
+    let cube = TextureCube::create_init(
+        context(),
+        4,
+        TextureFormat::Rgba8Unorm,
+        faces.each_ref().map(Vec::as_slice),
+    );
+    let environment = Environment::from_cube(context(), &cube.view(Default::default()));
+    scene.set_environment(context().wgpu_device(), Some(environment));
+    assert_golden("pbr_spheres_environment", &mut scene, &surface, 2);
+}
+
+#[test]
+fn shadows() {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let camera = context().create_camera(Camera::new(
+        point3(0.0, 3.0, 5.0),
+        vec3(0.0, 1.0, 0.0),
+        CameraDirection::LookAt(point3(0.0, -0.5, 0.0)),
+        Deg(50.0),
+        0.1,
+        100.0,
+    ));
+    let material = context().create_material(&materials::BlinnPhong::create(
+        context(),
+        Rgba::new(0.8, 0.8, 0.8, 1.0),
+        Rgba::new(0.2, 0.2, 0.2, 1.0),
+        16.0,
+    ));
+    let ground = add_object(
+        &mut scene,
+        &camera,
+        cube_mesh_tbn(),
+        material.clone(),
+        Matrix4::from_translation(vec3(-3.0, -1.2, -3.0))
+            * Matrix4::from_nonuniform_scale(6.0, 0.2, 6.0),
+    );
+    let sphere = sphere_mesh();
+    add_object(
+        &mut scene,
+        &camera,
+        sphere.clone(),
+        material.clone(),
+        Matrix4::from_translation(vec3(-0.8, -0.4, 0.0)) * Matrix4::from_scale(0.6),
+    );
+    let non_caster = add_object(
+        &mut scene,
+        &camera,
+        sphere,
+        material,
+        Matrix4::from_translation(vec3(1.2, -0.6, 0.5)) * Matrix4::from_scale(0.4),
+    );
+    non_caster.set_casts_shadows(false);
+    scene.set_ambient_light(Rgba::new(0.1, 0.1, 0.1, 1.0));
+
+    let sun = context().create_light(DirectionalLight {
+        direction: vec3(1.0, -2.0, -0.5),
+        color: Rgba::new(1.0, 0.9, 0.8, 1.0),
+        intensity: 1.0,
+    });
+    let spot = context().create_light(SpotLight {
+        position: point3(-2.0, 2.0, 1.0),
+        direction: vec3(1.0, -1.5, -0.5),
+        color: Rgba::new(0.3, 0.3, 1.0, 1.0),
+        intensity: 6.0,
+        range: 10.0,
+        inner_angle: Deg(25.0).into(),
+        outer_angle: Deg(35.0).into(),
+    });
+    for light in [&sun, &spot] {
+        light.set_casts_shadows(true);
+        scene.add_light(light.clone());
+    }
+    scene.set_shadow_distance(20.0);
+    assert_golden("shadows", &mut scene, &surface, 2);
+
+    ground.set_receives_shadows(false);
+    assert_golden("shadows_not_received", &mut scene, &surface, 2);
+}
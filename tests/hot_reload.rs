@@ -0,0 +1,170 @@
+use std::{
+    fs,
+    path::Path,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use cgmath::*;
+use tbn_engine::*;
+
+const SHADER_PATH: &str = concat!(env!("CARGO_TARGET_TMPDIR"), "/hot_reload/fill.wgsl");
+
+const RED_FILL_SHADER: &str = "struct VertexOutput {
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 0.0, 0.0, 1.0);
+}";
+
+/// Like `RED_FILL_SHADER`, but with another color.
+fn fill_shader(color: &str) -> String {
+    RED_FILL_SHADER.replace("vec4<f32>(1.0, 0.0, 0.0, 1.0)", color)
+}
+
+#[derive(AsBindGroup)]
+struct Fill;
+
+impl AsMaterial for Fill {
+    fn fragment_shader_source() -> Option<ShaderSource> {
+        Some(ShaderSource::new(RED_FILL_SHADER).with_path(SHADER_PATH))
+    }
+}
+
+/// Fills with a color converted by a function of a shader module of the engine, and has no file of
+/// its own.
+#[derive(AsBindGroup)]
+struct SrgbFill;
+
+impl AsMaterial for SrgbFill {
+    fn fragment_shader_source() -> Option<ShaderSource> {
+        Some(ShaderSource::new(
+            "#import tbn::vertex_output
+            #import tbn::color
+
+            @fragment
+            fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+                return vec4<f32>(linear_to_srgb(vec3<f32>(1.0)), 1.0);
+            }",
+        ))
+    }
+}
+
+/// Restores the content of a file when dropped, even if the test fails.
+struct RestoreFile {
+    path: &'static str,
+    content: String,
+}
+
+impl Drop for RestoreFile {
+    fn drop(&mut self) {
+        fs::write(self.path, &self.content).unwrap();
+    }
+}
+
+/// Creates a context with hot reloading enabled, and a scene with a quad covering the surface.
+fn setup(material: &impl AsMaterial) -> (Context, Scene, Surface) {
+    let context = Context::builder().build().unwrap();
+    context.enable_shader_hot_reload().unwrap();
+    let surface = Surface::create(context.wgpu_device(), vec2(8, 8), TextureFormat::Rgba8Unorm);
+    let mut scene = Scene::new(
+        context.wgpu_device(),
+        surface.format(),
+        surface.depth_stencil_texture().format(),
+    );
+    let camera = context.create_camera(Camera::new(
+        point3(0.0, 0.0, 4.0),
+        vec3(0.0, 1.0, 0.0),
+        CameraDirection::LookAt(point3(0.0, 0.0, 0.0)),
+        Deg(60.0),
+        0.1,
+        100.0,
+    ));
+    let mesh = context.create_mesh(Arc::new(meshes::Quad::create(&context)));
+    let material = context.create_material(material);
+    let object = context.create_object(&scene, camera, mesh, material);
+    scene.set_object_model(
+        &object,
+        Matrix4::from_scale(2.0) * Matrix4::from_translation(vec3(-0.5, -0.5, 0.0)),
+    );
+    scene.add_object(object);
+    (context, scene, surface)
+}
+
+/// Renders the scene and returns the color of the pixel in the center.
+fn render(context: &Context, scene: &mut Scene, surface: &Surface) -> [u8; 4] {
+    scene.render(context, &surface.view(), &RenderPassOptions::default());
+    let pixels = surface.read_pixels(context).unwrap();
+    let center = surface.size() / 2;
+    let index = ((center.y * surface.size().x + center.x) * 4) as usize;
+    pixels.bytes()[index..index + 4].try_into().unwrap()
+}
+
+/// Renders until the pixel in the center has `color`.
+#[track_caller]
+fn wait_for_color(context: &Context, scene: &mut Scene, surface: &Surface, color: [u8; 4]) {
+    let start = Instant::now();
+    while render(context, scene, surface) != color {
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "shader wasn't reloaded",
+        );
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn reloads_changed_shader() {
+    fs::create_dir_all(Path::new(SHADER_PATH).parent().unwrap()).unwrap();
+    fs::write(SHADER_PATH, RED_FILL_SHADER).unwrap();
+
+    let (context, mut scene, surface) = setup(&Fill);
+    assert_eq!(render(&context, &mut scene, &surface), [255, 0, 0, 255]);
+
+    fs::write(SHADER_PATH, fill_shader("vec4<f32>(0.0, 1.0, 0.0, 1.0)")).unwrap();
+    wait_for_color(&context, &mut scene, &surface, [0, 255, 0, 255]);
+
+    // A shader that doesn't compile keeps the previous version.
+    fs::write(SHADER_PATH, fill_shader("vec4<f32>(0.0, 0.0, 1.0)")).unwrap();
+    thread::sleep(Duration::from_millis(500));
+    assert_eq!(render(&context, &mut scene, &surface), [0, 255, 0, 255]);
+}
+
+/// Changes a shader module in the engine's source, which is restored at the end.
+#[test]
+fn reloads_shaders_importing_changed_module() {
+    const MODULE_PATH: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/shaders/modules/color.wgsl"
+    );
+    let content = fs::read_to_string(MODULE_PATH).unwrap();
+    let _restore = RestoreFile {
+        path: MODULE_PATH,
+        content: content.clone(),
+    };
+
+    let (context, mut scene, surface) = setup(&SrgbFill);
+    assert_eq!(render(&context, &mut scene, &surface), [255, 255, 255, 255]);
+
+    let changed = content.replace(
+        "return select(high, low, color <= vec3<f32>(0.0031308));",
+        "return vec3<f32>(0.0, 1.0, 0.0);",
+    );
+    assert_ne!(changed, content);
+    fs::write(MODULE_PATH, changed).unwrap();
+    wait_for_color(&context, &mut scene, &surface, [0, 255, 0, 255]);
+}
+
+#[test]
+fn include_shader_path() {
+    let source = include_shader!("shaders/view_normal.wgsl");
+    let path = source.path().unwrap();
+    assert!(path.ends_with("tests/shaders/view_normal.wgsl"));
+    assert_eq!(fs::read_to_string(path).unwrap(), source.wgsl());
+    assert_eq!(ShaderSource::new("").path(), None);
+}
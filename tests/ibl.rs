@@ -0,0 +1,263 @@
+mod common;
+
+use std::sync::Arc;
+
+use cgmath::*;
+use tbn_engine::*;
+
+use common::*;
+
+struct Setup {
+    surface: Surface,
+    scene: Scene,
+    camera: CameraRef,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let surface = create_surface();
+        let scene = create_scene(&surface);
+        Self {
+            surface,
+            scene,
+            camera: create_camera(),
+        }
+    }
+
+    /// A small environment from a cube map with a single color on every face.
+    fn environment(&self, faces: [[u8; 4]; 6]) -> Environment {
+        let faces = faces.map(|color| color.repeat(4));
+        let cube = TextureCube::create_init(
+            context(),
+            2,
+            TextureFormat::Rgba8Unorm,
+            faces.each_ref().map(Vec::as_slice),
+        );
+        Environment::from_cube_with_sizes(context(), &cube.view(Default::default()), 8, 16)
+    }
+
+    fn set_environment(&mut self, environment: Option<Environment>) {
+        self.scene
+            .set_environment(context().wgpu_device(), environment);
+    }
+
+    /// Adds a quad of size 2 facing the camera, rotated around the X axis by `angle`.
+    fn add_quad(&mut self, material: &impl AsMaterial, angle: Deg<f32>) {
+        let vertex = |x: f32, y: f32| {
+            Vertex3dNormalTangentUV::new(
+                [x, y, 0.0],
+                [(x + 1.0) / 2.0, (1.0 - y) / 2.0],
+                [0.0, 0.0, 1.0],
+                [1.0, 0.0, 0.0, 1.0],
+            )
+        };
+        let vertices = [
+            vertex(-1.0, -1.0),
+            vertex(1.0, -1.0),
+            vertex(1.0, 1.0),
+            vertex(-1.0, 1.0),
+        ];
+        let mesh = context().create_mesh(Arc::new(meshes::Mesh3DTbn::create(
+            context(),
+            &vertices,
+            &[0, 1, 2, 0, 2, 3],
+        )));
+        let material = context().create_material(material);
+        add_object(
+            &mut self.scene,
+            &self.camera,
+            mesh,
+            material,
+            Matrix4::from_angle_x(angle),
+        );
+    }
+
+    /// Renders the scene and returns the color of the pixel in the center.
+    fn render(&mut self) -> [u8; 4] {
+        self.scene.render(
+            context(),
+            &self.surface.view(),
+            &RenderPassOptions::default(),
+        );
+        let pixels = self.surface.read_pixels(context()).unwrap();
+        let index = ((SIZE.y / 2 * SIZE.x + SIZE.x / 2) * 4) as usize;
+        pixels.bytes()[index..index + 4].try_into().unwrap()
+    }
+}
+
+fn white_diffuse() -> materials::BlinnPhong {
+    materials::BlinnPhong::create(
+        context(),
+        Rgba::new(1.0, 1.0, 1.0, 1.0),
+        Rgba::new(0.0, 0.0, 0.0, 1.0),
+        1.0,
+    )
+}
+
+/// A black `Rgba16Float` environment of `size` with a square sun of `sun_size` texels and
+/// `radiance` in the center of the +Z face.
+fn sun_environment(size: u32, sun_size: u32, radiance: f32) -> TextureCube {
+    let black = [0.0, 0.0, 0.0, 1.0];
+    let sun = [radiance, radiance, radiance, 1.0];
+    let sun_texels = (size - sun_size) / 2..(size + sun_size) / 2;
+    let face = |has_sun: bool| -> Vec<u8> {
+        (0..size * size)
+            .flat_map(|i| {
+                let in_sun = sun_texels.contains(&(i % size)) && sun_texels.contains(&(i / size));
+                if has_sun && in_sun { sun } else { black }
+            })
+            .flat_map(|channel| half::f16::from_f32(channel).to_le_bytes())
+            .collect()
+    };
+    let faces = [0, 1, 2, 3, 4, 5].map(|face_index| face(face_index == 4));
+    TextureCube::create_init(
+        context(),
+        size,
+        TextureFormat::Rgba16Float,
+        faces.each_ref().map(Vec::as_slice),
+    )
+}
+
+#[track_caller]
+fn assert_color_near(actual: [u8; 4], expected: [u8; 3], tolerance: u8) {
+    for (actual_channel, expected_channel) in actual.into_iter().zip(expected) {
+        assert!(
+            actual_channel.abs_diff(expected_channel) <= tolerance,
+            "{actual:?} isn't near {expected:?}",
+        );
+    }
+}
+
+#[test]
+fn uniform_environment_lights_diffuse_surfaces_with_its_color() {
+    let mut setup = Setup::new();
+    setup.add_quad(&white_diffuse(), Deg(0.0));
+    assert_eq!(setup.render(), [0, 0, 0, 255]);
+
+    let environment = setup.environment([[51, 102, 153, 255]; 6]);
+    assert_eq!(environment.irradiance().size(), 8);
+    assert_eq!(environment.prefiltered().size(), 16);
+    assert_eq!(
+        environment.prefiltered().mip_level_count(),
+        Environment::PREFILTERED_MIP_LEVEL_COUNT,
+    );
+    assert_eq!(environment.intensity(), 1.0);
+    setup.set_environment(Some(environment.clone()));
+    assert_color_near(setup.render(), [51, 102, 153], 3);
+
+    setup.set_environment(Some(environment.with_intensity(1.5)));
+    assert_eq!(setup.scene.environment().unwrap().intensity(), 1.5);
+    assert_color_near(setup.render(), [77, 153, 230], 4);
+
+    setup.set_environment(None);
+    assert!(setup.scene.environment().is_none());
+    assert_eq!(setup.render(), [0, 0, 0, 255]);
+}
+
+#[test]
+fn irradiance_comes_from_the_direction_of_the_environment() {
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+    // Only the sky (+Y) is lit.
+    let faces = [BLACK, BLACK, WHITE, BLACK, BLACK, BLACK];
+    let brightness = |angle| {
+        let mut setup = Setup::new();
+        let environment = setup.environment(faces);
+        setup.set_environment(Some(environment));
+        setup.add_quad(&white_diffuse(), angle);
+        setup.render()[0]
+    };
+    let facing_up = brightness(Deg(-45.0));
+    let facing_camera = brightness(Deg(0.0));
+    let facing_down = brightness(Deg(45.0));
+    assert!(facing_up > facing_camera, "{facing_up} > {facing_camera}");
+    assert!(
+        facing_camera > facing_down,
+        "{facing_camera} > {facing_down}"
+    );
+}
+
+#[test]
+fn smooth_metal_reflects_the_environment() {
+    let mut setup = Setup::new();
+    // Red behind the camera (+Z), and blue everywhere else.
+    let blue = [0, 0, 255, 255];
+    let environment = setup.environment([blue, blue, blue, blue, [255, 0, 0, 255], blue]);
+    setup.set_environment(Some(environment));
+    let mirror = materials::Pbr::builder()
+        .base_color(Rgba::new(1.0, 1.0, 1.0, 1.0))
+        .metallic(1.0)
+        .roughness(0.0)
+        .build(context());
+    setup.add_quad(&mirror, Deg(0.0));
+    assert_color_near(setup.render(), [255, 0, 0], 12);
+}
+
+#[test]
+fn brdf_lut() {
+    let brdf_lut = context().brdf_lut();
+    assert_eq!(brdf_lut.size(), vec2(128, 128));
+    assert_eq!(brdf_lut.format(), TextureFormat::Rg16Float);
+
+    let pixels = brdf_lut.read_pixels(context()).unwrap();
+    let texels: Vec<[f32; 2]> = pixels
+        .bytes()
+        .chunks_exact(4)
+        .map(|texel| [0, 2].map(|i| half::f16::from_le_bytes([texel[i], texel[i + 1]]).to_f32()))
+        .collect();
+    for &[scale, bias] in &texels {
+        assert!((0.0..=1.0).contains(&scale), "{scale}");
+        assert!((0.0..=1.0).contains(&bias), "{bias}");
+    }
+    let reflectance = |x: usize, y: usize| {
+        let [scale, bias] = texels[y * 128 + x];
+        scale + bias
+    };
+    // A smooth surface seen head-on reflects all light with a reflectance of 1 at normal
+    // incidence, and rough surfaces at grazing angles lose light to masking.
+    assert!(reflectance(127, 0) > 0.95, "{}", reflectance(127, 0));
+    assert!(reflectance(0, 127) < reflectance(127, 0));
+}
+
+#[test]
+fn small_bright_lights_are_filtered_like_large_dim_ones() {
+    // The same energy from 2x2 texels, or from 8x8 texels with a 16th of the radiance. The
+    // convolutions sample too sparsely to hit every texel of the small sun, so they have to read
+    // mip levels that average it with its surroundings to light surfaces alike.
+    let small_sun = sun_environment(64, 2, 800.0);
+    let large_sun = sun_environment(64, 8, 50.0);
+    let rough_metal = materials::Pbr::builder()
+        .base_color(Rgba::new(1.0, 1.0, 1.0, 1.0))
+        .metallic(1.0)
+        .roughness(1.0)
+        .build(context());
+    let brightness = |cube: &TextureCube, add_quad: &dyn Fn(&mut Setup)| {
+        let mut setup = Setup::new();
+        setup.set_environment(Some(Environment::from_cube_with_sizes(
+            context(),
+            &cube.view(Default::default()),
+            8,
+            16,
+        )));
+        add_quad(&mut setup);
+        setup.render()[0]
+    };
+    // Diffuse, with the sun 45 degrees from the normal.
+    let diffuse = |cube| brightness(cube, &|setup| setup.add_quad(&white_diffuse(), Deg(-45.0)));
+    let small = diffuse(&small_sun);
+    let large = diffuse(&large_sun);
+    assert!(large > 50, "{large}");
+    assert!(
+        small.abs_diff(large) <= large / 10,
+        "{small} isn't near {large}"
+    );
+    // Specular, reflecting the sun.
+    let specular = |cube| brightness(cube, &|setup| setup.add_quad(&rough_metal, Deg(0.0)));
+    let small = specular(&small_sun);
+    let large = specular(&large_sun);
+    assert!(large > 50, "{large}");
+    assert!(
+        small.abs_diff(large) <= large / 10,
+        "{small} isn't near {large}"
+    );
+}
@@ -0,0 +1,146 @@
+use std::io::Cursor;
+
+use cgmath::*;
+use image::{DynamicImage, ImageFormat};
+use tbn_engine::*;
+
+/// A 2x2 RGB image with red, green, blue and white pixels.
+fn rgb_image() -> DynamicImage {
+    let pixels = [255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255];
+    DynamicImage::ImageRgb8(image::RgbImage::from_raw(2, 2, pixels.to_vec()).unwrap())
+}
+
+const RGBA_BYTES: [u8; 16] = [
+    255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 255,
+];
+
+fn encode(image: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut bytes), format)
+        .unwrap();
+    bytes
+}
+
+fn read_back(context: &Context, texture: &Texture2d) -> Vec<u8> {
+    texture.read_pixels(context).unwrap().into_bytes()
+}
+
+#[test]
+fn lossless_formats() {
+    let context = Context::builder().build().unwrap();
+    for format in [ImageFormat::Png, ImageFormat::Bmp, ImageFormat::Tga] {
+        let bytes = encode(&rgb_image(), format);
+        let texture = Texture2d::from_image_bytes(&context, &bytes, ColorSpace::Srgb).unwrap();
+        assert_eq!(texture.size(), vec2(2, 2), "{format:?}");
+        assert_eq!(
+            texture.format(),
+            TextureFormat::Rgba8UnormSrgb,
+            "{format:?}"
+        );
+        assert_eq!(read_back(&context, &texture), RGBA_BYTES, "{format:?}");
+    }
+}
+
+#[test]
+fn color_space() {
+    let context = Context::builder().build().unwrap();
+    let bytes = encode(&rgb_image(), ImageFormat::Png);
+    let texture = Texture2d::from_image_bytes(&context, &bytes, ColorSpace::Linear).unwrap();
+    assert_eq!(texture.format(), TextureFormat::Rgba8Unorm);
+    // The bytes are the same, only their interpretation differs.
+    assert_eq!(read_back(&context, &texture), RGBA_BYTES);
+}
+
+#[test]
+fn channels_are_expanded_to_rgba8() {
+    let context = Context::builder().build().unwrap();
+    let gray =
+        DynamicImage::ImageLuma16(image::ImageBuffer::from_raw(2, 1, vec![0, 65535]).unwrap());
+    let bytes = encode(&gray, ImageFormat::Png);
+    let texture = Texture2d::from_image_bytes(&context, &bytes, ColorSpace::Linear).unwrap();
+    assert_eq!(texture.format(), TextureFormat::Rgba8Unorm);
+    assert_eq!(
+        read_back(&context, &texture),
+        [0, 0, 0, 255, 255, 255, 255, 255]
+    );
+
+    let gray_alpha =
+        DynamicImage::ImageLumaA8(image::ImageBuffer::from_raw(1, 1, vec![100, 50]).unwrap());
+    let bytes = encode(&gray_alpha, ImageFormat::Png);
+    let texture = Texture2d::from_image_bytes(&context, &bytes, ColorSpace::Linear).unwrap();
+    assert_eq!(read_back(&context, &texture), [100, 100, 100, 50]);
+}
+
+#[test]
+fn jpeg() {
+    let context = Context::builder().build().unwrap();
+    let image =
+        DynamicImage::ImageRgb8(image::RgbImage::from_pixel(8, 8, image::Rgb([200, 40, 90])));
+    let bytes = encode(&image, ImageFormat::Jpeg);
+    let texture = Texture2d::from_image_bytes(&context, &bytes, ColorSpace::Srgb).unwrap();
+    assert_eq!(texture.size(), vec2(8, 8));
+    // JPEG is lossy.
+    for pixel in read_back(&context, &texture).chunks_exact(4) {
+        for (&actual, expected) in pixel.iter().zip([200u8, 40, 90, 255]) {
+            assert!(actual.abs_diff(expected) <= 4, "{pixel:?}");
+        }
+    }
+}
+
+#[test]
+fn hdr() {
+    let context = Context::builder().build().unwrap();
+    let image = DynamicImage::ImageRgb32F(
+        image::Rgb32FImage::from_raw(2, 1, vec![4.0, 0.5, 0.0, 0.25, 1.0, 16.0]).unwrap(),
+    );
+    let bytes = encode(&image, ImageFormat::Hdr);
+    // Float images ignore the color space.
+    let texture = Texture2d::from_image_bytes(&context, &bytes, ColorSpace::Srgb).unwrap();
+    assert_eq!(texture.format(), TextureFormat::Rgba16Float);
+    let texels: Vec<f32> = read_back(&context, &texture)
+        .chunks_exact(2)
+        .map(|bits| half::f16::from_bits(u16::from_le_bytes([bits[0], bits[1]])).to_f32())
+        .collect();
+    assert_eq!(texels, [4.0, 0.5, 0.0, 1.0, 0.25, 1.0, 16.0, 1.0]);
+}
+
+#[test]
+fn errors() {
+    let context = Context::builder().build().unwrap();
+    let error =
+        Texture2d::from_image_bytes(&context, b"not an image", ColorSpace::Srgb).unwrap_err();
+    assert!(matches!(error, LoadImageError::UnknownFormat), "{error:?}");
+
+    let bytes = encode(&rgb_image(), ImageFormat::Png);
+    let error = Texture2d::from_image_bytes(&context, &bytes[..bytes.len() / 2], ColorSpace::Srgb)
+        .unwrap_err();
+    assert!(matches!(error, LoadImageError::Decode(_)), "{error:?}");
+
+    let error = Texture2d::from_path(&context, "does/not/exist.png", ColorSpace::Srgb).unwrap_err();
+    assert!(matches!(error, LoadImageError::Io(_)), "{error:?}");
+}
+
+#[test]
+fn images_must_fit_into_a_texture() {
+    let context = Context::builder().build().unwrap();
+    let limit = context.wgpu_device().limits().max_texture_dimension_2d;
+    let image = DynamicImage::ImageRgb8(image::RgbImage::new(limit + 1, 1));
+    let bytes = encode(&image, ImageFormat::Png);
+    let error = Texture2d::from_image_bytes(&context, &bytes, ColorSpace::Srgb).unwrap_err();
+    match error {
+        LoadImageError::TooLarge { size, max_size } => {
+            assert_eq!(size, vec2(limit + 1, 1));
+            assert_eq!(max_size, limit);
+        }
+        error => panic!("{error:?}"),
+    }
+}
+
+#[test]
+fn from_path() {
+    let context = Context::builder().build().unwrap();
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/foobar/image.png");
+    let texture = Texture2d::from_path(&context, path, ColorSpace::Srgb).unwrap();
+    assert_eq!(texture.size(), vec2(256, 256));
+}
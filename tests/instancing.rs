@@ -0,0 +1,90 @@
+mod common;
+
+use cgmath::*;
+use tbn_engine::*;
+
+use common::*;
+
+fn white() -> MaterialRef {
+    context().create_material(&materials::UniformFill::create(
+        context(),
+        Rgba::new(1.0, 1.0, 1.0, 1.0),
+    ))
+}
+
+fn setup() -> (Surface, Scene, ObjectRef) {
+    let surface = create_surface();
+    let scene = create_scene(&surface);
+    let object = context().create_instanced_object(&scene, create_camera(), quad_mesh(), white());
+    (surface, scene, object)
+}
+
+fn translation(x: f32) -> Instance {
+    Instance::new(Matrix4::from_translation(vec3(x, 0.0, 0.0)))
+}
+
+#[test]
+fn add_remove_update() {
+    let (_surface, _scene, object) = setup();
+    assert!(object.is_instanced());
+    let a = object.add_instance(translation(1.0));
+    let b = object.add_instance(translation(2.0));
+    let c = object.add_instance(translation(3.0));
+    assert_eq!(object.instance_count(), 3);
+
+    assert_eq!(object.remove_instance(a), translation(1.0));
+    assert_eq!(object.instance_count(), 2);
+    assert_eq!(object.get_instance(a), None);
+    assert_eq!(object.get_instance(b), Some(translation(2.0)));
+    assert_eq!(object.get_instance(c), Some(translation(3.0)));
+
+    object.update_instance(c, translation(4.0));
+    assert_eq!(object.get_instance(c), Some(translation(4.0)));
+    assert_eq!(object.get_instance(b), Some(translation(2.0)));
+}
+
+#[test]
+fn render_many_instances() {
+    let (surface, mut scene, object) = setup();
+    scene.add_object(object.clone());
+    // Unit quads from x = -2 every 2 units, of which only the first two are fully visible.
+    let instances: Vec<InstanceId> = (0..1000)
+        .map(|i| object.add_instance(translation(2.0 * i as f32 - 2.0)))
+        .collect();
+
+    let render = |scene: &mut Scene| {
+        scene.render(context(), &surface.view(), &RenderPassOptions::default());
+        surface.read_pixels(context()).unwrap()
+    };
+    let pixels = render(&mut scene);
+    assert!(is_covered(&pixels, -1.5));
+    assert!(!is_covered(&pixels, -0.5));
+    assert!(is_covered(&pixels, 0.5));
+    assert!(!is_covered(&pixels, 1.5));
+
+    object.remove_instance(instances[1]);
+    let pixels = render(&mut scene);
+    assert!(is_covered(&pixels, -1.5));
+    assert!(!is_covered(&pixels, 0.5));
+}
+
+/// Whether the pixel at the projection of (x, 0.5, 0) by `create_camera` is white.
+fn is_covered(pixels: &Pixels<TextureFormat>, x: f32) -> bool {
+    let half_extent = 4.0 * Deg(30.0).tan();
+    let column = ((x / half_extent + 1.0) / 2.0 * SIZE.x as f32) as usize;
+    let row = ((1.0 - 0.5 / half_extent) / 2.0 * SIZE.y as f32) as usize;
+    let index = (row * SIZE.x as usize + column) * 4;
+    match pixels.bytes()[index..index + 4] {
+        [255, 255, 255, 255] => true,
+        [0, 0, 0, 255] => false,
+        ref color => panic!("unexpected color {color:?} at x = {x}"),
+    }
+}
+
+#[test]
+#[should_panic = "object isn't instanced"]
+fn non_instanced_object_panics() {
+    let (_surface, scene, _) = setup();
+    let object = context().create_object(&scene, create_camera(), quad_mesh(), white());
+    object.add_instance(Instance::default());
+}
@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use cgmath::*;
+use tbn_engine::*;
+
+fn usage() -> wgpu::TextureUsages {
+    wgpu::TextureUsages::COPY_DST
+        | wgpu::TextureUsages::COPY_SRC
+        | wgpu::TextureUsages::TEXTURE_BINDING
+        | wgpu::TextureUsages::RENDER_ATTACHMENT
+}
+
+#[test]
+fn mip_level_counts_and_sizes() {
+    assert_eq!(Texture2d::max_mip_level_count(vec2(256, 256)), 9);
+    assert_eq!(Texture2d::max_mip_level_count(vec2(5, 3)), 3);
+    assert_eq!(Texture2d::max_mip_level_count(vec2(1, 1)), 1);
+
+    let context = Context::builder().build().unwrap();
+    let texture = Texture2d::create_with_mip_levels(
+        context.wgpu_device(),
+        vec2(5, 3),
+        TextureFormat::Rgba8Unorm,
+        usage(),
+        3,
+    );
+    assert_eq!(texture.mip_level_count(), 3);
+    assert_eq!(texture.mip_level_size(1), vec2(2, 1));
+    assert_eq!(texture.mip_level_size(2), vec2(1, 1));
+    let view = texture.view_mip_levels(wgpu::TextureSampleType::default(), 1..3);
+    assert_eq!(view.size(), vec2(2, 1));
+}
+
+#[test]
+#[should_panic = "out of range"]
+fn view_mip_levels_out_of_range() {
+    let context = Context::builder().build().unwrap();
+    let texture = Texture2d::create(
+        context.wgpu_device(),
+        vec2(4, 4),
+        TextureFormat::Rgba8Unorm,
+        usage(),
+    );
+    texture.view_mip_levels(wgpu::TextureSampleType::default(), 0..2);
+}
+
+#[test]
+fn generated_levels_average_the_level_above() {
+    let context = Context::builder().build().unwrap();
+    // Columns of 0, 40, 80 and 120 in the red channel.
+    let data: Vec<u8> = (0..16).flat_map(|i| [(i % 4) * 40, 0, 0, 255]).collect();
+    let texture = Texture2d::create_init_mipmapped(
+        &context,
+        vec2(4, 4),
+        TextureFormat::Rgba8Unorm,
+        wgpu::TextureUsages::COPY_SRC,
+        &data,
+    )
+    .unwrap();
+    assert_eq!(texture.mip_level_count(), 3);
+    let level_1 = texture.read_mip_level_pixels(&context, 1).unwrap();
+    assert_eq!(level_1.size(), vec2(2, 2));
+    assert_eq!(
+        level_1.bytes(),
+        [20, 0, 0, 255, 100, 0, 0, 255, 20, 0, 0, 255, 100, 0, 0, 255]
+    );
+    let level_2 = texture.read_mip_level_pixels(&context, 2).unwrap();
+    assert_eq!(level_2.bytes(), [60, 0, 0, 255]);
+    // The first level is untouched.
+    assert_eq!(texture.read_pixels(&context).unwrap().bytes(), data);
+}
+
+#[test]
+fn srgb_textures_are_averaged_in_linear_space() {
+    let context = Context::builder().build().unwrap();
+    let data = [0, 0, 0, 255, 255, 255, 255, 255];
+    let texture = Texture2d::create_init_mipmapped(
+        &context,
+        vec2(2, 1),
+        TextureFormat::Rgba8UnormSrgb,
+        wgpu::TextureUsages::COPY_SRC,
+        &data,
+    )
+    .unwrap();
+    let level_1 = texture.read_mip_level_pixels(&context, 1).unwrap();
+    // Linear 0.5 is about 188 in sRGB.
+    assert!(
+        level_1.bytes()[0].abs_diff(188) <= 1,
+        "{:?}",
+        level_1.bytes()
+    );
+}
+
+#[test]
+fn snorm_levels_are_generated_in_a_compute_pass() {
+    let context = Context::builder().build().unwrap();
+    // Columns of 0, 40, 80 and 120 in the red channel. Negative values aren't used, as they read
+    // back as 0 on GL.
+    let data: Vec<u8> = (0..16).flat_map(|i| [(i % 4) * 40, 0, 0, 127]).collect();
+    let texture = Texture2d::create_init_mipmapped(
+        &context,
+        vec2(4, 4),
+        TextureFormat::Rgba8Snorm,
+        wgpu::TextureUsages::COPY_SRC,
+        &data,
+    )
+    .unwrap();
+    assert!(
+        !texture
+            .usage()
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+    );
+    let level_1 = texture.read_mip_level_pixels(&context, 1).unwrap();
+    assert_eq!(
+        level_1.bytes(),
+        [20, 0, 0, 127, 100, 0, 0, 127, 20, 0, 0, 127, 100, 0, 0, 127]
+    );
+    let level_2 = texture.read_mip_level_pixels(&context, 2).unwrap();
+    assert_eq!(level_2.bytes(), [60, 0, 0, 127]);
+
+    let texture = Texture2d::create_with_mip_levels(
+        context.wgpu_device(),
+        vec2(4, 4),
+        TextureFormat::Rgba8Snorm,
+        wgpu::TextureUsages::TEXTURE_BINDING,
+        3,
+    );
+    let error = texture.generate_mipmaps(&context).unwrap_err();
+    let GenerateMipmapsError::MissingUsage(usage) = error else {
+        panic!("{error:?}");
+    };
+    assert_eq!(usage, wgpu::TextureUsages::COPY_DST);
+}
+
+#[test]
+fn errors() {
+    let context = Context::builder().build().unwrap();
+    let texture = Texture2d::create_with_mip_levels(
+        context.wgpu_device(),
+        vec2(4, 4),
+        TextureFormat::Rgba8Unorm,
+        wgpu::TextureUsages::TEXTURE_BINDING,
+        3,
+    );
+    let error = texture.generate_mipmaps(&context).unwrap_err();
+    let GenerateMipmapsError::MissingUsage(usage) = error else {
+        panic!("{error:?}");
+    };
+    assert_eq!(usage, wgpu::TextureUsages::RENDER_ATTACHMENT);
+
+    // An integer format, and a format that is neither renderable nor supports storage.
+    for format in [TextureFormat::Rgba8Uint, TextureFormat::R8Snorm] {
+        let texture = Texture2d::create_with_mip_levels(
+            context.wgpu_device(),
+            vec2(4, 4),
+            format,
+            wgpu::TextureUsages::TEXTURE_BINDING,
+            3,
+        );
+        assert!(
+            matches!(
+                texture.generate_mipmaps(&context),
+                Err(GenerateMipmapsError::UnsupportedFormat(_)),
+            ),
+            "{format:?}",
+        );
+    }
+}
+
+/// Renders a small quad far away with a 64x64 texture whose first mip level is red and the others
+/// blue, and returns the color in the center.
+fn render_minified(sampler_options: SamplerOptions) -> [u8; 4] {
+    let context = Context::builder().build().unwrap();
+    let surface = Surface::create(context.wgpu_device(), vec2(8, 8), TextureFormat::Rgba8Unorm);
+    let mut scene = Scene::new(
+        context.wgpu_device(),
+        surface.format(),
+        surface.depth_stencil_texture().format(),
+    );
+    let camera = context.create_camera(Camera::new(
+        point3(0.0, 0.0, 4.0),
+        vec3(0.0, 1.0, 0.0),
+        CameraDirection::LookAt(point3(0.0, 0.0, 0.0)),
+        Deg(60.0),
+        0.1,
+        100.0,
+    ));
+    let size = vec2(64, 64);
+    let texture = Texture2d::create_with_mip_levels(
+        context.wgpu_device(),
+        size,
+        TextureFormat::Rgba8Unorm,
+        usage(),
+        Texture2d::max_mip_level_count(size),
+    );
+    for mip_level in 0..texture.mip_level_count() {
+        let level_size = texture.mip_level_size(mip_level);
+        let color = if mip_level == 0 {
+            [255, 0, 0, 255]
+        } else {
+            [0, 0, 255, 255]
+        };
+        let data = color.repeat((level_size.x * level_size.y) as usize);
+        context.wgpu_queue().write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: texture.wgpu_texture(),
+                mip_level,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &data,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(level_size.x * 4),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: level_size.x,
+                height: level_size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+    let sampler = Sampler::create_with_options(&context, &sampler_options);
+    let material = context.create_material(&materials::Textured::create(
+        texture.view(Default::default()),
+        sampler,
+    ));
+    let mesh = context.create_mesh(Arc::new(meshes::Quad::create(&context)));
+    let object = context.create_object(&scene, camera, mesh, material);
+    scene.set_object_model(&object, Matrix4::from_translation(vec3(-0.5, -0.5, 0.0)));
+    scene.add_object(object);
+    scene.render(&context, &surface.view(), &RenderPassOptions::default());
+    let pixels = surface.read_pixels(&context).unwrap();
+    let index = ((4 * 8 + 4) * 4) as usize;
+    pixels.bytes()[index..index + 4].try_into().unwrap()
+}
+
+#[test]
+fn samplers_clamp_mip_levels() {
+    let sampled_mip_levels = render_minified(SamplerOptions::default());
+    assert_eq!(sampled_mip_levels, [0, 0, 255, 255]);
+    let first_mip_level = render_minified(SamplerOptions {
+        lod_max_clamp: 0.0,
+        ..Default::default()
+    });
+    assert_eq!(first_mip_level, [255, 0, 0, 255]);
+    let anisotropic = render_minified(SamplerOptions {
+        anisotropy_clamp: 16,
+        ..Default::default()
+    });
+    assert_eq!(anisotropic, [0, 0, 255, 255]);
+}
@@ -0,0 +1,69 @@
+use tbn_engine::*;
+
+const TWO_MATERIALS: &str = "\
+mtllib test.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+usemtl red
+f 1/1/1 2/2/1 3/3/1 4/4/1
+usemtl blue
+f 1/1/1 3/3/1 4/4/1
+";
+
+const MTL: &str = "\
+newmtl red
+Kd 1 0 0
+newmtl blue
+Kd 0 0 1
+";
+
+#[test]
+fn quad_is_triangulated_and_deduplicated() {
+    let model = ObjModel::from_reader(TWO_MATERIALS.as_bytes()).unwrap();
+    let red = &model.meshes[0];
+    assert_eq!(red.material_name.as_deref(), Some("red"));
+    assert_eq!(red.vertices.len(), 4);
+    assert_eq!(red.indices, [0, 1, 2, 0, 2, 3]);
+    assert_eq!(red.normals, [[0.0, 0.0, 1.0]; 4]);
+    // V is flipped.
+    assert_eq!(red.vertices[0].uv, [0.0, 1.0]);
+    assert_eq!(red.vertices[2].uv, [1.0, 0.0]);
+}
+
+#[test]
+fn meshes_are_split_by_material() {
+    let model =
+        ObjModel::from_reader_with_mtls(TWO_MATERIALS.as_bytes(), |_| Ok(MTL.as_bytes())).unwrap();
+    assert_eq!(model.meshes.len(), 2);
+    let blue = &model.meshes[1];
+    assert_eq!(blue.material_name.as_deref(), Some("blue"));
+    assert_eq!(blue.material.as_ref().unwrap().kd, Some([0.0, 0.0, 1.0]));
+    assert_eq!(blue.vertices.len(), 3);
+    assert_eq!(blue.indices, [0, 1, 2]);
+}
+
+#[test]
+fn out_of_range_index_is_an_error() {
+    let source = "v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 3/5\n";
+    let error = ObjModel::from_reader(source.as_bytes()).unwrap_err();
+    assert!(matches!(
+        error,
+        LoadObjError::IndexOutOfRange {
+            attribute: ObjAttribute::Uv,
+            index: 4,
+        }
+    ));
+}
+
+#[test]
+fn malformed_input_is_an_error() {
+    let error = ObjModel::from_reader("v 0 0\n".as_bytes()).unwrap_err();
+    assert!(matches!(error, LoadObjError::Obj(_)));
+}
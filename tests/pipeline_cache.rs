@@ -0,0 +1,81 @@
+mod common;
+
+use std::sync::Arc;
+
+use tbn_engine::*;
+
+use common::*;
+
+/// Uses a context of its own, so that only the pipelines of the test are counted.
+fn setup() -> (Context, Scene, CameraRef) {
+    let context = Context::builder().build().unwrap();
+    let scene = Scene::new(
+        context.wgpu_device(),
+        FORMAT,
+        DepthStencilTextureFormat::Depth32Float,
+    );
+    let camera = context.create_camera(camera());
+    (context, scene, camera)
+}
+
+fn uniform_fill(context: &Context, rgba: Rgba) -> MaterialRef {
+    context.create_material(&materials::UniformFill::create(context, rgba))
+}
+
+#[test]
+fn objects_of_the_same_kind_share_a_pipeline() {
+    let (context, scene, camera) = setup();
+    for i in 0..10 {
+        let mesh = context.create_mesh(Arc::new(meshes::Quad::create(&context)));
+        let material = uniform_fill(&context, Rgba::new(i as f32 / 10.0, 0.0, 0.0, 1.0));
+        context.create_object(&scene, camera.clone(), mesh, material);
+    }
+    assert_eq!(context.render_pipeline_count(), 1);
+}
+
+#[test]
+fn different_kinds_get_different_pipelines() {
+    let (context, scene, camera) = setup();
+    let quad = context.create_mesh(Arc::new(meshes::Quad::create(&context)));
+    let mesh_3d = context.create_mesh(Arc::new(meshes::Mesh3D::create(
+        &context,
+        &[
+            Vertex3dUV::new([0.0, 0.0, 0.0], [0.0, 0.0]),
+            Vertex3dUV::new([1.0, 0.0, 0.0], [1.0, 0.0]),
+            Vertex3dUV::new([0.0, 1.0, 0.0], [0.0, 1.0]),
+        ],
+        &[0, 1, 2],
+    )));
+    let fill = uniform_fill(&context, Rgba::new(1.0, 1.0, 1.0, 1.0));
+    let circle = context.create_material(&materials::SdfCircle::create(
+        &context,
+        Rgba::new(1.0, 1.0, 1.0, 1.0),
+    ));
+    context.create_object(&scene, camera.clone(), quad.clone(), fill.clone());
+    context.create_object(&scene, camera.clone(), quad.clone(), circle.clone());
+    context.create_object(&scene, camera.clone(), mesh_3d.clone(), fill.clone());
+    assert_eq!(context.render_pipeline_count(), 3);
+
+    let other_scene = Scene::new(
+        context.wgpu_device(),
+        TextureFormat::Bgra8UnormSrgb,
+        DepthStencilTextureFormat::Depth32Float,
+    );
+    context.create_object(&other_scene, camera.clone(), quad, fill);
+    assert_eq!(context.render_pipeline_count(), 4);
+}
+
+#[test]
+fn scenes_with_the_same_formats_share_pipelines() {
+    let (context, scene, camera) = setup();
+    let other_scene = Scene::new(
+        context.wgpu_device(),
+        FORMAT,
+        DepthStencilTextureFormat::Depth32Float,
+    );
+    let quad = context.create_mesh(Arc::new(meshes::Quad::create(&context)));
+    let fill = uniform_fill(&context, Rgba::new(1.0, 1.0, 1.0, 1.0));
+    context.create_object(&scene, camera.clone(), quad.clone(), fill.clone());
+    context.create_object(&other_scene, camera, quad, fill);
+    assert_eq!(context.render_pipeline_count(), 1);
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use cgmath::*;
+use tbn_engine::*;
+
+use common::*;
+
+/// Distinct bytes for every texel, so that misplaced rows show up.
+fn pattern(size: Vector2<u32>, bytes_per_texel: u32) -> Vec<u8> {
+    (0..size.x * size.y * bytes_per_texel)
+        .map(|i| (i * 7 % 251) as u8)
+        .collect()
+}
+
+fn usage() -> wgpu::TextureUsages {
+    wgpu::TextureUsages::COPY_DST
+        | wgpu::TextureUsages::COPY_SRC
+        | wgpu::TextureUsages::TEXTURE_BINDING
+}
+
+#[test]
+fn rows_not_aligned_to_256_bytes() {
+    // 52 and 13 bytes per row, which the copy pads to 256.
+    for (format, bytes_per_texel) in [(TextureFormat::Rgba8Unorm, 4), (TextureFormat::R8Unorm, 1)] {
+        let size = vec2(13, 7);
+        let data = pattern(size, bytes_per_texel);
+        let texture = Texture2d::create_init_with_usage(context(), size, format, usage(), &data);
+        let pixels = texture.read_pixels(context()).unwrap();
+        assert_eq!(pixels.size(), size);
+        assert_eq!(pixels.format(), format);
+        assert_eq!(pixels.bytes(), data, "{format:?}");
+    }
+}
+
+#[test]
+fn surface_with_unaligned_width() {
+    let surface = Surface::create(context().wgpu_device(), vec2(13, 7), FORMAT);
+    let mut scene = create_scene(&surface);
+    let options = RenderPassOptions::clear(Rgba::new(1.0, 0.0, 1.0, 1.0));
+    scene.render(context(), &surface.view(), &options);
+    let pixels = surface.read_pixels(context()).unwrap();
+    assert_eq!(pixels.size(), vec2(13, 7));
+    assert_eq!(pixels.bytes(), [255, 0, 255, 255].repeat(13 * 7));
+}
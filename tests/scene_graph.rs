@@ -0,0 +1,85 @@
+mod common;
+
+use cgmath::*;
+use tbn_engine::*;
+
+use common::*;
+
+fn setup() -> (Scene, CameraRef, MeshRef, MaterialRef) {
+    let scene = create_scene(&create_surface());
+    let material = context().create_material(&materials::UniformFill::create(
+        context(),
+        Rgba::new(1.0, 1.0, 1.0, 1.0),
+    ));
+    (scene, create_camera(), quad_mesh(), material)
+}
+
+fn add(scene: &mut Scene, camera: &CameraRef, mesh: &MeshRef, material: &MaterialRef) -> ObjectRef {
+    let object = context().create_object(scene, camera.clone(), mesh.clone(), material.clone());
+    scene.add_object(object.clone());
+    object
+}
+
+#[test]
+fn world_matrix_follows_ancestors() {
+    let (mut scene, camera, mesh, material) = setup();
+    let car = add(&mut scene, &camera, &mesh, &material);
+    let wheel = add(&mut scene, &camera, &mesh, &material);
+    let bolt = add(&mut scene, &camera, &mesh, &material);
+    scene.set_object_parent(&wheel, Some(&car));
+    scene.set_object_parent(&bolt, Some(&wheel));
+    scene.set_object_transform(&car, LocalTransform::from_translation(vec3(10.0, 0.0, 0.0)));
+    scene.set_object_transform(&wheel, LocalTransform::from_scale(2.0));
+    scene.set_object_transform(&bolt, LocalTransform::from_translation(vec3(0.0, 1.0, 0.0)));
+
+    let bolt_origin = scene.object_world_matrix(&bolt) * vec4(0.0, 0.0, 0.0, 1.0);
+    assert_eq!(bolt_origin, vec4(10.0, 2.0, 0.0, 1.0));
+
+    // Moving the root moves the whole subtree.
+    scene.set_object_transform(
+        &car,
+        LocalTransform::from_translation(vec3(-10.0, 0.0, 0.0)),
+    );
+    let bolt_origin = scene.object_world_matrix(&bolt) * vec4(0.0, 0.0, 0.0, 1.0);
+    assert_eq!(bolt_origin, vec4(-10.0, 2.0, 0.0, 1.0));
+}
+
+#[test]
+fn reparenting() {
+    let (mut scene, camera, mesh, material) = setup();
+    let a = add(&mut scene, &camera, &mesh, &material);
+    let b = add(&mut scene, &camera, &mesh, &material);
+    let child = add(&mut scene, &camera, &mesh, &material);
+    scene.set_object_transform(&a, LocalTransform::from_translation(vec3(1.0, 0.0, 0.0)));
+    scene.set_object_transform(&b, LocalTransform::from_translation(vec3(0.0, 1.0, 0.0)));
+
+    scene.set_object_parent(&child, Some(&a));
+    assert_eq!(scene.object_children(&a).len(), 1);
+    assert_eq!(
+        scene.object_world_matrix(&child),
+        Matrix4::from_translation(vec3(1.0, 0.0, 0.0)),
+    );
+
+    scene.set_object_parent(&child, Some(&b));
+    assert!(scene.object_children(&a).is_empty());
+    assert_eq!(scene.object_children(&b).len(), 1);
+    assert_eq!(
+        scene.object_world_matrix(&child),
+        Matrix4::from_translation(vec3(0.0, 1.0, 0.0)),
+    );
+
+    // Removing the parent turns the child into a root object.
+    scene.remove_object(&b);
+    assert!(scene.object_parent(&child).is_none());
+    assert_eq!(scene.object_world_matrix(&child), Matrix4::identity());
+}
+
+#[test]
+#[should_panic = "object cannot be parented to itself or its descendant"]
+fn parenting_to_descendant_panics() {
+    let (mut scene, camera, mesh, material) = setup();
+    let parent = add(&mut scene, &camera, &mesh, &material);
+    let child = add(&mut scene, &camera, &mesh, &material);
+    scene.set_object_parent(&child, Some(&parent));
+    scene.set_object_parent(&parent, Some(&child));
+}
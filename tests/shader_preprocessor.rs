@@ -0,0 +1,163 @@
+use std::sync::Arc;
+
+use cgmath::*;
+use tbn_engine::*;
+
+/// Lines of the output that aren't empty, without leading and trailing whitespace.
+fn lines(wgsl: &str) -> Vec<&str> {
+    wgsl.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+#[test]
+fn imports_are_included_once() {
+    let wgsl = preprocess_wgsl("#import tbn::lights\n#import tbn::camera\n", &[]).unwrap();
+    // `tbn::lights` imports `tbn::camera` itself.
+    assert_eq!(wgsl.matches("struct Camera {").count(), 1);
+    assert!(wgsl.contains("struct Lights {"));
+    assert!(!wgsl.contains("#import"));
+}
+
+#[test]
+fn all_modules_are_valid() {
+    for name in shader_module_names() {
+        let wgsl = preprocess_wgsl(&format!("#import {name}"), &[]).unwrap();
+        naga::front::wgsl::parse_str(&wgsl)
+            .unwrap_or_else(|error| panic!("{name}: {}", error.emit_to_string(&wgsl)));
+    }
+}
+
+#[test]
+fn conditions() {
+    let source = "
+        #ifdef A
+            a
+            #ifndef B
+                a_not_b
+            #else
+                a_b
+            #endif
+        #else
+            not_a
+            #ifdef B
+                not_a_b
+            #endif
+        #endif
+        always
+    ";
+    let expand = |defines| preprocess_wgsl(source, defines).unwrap();
+    assert_eq!(lines(&expand(&[])), ["not_a", "always"]);
+    assert_eq!(lines(&expand(&["A"])), ["a", "a_not_b", "always"]);
+    assert_eq!(lines(&expand(&["A", "B"])), ["a", "a_b", "always"]);
+    assert_eq!(lines(&expand(&["B"])), ["not_a", "not_a_b", "always"]);
+}
+
+#[test]
+fn define() {
+    let source = "
+        #ifdef A
+            before
+        #endif
+        #define A
+        #ifdef A
+            after
+        #endif
+    ";
+    assert_eq!(lines(&preprocess_wgsl(source, &[]).unwrap()), ["after"]);
+}
+
+#[test]
+fn errors() {
+    let error = preprocess_wgsl("\n#import tbn::nothing", &[]).unwrap_err();
+    assert_eq!(
+        error,
+        PreprocessError {
+            module: None,
+            line: 2,
+            kind: PreprocessErrorKind::UnknownModule("tbn::nothing".to_string()),
+        },
+    );
+    assert_eq!(
+        error.to_string(),
+        "line 2: unknown shader module `tbn::nothing`",
+    );
+
+    let error = preprocess_wgsl("#ifdef A\n#endif\n#endif", &[]).unwrap_err();
+    assert_eq!(error.line, 3);
+    assert_eq!(error.kind, PreprocessErrorKind::UnmatchedDirective("endif"));
+
+    let error = preprocess_wgsl("#ifdef A\n#else\n#else\n#endif", &[]).unwrap_err();
+    assert_eq!(error.line, 3);
+    assert_eq!(error.kind, PreprocessErrorKind::UnmatchedDirective("else"));
+
+    let error = preprocess_wgsl("#ifdef A\n", &[]).unwrap_err();
+    assert_eq!(error.kind, PreprocessErrorKind::UnterminatedCondition);
+
+    let error = preprocess_wgsl("#ifdef", &[]).unwrap_err();
+    assert_eq!(
+        error.kind,
+        PreprocessErrorKind::MissingName("ifdef".to_string())
+    );
+
+    let error = preprocess_wgsl("#include \"a.wgsl\"", &[]).unwrap_err();
+    assert_eq!(
+        error.kind,
+        PreprocessErrorKind::UnknownDirective("include".to_string()),
+    );
+}
+
+#[test]
+fn defines_specialize_material_shaders() {
+    let context = Context::builder().build().unwrap();
+    let scene = Scene::new(
+        context.wgpu_device(),
+        TextureFormat::Rgba8Unorm,
+        DepthStencilTextureFormat::Depth32Float,
+    );
+    let camera = context.create_camera(Camera::new(
+        point3(0.0, 0.0, 4.0),
+        vec3(0.0, 1.0, 0.0),
+        CameraDirection::LookAt(point3(0.0, 0.0, 0.0)),
+        Deg(60.0),
+        0.1,
+        100.0,
+    ));
+    let vertex = |position, uv| {
+        Vertex3dNormalTangentUV::new(position, uv, [0.0, 0.0, 1.0], [1.0, 0.0, 0.0, 1.0])
+    };
+    let mesh = context.create_mesh(Arc::new(meshes::Mesh3DTbn::create(
+        &context,
+        &[
+            vertex([0.0, 0.0, 0.0], [0.0, 0.0]),
+            vertex([1.0, 0.0, 0.0], [1.0, 0.0]),
+            vertex([0.0, 1.0, 0.0], [0.0, 1.0]),
+        ],
+        &[0, 1, 2],
+    )));
+    let normal_texture = Texture2d::create_init(
+        &context,
+        vec2(1, 1),
+        TextureFormat::Rgba8Unorm,
+        &[128, 128, 255, 255],
+    );
+    let sampler = Sampler::create(
+        &context,
+        wgpu::AddressMode::Repeat,
+        wgpu::FilterMode::Linear,
+        wgpu::FilterMode::Linear,
+    );
+    let plain = [
+        materials::Pbr::builder().build(&context),
+        materials::Pbr::builder().metallic(1.0).build(&context),
+    ];
+    let normal_mapped = materials::Pbr::builder()
+        .normal_texture(normal_texture.view(Default::default()), sampler)
+        .build(&context);
+    for material in plain.iter().chain([&normal_mapped]) {
+        let material = context.create_material(material);
+        context.create_object(&scene, camera.clone(), mesh.clone(), material);
+    }
+    assert_eq!(context.render_pipeline_count(), 2);
+}
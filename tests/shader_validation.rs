@@ -0,0 +1,343 @@
+mod common;
+
+use std::sync::Arc;
+
+use tbn_engine::*;
+
+use common::*;
+
+/// A fragment shader with the same interface as the built-in materials, declaring `$bindings` in
+/// group 2 and returning `$color`.
+macro_rules! fragment_shader {
+    ($bindings:literal, $color:literal $(,)?) => {
+        concat!(
+            "struct VertexOutput {
+                @location(0) uv: vec2<f32>,
+                @location(1) color: vec4<f32>,
+                @builtin(position) position: vec4<f32>,
+            };
+            ",
+            $bindings,
+            "
+            @fragment
+            fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+                return ",
+            $color,
+            ";
+            }",
+        )
+    };
+}
+
+/// Binds a color, but its shader expects a texture and a sampler.
+#[derive(AsBindGroup)]
+struct TextureInShader {
+    #[binding(0, fragment)]
+    color: UniformBuffer<Rgba>,
+}
+
+impl AsMaterial for TextureInShader {
+    fn fragment_shader_source() -> Option<ShaderSource> {
+        Some(ShaderSource::new(fragment_shader!(
+            "@group(2) @binding(0) var texture: texture_2d<f32>;
+            @group(2) @binding(1) var texture_sampler: sampler;",
+            "textureSample(texture, texture_sampler, vertex.uv)",
+        )))
+    }
+}
+
+/// Binds a color, but its shader also uses a radius.
+#[derive(AsBindGroup)]
+struct MissingRadius {
+    #[binding(0, fragment)]
+    color: UniformBuffer<Rgba>,
+}
+
+impl AsMaterial for MissingRadius {
+    fn fragment_shader_source() -> Option<ShaderSource> {
+        Some(ShaderSource::new(fragment_shader!(
+            "@group(2) @binding(0) var<uniform> color: vec4<f32>;
+            @group(2) @binding(1) var<uniform> radius: f32;",
+            "color * radius",
+        )))
+    }
+}
+
+/// Binds a color for the vertex stage only, but its fragment shader uses it.
+#[derive(AsBindGroup)]
+struct ColorNotInFragment {
+    #[binding(0, vertex)]
+    color: UniformBuffer<Rgba>,
+}
+
+impl AsMaterial for ColorNotInFragment {
+    fn fragment_shader_source() -> Option<ShaderSource> {
+        Some(ShaderSource::new(fragment_shader!(
+            "@group(2) @binding(0) var<uniform> color: vec4<f32>;",
+            "color",
+        )))
+    }
+}
+
+/// Binds a color, but its shader reads a matrix from it.
+#[derive(AsBindGroup)]
+struct UniformTooSmall {
+    #[binding(0, fragment)]
+    color: UniformBuffer<Rgba>,
+}
+
+impl AsMaterial for UniformTooSmall {
+    fn fragment_shader_source() -> Option<ShaderSource> {
+        Some(ShaderSource::new(fragment_shader!(
+            "@group(2) @binding(0) var<uniform> transform: mat4x4<f32>;",
+            "transform * vertex.color",
+        )))
+    }
+}
+
+/// Declares a binding that its shader doesn't use, which is allowed.
+#[derive(AsBindGroup)]
+struct UnusedBinding {
+    #[binding(0, fragment)]
+    color: UniformBuffer<Rgba>,
+    #[binding(1, fragment)]
+    radius: UniformBuffer<f32>,
+}
+
+impl AsMaterial for UnusedBinding {
+    fn fragment_shader_source() -> Option<ShaderSource> {
+        Some(ShaderSource::new(fragment_shader!(
+            "@group(2) @binding(0) var<uniform> color: vec4<f32>;",
+            "color",
+        )))
+    }
+}
+
+/// Its shader has an unknown directive.
+#[derive(AsBindGroup)]
+struct UnknownDirective {}
+
+impl AsMaterial for UnknownDirective {
+    fn fragment_shader_source() -> Option<ShaderSource> {
+        Some(ShaderSource::new(fragment_shader!(
+            "#unknown",
+            "vec4<f32>(1.0)",
+        )))
+    }
+}
+
+/// Its shader has a syntax error.
+#[derive(AsBindGroup)]
+struct SyntaxError {}
+
+impl AsMaterial for SyntaxError {
+    fn fragment_shader_source() -> Option<ShaderSource> {
+        Some(ShaderSource::new(fragment_shader!("", "vec4<f32>(1.0")))
+    }
+}
+
+/// A mesh with `Vertex3dUV` vertices, whose vertex shader takes the UV as `$uv_type` and converts
+/// it with `$uv`.
+macro_rules! uv_mesh {
+    ($name:ident, $uv_type:literal, $uv:literal $(,)?) => {
+        #[derive(AsBindGroup)]
+        struct $name {
+            vertex_buffer: VertexBuffer<Vertex3dUV>,
+            index_buffer: IndexBuffer<u32>,
+        }
+
+        impl $name {
+            fn create() -> Self {
+                let device = context().wgpu_device();
+                Self {
+                    vertex_buffer: VertexBuffer::create_init(
+                        device,
+                        &[
+                            Vertex3dUV::new([0.0, 0.0, 0.0], [0.0, 0.0]),
+                            Vertex3dUV::new([1.0, 0.0, 0.0], [1.0, 0.0]),
+                            Vertex3dUV::new([0.0, 1.0, 0.0], [0.0, 1.0]),
+                        ],
+                    ),
+                    index_buffer: IndexBuffer::create_init(device, &[0, 1, 2]),
+                }
+            }
+        }
+
+        impl AsMesh for $name {
+            type Vertex = Vertex3dUV;
+
+            type Index = u32;
+
+            fn vertex_shader_source() -> Option<ShaderSource> {
+                Some(ShaderSource::new(concat!(
+                    "struct VertexOutput {
+                        @location(0) uv: vec2<f32>,
+                        @location(1) color: vec4<f32>,
+                        @builtin(position) position: vec4<f32>,
+                    };
+
+                    @vertex
+                    fn vs_main(@location(0) position: vec3<f32>, @location(1) uv: ",
+                    $uv_type,
+                    ") -> VertexOutput {
+                        var result: VertexOutput;
+                        result.color = vec4<f32>(1.0);
+                        result.uv = ",
+                    $uv,
+                    ";
+                        result.position = vec4<f32>(position, 1.0);
+                        return result;
+                    }",
+                )))
+            }
+
+            fn vertex_buffer(&self) -> &VertexBuffer<Self::Vertex> {
+                &self.vertex_buffer
+            }
+
+            fn index_buffer(&self) -> &IndexBuffer<Self::Index> {
+                &self.index_buffer
+            }
+        }
+    };
+}
+
+// Takes more components than the vertices have, which are filled in.
+uv_mesh!(WideUv, "vec4<f32>", "uv.xy");
+// Takes integers, but the vertices have floats.
+uv_mesh!(IntegerUv, "vec2<u32>", "vec2<f32>(uv)");
+
+fn color() -> UniformBuffer<Rgba> {
+    UniformBuffer::create_init(context().wgpu_device(), Rgba::new(1.0, 1.0, 1.0, 1.0))
+}
+
+fn try_create_object(
+    mesh: MeshRef,
+    material: &impl AsMaterial,
+) -> Result<ObjectRef, ShaderValidationError> {
+    let scene = create_scene(&create_surface());
+    let material = context().create_material(material);
+    context().try_create_object(&scene, create_camera(), mesh, material)
+}
+
+fn try_create_quad(material: &impl AsMaterial) -> Result<ObjectRef, ShaderValidationError> {
+    try_create_object(quad_mesh(), material)
+}
+
+fn white() -> materials::UniformFill {
+    materials::UniformFill::create(context(), Rgba::new(1.0, 1.0, 1.0, 1.0))
+}
+
+#[test]
+fn binding_type_mismatch() {
+    let error = try_create_quad(&TextureInShader { color: color() }).unwrap_err();
+    assert!(error.type_name.ends_with("TextureInShader"));
+    assert!(matches!(
+        error.kind,
+        ShaderValidationErrorKind::BindingTypeMismatch {
+            group: 2,
+            binding: 0,
+            ref name,
+            ref found,
+            ..
+        } if name == "texture" && found == "texture_2d<f32>",
+    ));
+}
+
+#[test]
+fn binding_not_visible() {
+    let error = try_create_quad(&ColorNotInFragment { color: color() }).unwrap_err();
+    assert_eq!(
+        error.kind,
+        ShaderValidationErrorKind::BindingNotVisible {
+            group: 2,
+            binding: 0,
+            name: "color".to_string(),
+            stage: wgpu::ShaderStages::FRAGMENT,
+        },
+    );
+    assert!(error.to_string().starts_with("fragment shader of"));
+}
+
+#[test]
+fn missing_binding() {
+    let error = try_create_quad(&MissingRadius { color: color() }).unwrap_err();
+    assert_eq!(
+        error.kind,
+        ShaderValidationErrorKind::MissingBinding {
+            group: 2,
+            binding: 1,
+            name: "radius".to_string(),
+        },
+    );
+    assert!(error.to_string().contains("@group(2) @binding(1)"));
+}
+
+#[test]
+fn uniform_too_small() {
+    let error = try_create_quad(&UniformTooSmall { color: color() }).unwrap_err();
+    assert!(matches!(
+        error.kind,
+        ShaderValidationErrorKind::BindingTypeMismatch { ref found, .. }
+            if found == "var<uniform> of 64 bytes",
+    ));
+}
+
+#[test]
+fn unused_binding() {
+    try_create_quad(&UnusedBinding {
+        color: color(),
+        radius: UniformBuffer::create_init(context().wgpu_device(), 1.0),
+    })
+    .unwrap();
+}
+
+#[test]
+fn invalid_directive() {
+    let error = try_create_quad(&UnknownDirective {}).unwrap_err();
+    assert!(error.type_name.ends_with("UnknownDirective"));
+    assert!(matches!(
+        error.kind,
+        ShaderValidationErrorKind::Preprocess(PreprocessError {
+            kind: PreprocessErrorKind::UnknownDirective(ref directive),
+            ..
+        }) if directive == "unknown",
+    ));
+}
+
+#[test]
+fn invalid_wgsl() {
+    let error = try_create_quad(&SyntaxError {}).unwrap_err();
+    assert!(error.type_name.ends_with("SyntaxError"));
+    assert!(matches!(error.kind, ShaderValidationErrorKind::Invalid(_)));
+}
+
+#[test]
+fn vertex_attribute_with_more_components() {
+    let mesh = context().create_mesh(Arc::new(WideUv::create()));
+    try_create_object(mesh, &white()).unwrap();
+}
+
+#[test]
+fn vertex_attribute_type_mismatch() {
+    let mesh = context().create_mesh(Arc::new(IntegerUv::create()));
+    let error = try_create_object(mesh, &white()).unwrap_err();
+    assert!(error.type_name.ends_with("IntegerUv"));
+    assert_eq!(
+        error.kind,
+        ShaderValidationErrorKind::VertexAttributeTypeMismatch {
+            location: 1,
+            name: "uv".to_string(),
+            expected: wgpu::VertexFormat::Float32x2,
+            found: "vec2<u32>".to_string(),
+        },
+    );
+}
+
+#[test]
+#[should_panic(expected = "which the bind group doesn't have")]
+fn create_object_panics() {
+    let scene = create_scene(&create_surface());
+    let material = context().create_material(&MissingRadius { color: color() });
+    context().create_object(&scene, create_camera(), quad_mesh(), material);
+}
@@ -0,0 +1,8 @@
+#define VERTEX_OUTPUT_TBN
+#import tbn::vertex_output
+
+// Encodes the view space normal as color.
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(normalize(vertex.normal) * 0.5 + 0.5, 1.0);
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use cgmath::*;
+use tbn_engine::*;
+
+use common::*;
+
+#[test]
+fn degenerate_spot_lights_cast_no_shadows() {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let material = context().create_material(&materials::BlinnPhong::create(
+        context(),
+        Rgba::new(1.0, 1.0, 1.0, 1.0),
+        Rgba::new(0.0, 0.0, 0.0, 1.0),
+        1.0,
+    ));
+    add_object(
+        &mut scene,
+        &create_camera(),
+        cube_mesh_tbn(),
+        material,
+        Matrix4::from_translation(vec3(-0.5, -0.5, -0.5)),
+    );
+    let spot = SpotLight {
+        position: point3(0.0, 0.0, 2.0),
+        direction: vec3(0.0, 0.0, -1.0),
+        color: Rgba::new(1.0, 1.0, 1.0, 1.0),
+        intensity: 1.0,
+        range: 10.0,
+        inner_angle: Rad(0.0),
+        outer_angle: Deg(30.0).into(),
+    };
+    for light in [
+        SpotLight {
+            outer_angle: Rad(0.0),
+            ..spot
+        },
+        SpotLight { range: 0.0, ..spot },
+        SpotLight {
+            direction: vec3(0.0, 0.0, 0.0),
+            ..spot
+        },
+    ] {
+        let light = context().create_light(light);
+        light.set_casts_shadows(true);
+        scene.add_light(light);
+    }
+
+    scene.render(context(), &surface.view(), &RenderPassOptions::default());
+    let pixels = surface.read_pixels(context()).unwrap();
+    let index = ((SIZE.y / 2 * SIZE.x + SIZE.x / 2) * 4) as usize;
+    assert_eq!(pixels.bytes()[index..index + 4], [0, 0, 0, 255]);
+}
+
+/// Renders a floor lit by a spot light from above, with a 2x2 quad between them casting a shadow
+/// onto the center of the floor if `occluder` is `Some`, and returns the brightness of the floor
+/// below the center of the surface.
+fn floor_brightness(occluder: Option<materials::UniformFill>) -> u8 {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let camera = create_camera();
+    let floor = context().create_material(&materials::BlinnPhong::create(
+        context(),
+        Rgba::new(1.0, 1.0, 1.0, 1.0),
+        Rgba::new(0.0, 0.0, 0.0, 1.0),
+        1.0,
+    ));
+    add_object(
+        &mut scene,
+        &camera,
+        cube_mesh_tbn(),
+        floor,
+        Matrix4::from_translation(vec3(-3.0, -1.2, -3.0))
+            * Matrix4::from_nonuniform_scale(6.0, 0.2, 6.0),
+    );
+    // Horizontal, so that the camera only sees its edge.
+    if let Some(occluder) = occluder {
+        add_object(
+            &mut scene,
+            &camera,
+            quad_mesh(),
+            context().create_material(&occluder),
+            Matrix4::from_angle_x(Deg(-90.0))
+                * Matrix4::from_scale(2.0)
+                * Matrix4::from_translation(vec3(-0.5, -0.5, 0.0)),
+        );
+    }
+    let light = context().create_light(SpotLight {
+        position: point3(0.0, 3.0, 0.0),
+        direction: vec3(0.0, -1.0, 0.0),
+        color: Rgba::new(1.0, 1.0, 1.0, 1.0),
+        intensity: 20.0,
+        range: 10.0,
+        inner_angle: Deg(40.0).into(),
+        outer_angle: Deg(45.0).into(),
+    });
+    light.set_casts_shadows(true);
+    scene.add_light(light);
+
+    scene.render(context(), &surface.view(), &RenderPassOptions::default());
+    let pixels = surface.read_pixels(context()).unwrap();
+    // The floor at (0, -1, 0) is a quarter of the camera's distance below its view direction.
+    let row = SIZE.y / 2 + (0.25 / Deg(30.0).tan() * (SIZE.y / 2) as f32) as u32;
+    let index = ((row * SIZE.x + SIZE.x / 2) * 4) as usize;
+    pixels.bytes()[index]
+}
+
+#[test]
+fn masked_casters_discard_fragments_below_cutoff_and_blended_ones_cast_no_shadows() {
+    let fill = |alpha, alpha_mode| {
+        let color = Rgba::new(1.0, 1.0, 1.0, alpha);
+        Some(materials::UniformFill::create(context(), color).with_alpha_mode(alpha_mode))
+    };
+    let lit = floor_brightness(None);
+    assert!(lit > 100, "{lit}");
+    assert_eq!(floor_brightness(fill(1.0, AlphaMode::Opaque)), 0);
+    assert_eq!(
+        floor_brightness(fill(1.0, AlphaMode::Mask { cutoff: 0.5 })),
+        0,
+    );
+    assert_eq!(
+        floor_brightness(fill(0.3, AlphaMode::Mask { cutoff: 0.5 })),
+        lit,
+    );
+    assert_eq!(floor_brightness(fill(0.5, AlphaMode::Blend)), lit);
+}
+
+#[test]
+#[should_panic = "exceeds the maximum texture size"]
+fn shadow_maps_must_fit_into_a_texture() {
+    let mut scene = create_scene(&create_surface());
+    let max_texture_dimension = context().wgpu_device().limits().max_texture_dimension_2d;
+    scene.set_shadow_map_size(context().wgpu_device(), max_texture_dimension / 4 + 1);
+}
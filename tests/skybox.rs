@@ -0,0 +1,163 @@
+mod common;
+
+use cgmath::*;
+use tbn_engine::*;
+
+use common::*;
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const GREEN: [u8; 4] = [0, 255, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+const YELLOW: [u8; 4] = [255, 255, 0, 255];
+const MAGENTA: [u8; 4] = [255, 0, 255, 255];
+const CYAN: [u8; 4] = [0, 255, 255, 255];
+
+struct Setup {
+    surface: Surface,
+    scene: Scene,
+    camera: CameraRef,
+}
+
+fn setup(cube: &TextureCube) -> Setup {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    let camera = context().create_camera(camera(point3(0.0, 0.0, 4.0), vec3(0.0, 0.0, -1.0)));
+    let sampler = Sampler::create(
+        context(),
+        wgpu::AddressMode::ClampToEdge,
+        wgpu::FilterMode::Nearest,
+        wgpu::FilterMode::Nearest,
+    );
+    let skybox = Skybox::new(camera.clone(), cube.view(Default::default()), sampler);
+    scene.set_skybox(context().wgpu_device(), Some(skybox));
+    Setup {
+        surface,
+        scene,
+        camera,
+    }
+}
+
+fn camera(position: Point3<f32>, direction: Vector3<f32>) -> Camera {
+    let up = if direction.y.abs() > 0.9 {
+        vec3(0.0, 0.0, -1.0)
+    } else {
+        vec3(0.0, 1.0, 0.0)
+    };
+    Camera::new(
+        position,
+        up,
+        CameraDirection::LookTo(direction),
+        Deg(60.0),
+        0.1,
+        100.0,
+    )
+}
+
+impl Setup {
+    fn look_to(&self, position: Point3<f32>, direction: Vector3<f32>) {
+        self.camera
+            .with_mut(|camera| *camera = self::camera(position, direction));
+    }
+
+    /// Renders the scene and returns the color of the pixel in the center.
+    fn render(&mut self) -> [u8; 4] {
+        self.scene.render(
+            context(),
+            &self.surface.view(),
+            &RenderPassOptions::default(),
+        );
+        let pixels = self.surface.read_pixels(context()).unwrap();
+        let index = ((SIZE.y / 2 * SIZE.x + SIZE.x / 2) * 4) as usize;
+        pixels.bytes()[index..index + 4].try_into().unwrap()
+    }
+
+    fn add_quad(&mut self, color: Rgba) {
+        add_object(
+            &mut self.scene,
+            &self.camera,
+            quad_mesh(),
+            context().create_material(&materials::UniformFill::create(context(), color)),
+            Matrix4::from_translation(vec3(-0.5, -0.5, 0.0)),
+        );
+    }
+}
+
+/// A scene with a skybox of a single color on every face.
+fn colored_cube_setup() -> Setup {
+    let faces = [RED, GREEN, BLUE, YELLOW, MAGENTA, CYAN].map(|color| color.repeat(4));
+    let cube = TextureCube::create_init(
+        context(),
+        2,
+        TextureFormat::Rgba8Unorm,
+        faces.each_ref().map(Vec::as_slice),
+    );
+    assert_eq!(cube.size(), 2);
+    assert_eq!(cube.view(Default::default()).size(), 2);
+    setup(&cube)
+}
+
+const DIRECTIONS: [(Vector3<f32>, [u8; 4]); 6] = [
+    (vec3(1.0, 0.0, 0.0), RED),
+    (vec3(-1.0, 0.0, 0.0), GREEN),
+    (vec3(0.0, 1.0, 0.0), BLUE),
+    (vec3(0.0, -1.0, 0.0), YELLOW),
+    (vec3(0.0, 0.0, 1.0), MAGENTA),
+    (vec3(0.0, 0.0, -1.0), CYAN),
+];
+
+#[test]
+fn cube_faces() {
+    let mut setup = colored_cube_setup();
+    for (direction, color) in DIRECTIONS {
+        // Only the rotation of the camera matters.
+        for position in [point3(0.0, 0.0, 0.0), point3(50.0, -20.0, 10.0)] {
+            setup.look_to(position, direction);
+            assert_eq!(setup.render(), color, "{direction:?} from {position:?}");
+        }
+    }
+}
+
+#[test]
+fn skybox_is_drawn_behind_opaque_and_under_blended_objects() {
+    let mut setup = colored_cube_setup();
+    setup.add_quad(Rgba::new(1.0, 1.0, 1.0, 1.0));
+    assert_eq!(setup.render(), [255, 255, 255, 255]);
+
+    let mut setup = colored_cube_setup();
+    setup.add_quad(Rgba::new(1.0, 0.0, 0.0, 0.5));
+    let color = setup.render();
+    // Half red over cyan.
+    for (actual, expected) in color.into_iter().zip([128, 128, 128]) {
+        assert!(actual.abs_diff(expected) <= 2, "{color:?}");
+    }
+
+    setup.scene.set_skybox(context().wgpu_device(), None);
+    assert!(setup.scene.skybox().is_none());
+    assert_eq!(setup.render()[1], 0);
+}
+
+#[test]
+fn equirectangular_to_cube() {
+    // Columns facing -X, -Z, +X and +Z, with +Y above and -Y below.
+    let column_colors = [GREEN, CYAN, CYAN, RED, RED, MAGENTA, MAGENTA, GREEN];
+    let rows = [[BLUE; 8], column_colors, column_colors, [YELLOW; 8]];
+    let equirectangular = Texture2d::create_init(
+        context(),
+        vec2(8, 4),
+        TextureFormat::Rgba8Unorm,
+        rows.as_flattened().as_flattened(),
+    );
+    let cube = TextureCube::from_equirectangular(
+        context(),
+        &equirectangular.view(Default::default()),
+        4,
+        TextureFormat::Rgba16Float,
+        wgpu::TextureUsages::empty(),
+    );
+    assert_eq!(cube.format(), TextureFormat::Rgba16Float);
+    let mut setup = setup(&cube);
+    for (direction, color) in DIRECTIONS {
+        setup.look_to(point3(0.0, 0.0, 0.0), direction);
+        assert_eq!(setup.render(), color, "{direction:?}");
+    }
+}
@@ -0,0 +1,97 @@
+use tbn_engine::*;
+
+/// A unit quad in the XY plane facing +Z, split into two triangles.
+fn quad(uvs: [[f32; 2]; 4]) -> (Vec<Vertex3dNormalTangentUV>, Vec<u32>) {
+    let positions = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ];
+    let vertices = positions
+        .into_iter()
+        .zip(uvs)
+        .map(|(position, uv)| Vertex3dUV::new(position, uv).into())
+        .collect();
+    (vertices, vec![0, 1, 2, 2, 3, 0])
+}
+
+#[track_caller]
+fn assert_close(actual: &[f32], expected: &[f32]) {
+    let is_close = actual.len() == expected.len()
+        && actual
+            .iter()
+            .zip(expected)
+            .all(|(a, b)| (a - b).abs() < 1e-5);
+    assert!(is_close, "expected {expected:?}, got {actual:?}");
+}
+
+#[test]
+fn smooth_normals() {
+    let (mut vertices, indices) = quad([[0.0, 0.0]; 4]);
+    compute_smooth_normals(&mut vertices, &indices);
+    for vertex in &vertices {
+        assert_eq!(vertex.normal, [0.0, 0.0, 1.0]);
+    }
+
+    // Two triangles folded along the Y axis at a right angle.
+    let mut vertices: Vec<Vertex3dNormalTangentUV> = [
+        [0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0],
+    ]
+    .into_iter()
+    .map(|position| Vertex3dUV::new(position, [0.0, 0.0]).into())
+    .collect();
+    let indices = [0, 2, 1, 0, 1, 3];
+    compute_smooth_normals(&mut vertices, &indices);
+    let shared = std::f32::consts::FRAC_1_SQRT_2;
+    assert_close(&vertices[0].normal, &[shared, 0.0, shared]);
+    assert_close(&vertices[2].normal, &[0.0, 0.0, 1.0]);
+    assert_close(&vertices[3].normal, &[1.0, 0.0, 0.0]);
+}
+
+#[test]
+fn flat_normals() {
+    let mut vertices: Vec<Vertex3dNormalTangentUV> = [
+        [0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0],
+    ]
+    .into_iter()
+    .map(|position| Vertex3dUV::new(position, [0.0, 0.0]).into())
+    .collect();
+    vertices[0].uv = [0.5, 0.5];
+    let (vertices, indices) = compute_flat_normals(&vertices, &[0, 2, 1, 0, 1, 3]);
+    assert_eq!(indices, [0, 1, 2, 3, 4, 5]);
+    assert_eq!(vertices.len(), 6);
+    for vertex in &vertices[..3] {
+        assert_close(&vertex.normal, &[0.0, 0.0, 1.0]);
+    }
+    for vertex in &vertices[3..] {
+        assert_close(&vertex.normal, &[1.0, 0.0, 0.0]);
+    }
+    // Other attributes are kept.
+    assert_eq!(vertices[0].uv, [0.5, 0.5]);
+    assert_eq!(vertices[3].position, [0.0, 0.0, 0.0]);
+}
+
+#[test]
+fn tangents() {
+    let (mut vertices, indices) = quad([[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]);
+    compute_smooth_normals(&mut vertices, &indices);
+    compute_tangents(&mut vertices, &indices);
+    for vertex in &vertices {
+        assert_close(&vertex.tangent, &[1.0, 0.0, 0.0, 1.0]);
+    }
+
+    // Mirrored along V, so the bitangent points the other way.
+    let (mut vertices, indices) = quad([[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]]);
+    compute_smooth_normals(&mut vertices, &indices);
+    compute_tangents(&mut vertices, &indices);
+    for vertex in &vertices {
+        assert_close(&vertex.tangent, &[1.0, 0.0, 0.0, -1.0]);
+    }
+}
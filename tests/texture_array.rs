@@ -0,0 +1,336 @@
+mod common;
+
+use cgmath::*;
+use tbn_engine::*;
+
+use common::*;
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const GREEN: [u8; 4] = [0, 255, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+const YELLOW: [u8; 4] = [255, 255, 0, 255];
+const MAGENTA: [u8; 4] = [255, 0, 255, 255];
+const CYAN: [u8; 4] = [0, 255, 255, 255];
+
+/// Samples one layer of a texture array.
+#[derive(AsBindGroup)]
+struct ArrayLayer {
+    #[binding(0, fragment)]
+    texture: TextureView2dArray,
+    #[binding(1, fragment)]
+    sampler: Sampler,
+    #[binding(2, fragment)]
+    layer: UniformBuffer<u32>,
+}
+
+impl AsMaterial for ArrayLayer {
+    fn fragment_shader_source() -> Option<ShaderSource> {
+        Some(ShaderSource::new(
+            "#import tbn::vertex_output
+
+            @group(2) @binding(0) var texture: texture_2d_array<f32>;
+            @group(2) @binding(1) var texture_sampler: sampler;
+            @group(2) @binding(2) var<uniform> layer: u32;
+
+            @fragment
+            fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+                return textureSample(texture, texture_sampler, vertex.uv, layer);
+            }",
+        ))
+    }
+}
+
+/// Samples a 3D texture at a depth.
+#[derive(AsBindGroup)]
+struct VolumeSlice {
+    #[binding(0, fragment)]
+    texture: TextureView3d,
+    #[binding(1, fragment)]
+    sampler: Sampler,
+    #[binding(2, fragment)]
+    depth: UniformBuffer<f32>,
+}
+
+impl AsMaterial for VolumeSlice {
+    fn fragment_shader_source() -> Option<ShaderSource> {
+        Some(ShaderSource::new(
+            "#import tbn::vertex_output
+
+            @group(2) @binding(0) var texture: texture_3d<f32>;
+            @group(2) @binding(1) var texture_sampler: sampler;
+            @group(2) @binding(2) var<uniform> depth: f32;
+
+            @fragment
+            fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+                return textureSample(texture, texture_sampler, vec3<f32>(vertex.uv, depth));
+            }",
+        ))
+    }
+}
+
+fn sampler(filter: wgpu::FilterMode) -> Sampler {
+    Sampler::create(context(), wgpu::AddressMode::ClampToEdge, filter, filter)
+}
+
+/// Renders a quad covering the center of the surface with `material`, and returns the color of
+/// the pixel in the center.
+fn render(material: &impl AsMaterial) -> [u8; 4] {
+    let surface = create_surface();
+    let mut scene = create_scene(&surface);
+    add_object(
+        &mut scene,
+        &create_camera(),
+        quad_mesh(),
+        context().create_material(material),
+        Matrix4::from_translation(vec3(-0.5, -0.5, 0.0)),
+    );
+    scene.render(context(), &surface.view(), &RenderPassOptions::default());
+    let pixels = surface.read_pixels(context()).unwrap();
+    let index = ((SIZE.y / 2 * SIZE.x + SIZE.x / 2) * 4) as usize;
+    pixels.bytes()[index..index + 4].try_into().unwrap()
+}
+
+/// Number of layers that arrays GL can't create as 2D arrays are padded with.
+fn padding_layer_count() -> u32 {
+    match context().adapter_info().backend {
+        wgpu::Backend::Gl => 1,
+        _ => 0,
+    }
+}
+
+/// The color of the center of each layer of `texture`.
+fn layer_colors(texture: &Texture2dArray, filter: wgpu::FilterMode) -> Vec<[u8; 4]> {
+    (0..texture.layer_count())
+        .map(|layer| {
+            render(&ArrayLayer {
+                texture: texture.view(Default::default()),
+                sampler: sampler(filter),
+                layer: UniformBuffer::create_init(context().wgpu_device(), layer),
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn texture_2d_array_layers() {
+    let layers = [RED, GREEN, BLUE].map(|color| color.repeat(4));
+    let texture = Texture2dArray::create_init(
+        context(),
+        vec2(2, 2),
+        TextureFormat::Rgba8Unorm,
+        &layers.each_ref().map(Vec::as_slice),
+    );
+    assert_eq!(texture.size(), vec2(2, 2));
+    assert_eq!(texture.layer_count(), 3);
+    assert_eq!(texture.view(Default::default()).layer_count(), 3);
+    assert_eq!(
+        layer_colors(&texture, wgpu::FilterMode::Linear),
+        [RED, GREEN, BLUE],
+    );
+
+    texture.write_layer(context().wgpu_queue(), 1, &YELLOW.repeat(4));
+    assert_eq!(
+        layer_colors(&texture, wgpu::FilterMode::Linear),
+        [RED, YELLOW, BLUE],
+    );
+}
+
+#[test]
+fn single_layer() {
+    let texture = Texture2dArray::create_init(
+        context(),
+        vec2(2, 2),
+        TextureFormat::Rgba8Unorm,
+        &[&GREEN.repeat(4)],
+    );
+    assert_eq!(texture.layer_count(), 1);
+    assert_eq!(
+        texture.wgpu_texture().depth_or_array_layers(),
+        1 + padding_layer_count(),
+    );
+    assert_eq!(texture.view(Default::default()).layer_count(), 1);
+    assert_eq!(layer_colors(&texture, wgpu::FilterMode::Nearest), [GREEN]);
+
+    texture.write_layer(context().wgpu_queue(), 0, &BLUE.repeat(4));
+    assert_eq!(layer_colors(&texture, wgpu::FilterMode::Nearest), [BLUE]);
+}
+
+#[test]
+fn six_square_layers() {
+    let colors = [RED, GREEN, BLUE, YELLOW, MAGENTA, CYAN];
+    let layers = colors.map(|color| color.repeat(4));
+    let texture = Texture2dArray::create_init(
+        context(),
+        vec2(2, 2),
+        TextureFormat::Rgba8Unorm,
+        &layers.each_ref().map(Vec::as_slice),
+    );
+    assert_eq!(texture.layer_count(), 6);
+    assert_eq!(
+        texture.wgpu_texture().depth_or_array_layers(),
+        6 + padding_layer_count(),
+    );
+    assert_eq!(texture.view(Default::default()).layer_count(), 6);
+    assert_eq!(layer_colors(&texture, wgpu::FilterMode::Nearest), colors);
+
+    let texture = Texture2dArray::create(
+        context(),
+        vec2(2, 2),
+        12,
+        TextureFormat::Rgba8Unorm,
+        wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+    );
+    for (layer, color) in colors.iter().chain(&colors).enumerate() {
+        texture.write_layer(context().wgpu_queue(), layer as u32, &color.repeat(4));
+    }
+    assert_eq!(
+        layer_colors(&texture, wgpu::FilterMode::Nearest),
+        [colors, colors].concat(),
+    );
+}
+
+#[test]
+#[should_panic = "layer 1 has 12 bytes of data instead of 16"]
+fn create_init_with_short_layer() {
+    Texture2dArray::create_init(
+        context(),
+        vec2(2, 2),
+        TextureFormat::Rgba8Unorm,
+        &[&RED.repeat(4), &RED.repeat(3)],
+    );
+}
+
+#[test]
+#[should_panic = "layer 0 has 20 bytes of data instead of 16"]
+fn write_layer_with_long_data() {
+    let texture = Texture2dArray::create(
+        context(),
+        vec2(2, 2),
+        2,
+        TextureFormat::Rgba8Unorm,
+        wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+    );
+    texture.write_layer(context().wgpu_queue(), 0, &RED.repeat(5));
+}
+
+#[test]
+#[should_panic = "3D texture has 28 bytes of data instead of 32"]
+fn create_init_3d_with_short_data() {
+    Texture3d::create_init(
+        context(),
+        vec3(2, 2, 2),
+        TextureFormat::Rgba8Unorm,
+        &RED.repeat(7),
+    );
+}
+
+#[test]
+#[should_panic = "layer 1 has 4 bytes of data instead of 16"]
+fn write_slice_with_short_data() {
+    let texture = Texture3d::create_init(
+        context(),
+        vec3(2, 2, 2),
+        TextureFormat::Rgba8Unorm,
+        &RED.repeat(8),
+    );
+    texture.write_slice(context().wgpu_queue(), 1, &RED);
+}
+
+#[test]
+fn render_into_layer() {
+    let size = vec2(8, 8);
+    let usage = wgpu::TextureUsages::COPY_DST
+        | wgpu::TextureUsages::TEXTURE_BINDING
+        | wgpu::TextureUsages::RENDER_ATTACHMENT;
+    let texture = Texture2dArray::create(context(), size, 2, TextureFormat::Rgba8Unorm, usage);
+    for layer in 0..2 {
+        texture.write_layer(context().wgpu_queue(), layer, &RED.repeat(64));
+    }
+
+    let layer_view = texture.layer_view(Default::default(), 1);
+    assert_eq!(layer_view.size(), size);
+    let depth_stencil_texture = DepthStencilTexture2d::create(
+        context().wgpu_device(),
+        size,
+        DepthStencilTextureFormat::Depth32Float,
+        wgpu::TextureUsages::RENDER_ATTACHMENT,
+    );
+    let mut scene = Scene::new(
+        context().wgpu_device(),
+        texture.format(),
+        depth_stencil_texture.format(),
+    );
+    let material = context().create_material(&materials::UniformFill::create(
+        context(),
+        Rgba::new(0.0, 0.0, 1.0, 1.0),
+    ));
+    // Covers the whole layer.
+    add_object(
+        &mut scene,
+        &create_camera(),
+        quad_mesh(),
+        material,
+        Matrix4::from_scale(10.0) * Matrix4::from_translation(vec3(-0.5, -0.5, 0.0)),
+    );
+    let surface_view = SurfaceView::new(
+        layer_view,
+        depth_stencil_texture.view(wgpu::TextureSampleType::Depth),
+    );
+    scene.render(context(), &surface_view, &RenderPassOptions::default());
+
+    assert_eq!(
+        layer_colors(&texture, wgpu::FilterMode::Nearest),
+        [RED, BLUE],
+    );
+}
+
+#[test]
+#[should_panic = "out of range"]
+fn layer_view_out_of_range() {
+    let texture = Texture2dArray::create(
+        context(),
+        vec2(2, 2),
+        2,
+        TextureFormat::Rgba8Unorm,
+        wgpu::TextureUsages::TEXTURE_BINDING,
+    );
+    texture.layer_view(Default::default(), 2);
+}
+
+#[test]
+fn texture_3d_slices() {
+    assert_eq!(Texture3d::max_mip_level_count(vec3(8, 8, 8)), 4);
+    assert_eq!(Texture3d::max_mip_level_count(vec3(8, 2, 1)), 4);
+
+    let slices = [RED, GREEN, BLUE, YELLOW].map(|color| color.repeat(4));
+    let texture = Texture3d::create_init(
+        context(),
+        vec3(2, 2, 4),
+        TextureFormat::Rgba8Unorm,
+        &slices.concat(),
+    );
+    assert_eq!(texture.size(), vec3(2, 2, 4));
+    assert_eq!(texture.mip_level_size(1), vec3(1, 1, 2));
+    let view = texture.view(Default::default());
+    assert_eq!(view.size(), vec3(2, 2, 4));
+
+    let color_at = |depth: f32, filter| {
+        render(&VolumeSlice {
+            texture: view.clone(),
+            sampler: sampler(filter),
+            depth: UniformBuffer::create_init(context().wgpu_device(), depth),
+        })
+    };
+    for (i, color) in [RED, GREEN, BLUE, YELLOW].into_iter().enumerate() {
+        let depth = (i as f32 + 0.5) / 4.0;
+        assert_eq!(color_at(depth, wgpu::FilterMode::Nearest), color);
+    }
+    // Halfway between the green and the blue slice.
+    let blended = color_at(0.5, wgpu::FilterMode::Linear);
+    for (actual, expected) in blended.into_iter().zip([0, 128, 128, 255]) {
+        assert!(actual.abs_diff(expected) <= 1, "{blended:?}");
+    }
+
+    texture.write_slice(context().wgpu_queue(), 3, &RED.repeat(4));
+    assert_eq!(color_at(0.875, wgpu::FilterMode::Nearest), RED);
+}